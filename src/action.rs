@@ -23,6 +23,10 @@ use serde::{
 use strum::Display;
 use crate::game::body::Body;
 use crate::game::system::System;
+use crate::game::galaxy::SystemId;
+use crate::game::order::Order;
+use crate::game::state::GameView;
+use crate::mode::Mode;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Display, Deserialize)]
 pub enum Action {
@@ -37,4 +41,19 @@ pub enum Action {
   Help,
   MoveSystemTreeSelectionUp,
   MoveSystemTreeSelectionDown,
+  SwitchMode(Mode),
+  ShowSystem(SystemId),
+  ShowPreviousSystem,
+  ShowNextSystem,
+  EndTurn,
+  PauseGame,
+  ResumeGame,
+  TogglePause,
+  SetGameSpeed(u32),
+  IssueOrder(Order),
+  /// Keys go to the focused component as text until [`Action::StopTextInput`], ignoring bindings
+  StartTextInput,
+  StopTextInput,
+  #[serde(skip)]
+  GameStateUpdated(GameView),
 }
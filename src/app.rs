@@ -26,9 +26,24 @@ use crate::{
     home::Home,
     fps::FpsCounter,
     system_tree::SystemTree,
+    status_bar::StatusBar,
+    empire_overview::EmpireOverview,
+    galaxy_map::GalaxyMap,
+    tech_tree::TechTree,
+    ship_designer::ShipDesigner,
+    diplomacy_screen::DiplomacyScreen,
+    statistics_screen::StatisticsScreen,
+    summary::Summary,
+    report_screen::ReportScreen,
+    event_popup::EventPopup,
     Component
   },
   config::Config,
+  game::{
+    clock::{self, TimeMode},
+    state::{GameState, Setup},
+    turn,
+  },
   mode::Mode,
   tui,
 };
@@ -43,26 +58,67 @@ pub struct App {
   pub should_suspend: bool,
   pub mode: Mode,
   pub last_tick_key_events: Vec<KeyEvent>,
+  pub game: GameState,
+  /// Whether a component takes keys as text, so that key bindings are not applied
+  pub text_input: bool,
 }
 
 impl App {
-  pub fn new(tick_rate: f64, frame_rate: f64) -> Result<Self> {
+  pub fn new(tick_rate: f64, frame_rate: f64, seed: u64, real_time: bool, setup: &Setup) -> Result<Self> {
     let fps = FpsCounter::default();
     let system_tree = SystemTree::new();
+    let status_bar = StatusBar::new();
+    let empire_overview = EmpireOverview::new();
+    let galaxy_map = GalaxyMap::new();
+    let tech_tree = TechTree::new();
+    let ship_designer = ShipDesigner::new();
+    let diplomacy_screen = DiplomacyScreen::new();
+    let statistics_screen = StatisticsScreen::new();
+    let summary = Summary::new();
+    let report_screen = ReportScreen::new();
+    let event_popup = EventPopup::new();
     let config = Config::new()?;
     let mode = Mode::Home;
+    let time_mode = if real_time { TimeMode::RealTime } else { TimeMode::Turns };
+    let game = GameState::with_setup(seed, time_mode, setup);
+    log::info!("Starting a game with seed {seed}");
     Ok(Self {
       tick_rate,
       frame_rate,
-      components: vec![Box::new(system_tree), Box::new(fps)],
+      components: vec![
+        Box::new(system_tree),
+        Box::new(empire_overview),
+        Box::new(galaxy_map),
+        Box::new(tech_tree),
+        Box::new(ship_designer),
+        Box::new(diplomacy_screen),
+        Box::new(statistics_screen),
+        Box::new(summary),
+        Box::new(report_screen),
+        Box::new(status_bar),
+        Box::new(fps),
+        Box::new(event_popup),
+      ],
       should_quit: false,
       should_suspend: false,
       config,
       mode,
       last_tick_key_events: Vec::new(),
+      game,
+      text_input: false,
     })
   }
 
+  /// Action bound to a key sequence in the current mode. Bindings of [`Mode::Home`] apply in
+  /// every mode unless the mode binds the same keys to something else
+  fn keybinding(&self, keys: &[KeyEvent]) -> Option<Action> {
+    [self.mode, Mode::Home]
+      .iter()
+      .filter_map(|mode| self.config.keybindings.get(mode))
+      .find_map(|keymap| keymap.get(keys))
+      .cloned()
+  }
+
   pub async fn run(&mut self) -> Result<()> {
     let (action_tx, mut action_rx) = mpsc::unbounded_channel();
 
@@ -82,34 +138,39 @@ impl App {
       component.init(tui.size()?)?;
     }
 
+    action_tx.send(Action::GameStateUpdated(self.game.view()))?;
+
     loop {
       if let Some(e) = tui.next().await {
+        let modal = self.components.iter().position(|c| c.is_modal());
         match e {
           tui::Event::Quit => action_tx.send(Action::Quit)?,
           tui::Event::Tick => action_tx.send(Action::Tick)?,
           tui::Event::Render => action_tx.send(Action::Render)?,
           tui::Event::Resize(x, y) => action_tx.send(Action::Resize(x, y))?,
+          tui::Event::Key(_) if self.text_input || modal.is_some() => {},
           tui::Event::Key(key) => {
-            if let Some(keymap) = self.config.keybindings.get(&self.mode) {
-              if let Some(action) = keymap.get(&vec![key]) {
-                log::info!("Got action: {action:?}");
-                action_tx.send(action.clone())?;
-              } else {
-                // If the key was not handled as a single key action,
-                // then consider it for multi-key combinations.
-                self.last_tick_key_events.push(key);
+            if let Some(action) = self.keybinding(&[key]) {
+              log::info!("Got action: {action:?}");
+              action_tx.send(action)?;
+            } else {
+              // If the key was not handled as a single key action,
+              // then consider it for multi-key combinations.
+              self.last_tick_key_events.push(key);
 
-                // Check for multi-key combinations
-                if let Some(action) = keymap.get(&self.last_tick_key_events) {
-                  log::info!("Got action: {action:?}");
-                  action_tx.send(action.clone())?;
-                }
+              // Check for multi-key combinations
+              if let Some(action) = self.keybinding(&self.last_tick_key_events) {
+                log::info!("Got action: {action:?}");
+                action_tx.send(action)?;
               }
-            };
+            }
           },
           _ => {},
         }
-        for component in self.components.iter_mut() {
+        for (i, component) in self.components.iter_mut().enumerate() {
+          if matches!(e, tui::Event::Key(_)) && modal.is_some_and(|m| m != i) {
+            continue;
+          }
           if let Some(action) = component.handle_events(Some(e.clone()))? {
             action_tx.send(action)?;
           }
@@ -123,10 +184,46 @@ impl App {
         match action {
           Action::Tick => {
             self.last_tick_key_events.drain(..);
+            let date = self.game.date;
+            let paused = self.game.clock.paused;
+            clock::tick(&mut self.game, 1.0 / self.tick_rate, self.config.auto_pause());
+            if self.game.date != date || self.game.clock.paused != paused {
+              action_tx.send(Action::GameStateUpdated(self.game.view()))?;
+              if self.game.outcome.is_some() {
+                action_tx.send(Action::SwitchMode(Mode::Summary))?;
+              }
+            }
           },
           Action::Quit => self.should_quit = true,
           Action::Suspend => self.should_suspend = true,
           Action::Resume => self.should_suspend = false,
+          Action::SwitchMode(mode) => self.mode = mode,
+          Action::StartTextInput => self.text_input = true,
+          Action::StopTextInput => self.text_input = false,
+          Action::EndTurn if self.game.clock.mode == TimeMode::Turns && self.game.outcome.is_none() => {
+            let report = turn::end_turn(&mut self.game);
+            log::info!("Turn {} processed with {} report entries", report.turn, report.entries.len());
+            action_tx.send(Action::GameStateUpdated(self.game.view()))?;
+            if self.game.outcome.is_some() {
+              action_tx.send(Action::SwitchMode(Mode::Summary))?;
+            }
+          },
+          Action::PauseGame | Action::ResumeGame | Action::TogglePause => {
+            self.game.clock.paused = match action {
+              Action::PauseGame => true,
+              Action::ResumeGame => false,
+              _ => !self.game.clock.paused,
+            };
+            action_tx.send(Action::GameStateUpdated(self.game.view()))?;
+          },
+          Action::SetGameSpeed(speed) => {
+            self.game.clock.set_speed(speed);
+            action_tx.send(Action::GameStateUpdated(self.game.view()))?;
+          },
+          Action::IssueOrder(ref order) => {
+            self.game.issue_order(self.game.player, order.clone());
+            action_tx.send(Action::GameStateUpdated(self.game.view()))?;
+          },
           Action::Resize(w, h) => {
             tui.resize(Rect::new(0, 0, w, h))?;
             tui.draw(|f| {
@@ -18,6 +18,9 @@ use std::path::PathBuf;
 
 use clap::Parser;
 
+use crate::game::ai::Difficulty;
+use crate::game::state::Setup;
+use crate::game::victory::{Victory, VictoryConditions};
 use crate::utils::version;
 
 #[derive(Parser, Debug)]
@@ -34,4 +37,56 @@ pub struct Cli {
     default_value_t = 4.0
   )]
   pub frame_rate: f64,
+
+  #[arg(short, long, value_name = "INT", help = "Seed of the generated galaxy, random if not given")]
+  pub seed: Option<u64>,
+
+  #[arg(short, long, help = "Play in real time with pause instead of turns")]
+  pub real_time: bool,
+
+  #[arg(short, long, value_name = "INT", help = "Number of empires the computer plays", default_value_t = 3)]
+  pub opponents: usize,
+
+  #[arg(short, long, value_name = "LEVEL", help = "Difficulty of the computer empires: easy, normal or hard", default_value_t = Difficulty::Normal)]
+  pub difficulty: Difficulty,
+
+  #[arg(long, value_name = "ID", help = "Personality of every computer empire, random if not given")]
+  pub personality: Option<String>,
+
+  #[arg(long, help = "Let the computer play every empire without a user interface and print the standings")]
+  pub headless: bool,
+
+  #[arg(long, value_name = "INT", help = "Turns a headless game lasts", default_value_t = 100)]
+  pub turns: u32,
+
+  #[arg(
+    long,
+    value_name = "LIST",
+    value_delimiter = ',',
+    help = "Ways to win the game: domination, colonization, technology and score",
+    default_values_t = Victory::ALL
+  )]
+  pub victories: Vec<Victory>,
+
+  #[arg(long, value_name = "PERCENT", help = "Share of the systems an empire has to own for a colonization victory", default_value_t = 50.0)]
+  pub colonization: f32,
+
+  #[arg(long, value_name = "INT", help = "Turn after which the best score wins", default_value_t = 300)]
+  pub turn_limit: u32,
+}
+
+impl Cli {
+  /// How the game is set up, from the arguments
+  pub fn setup(&self) -> Setup {
+    Setup {
+      opponents: self.opponents,
+      difficulty: self.difficulty,
+      personality: self.personality.clone(),
+      victory: VictoryConditions {
+        enabled: self.victories.clone(),
+        colonization: self.colonization,
+        turn_limit: self.turn_limit,
+      },
+    }
+  }
 }
@@ -28,6 +28,16 @@ use crate::{
 pub mod fps;
 pub mod home;
 pub mod system_tree;
+pub mod status_bar;
+pub mod empire_overview;
+pub mod galaxy_map;
+pub mod tech_tree;
+pub mod ship_designer;
+pub mod diplomacy_screen;
+pub mod statistics_screen;
+pub mod summary;
+pub mod event_popup;
+pub mod report_screen;
 
 /// `Component` is a trait that represents a visual and interactive element of the user interface.
 /// Implementors of this trait can be registered with the main application loop and will be able to receive events,
@@ -88,6 +98,15 @@ pub trait Component {
     };
     Ok(r)
   }
+  /// Whether the component is a modal dialog right now, which takes every key while it is shown.
+  /// Key bindings and the other components get no keys meanwhile.
+  ///
+  /// # Returns
+  ///
+  /// * `bool` - Whether the component is modal.
+  fn is_modal(&self) -> bool {
+    false
+  }
   /// Handle key events and produce actions if necessary.
   ///
   /// # Arguments
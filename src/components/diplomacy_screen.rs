@@ -0,0 +1,326 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::prelude::{Line, Span};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use crate::action::Action;
+use crate::components::Component;
+use crate::components::empire_overview::species_card;
+use crate::components::status_bar::StatusBar;
+use crate::game::diplomacy::{acceptance, check_proposal, opinion, opinion_breakdown, Proposal, Stance};
+use crate::game::empire::EmpireId;
+use crate::game::order::Order;
+use crate::game::state::GameView;
+use crate::mode::Mode;
+use crate::tui::Frame;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Panel {
+    Empires,
+    Proposals,
+}
+
+/// Relations of the player with the empires it met: stances, opinions, treaties, proposals and
+/// what happened between them
+pub struct DiplomacyScreen {
+    mode: Mode,
+    game: Option<GameView>,
+    focus: Panel,
+    empires_state: ListState,
+    proposals_state: ListState,
+}
+
+impl Default for DiplomacyScreen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn stance_color(stance: Stance) -> Color {
+    match stance {
+        Stance::War => { Color::LightRed }
+        Stance::Peace => { Color::Gray }
+        Stance::NonAggression => { Color::LightYellow }
+        Stance::Alliance | Stance::Federation => { Color::LightGreen }
+    }
+}
+
+fn amount_color(amount: f32) -> Color {
+    if amount > 0.0 {
+        Color::LightGreen
+    } else if amount < 0.0 {
+        Color::LightRed
+    } else {
+        Color::Gray
+    }
+}
+
+impl DiplomacyScreen {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Home,
+            game: None,
+            focus: Panel::Empires,
+            empires_state: ListState::default().with_selected(Some(0)),
+            proposals_state: ListState::default().with_selected(Some(0)),
+        }
+    }
+
+    /// Empires the player met, in the order of their ids
+    fn contacts(&self) -> Vec<EmpireId> {
+        let Some(game) = &self.game else { return Vec::new() };
+        let mut res = game.player_empire().contacts.clone();
+        res.sort();
+        res
+    }
+
+    fn selected_empire(&self) -> Option<EmpireId> {
+        self.contacts().get(self.empires_state.selected()?).copied()
+    }
+
+    fn selected_proposal(&self) -> Option<Proposal> {
+        Proposal::ALL.get(self.proposals_state.selected()?).copied()
+    }
+
+    fn move_selection(&mut self, up: bool) {
+        let (len, state) = match self.focus {
+            Panel::Empires => { (self.contacts().len(), &mut self.empires_state) }
+            Panel::Proposals => { (Proposal::ALL.len(), &mut self.proposals_state) }
+        };
+        if len == 0 {
+            return;
+        }
+        let selected = state.selected().unwrap_or(0).min(len - 1);
+        state.select(Some(if up { (selected + len - 1) % len } else { (selected + 1) % len }));
+    }
+
+    fn block(&self, title: String, panel: Panel) -> Block<'static> {
+        let block = Block::default().title(title).borders(Borders::ALL);
+        if self.focus == panel {
+            block.border_style(Style::default().fg(Color::LightCyan))
+        } else {
+            block
+        }
+    }
+
+    fn relation(game: &GameView, other: EmpireId) -> Vec<Line<'static>> {
+        let player = game.player;
+        let relation = game.diplomacy.relation(player, other);
+        let mut res = vec![
+            Line::from(vec![
+                Span::from("Stance: "),
+                Span::from(relation.stance.to_string()).fg(stance_color(relation.stance)).bold(),
+                Span::from(if relation.since > 0 { format!(" since turn {}", relation.since) } else { String::new() }),
+            ]),
+            Line::from(format!("Treaties: {}", if relation.stance == Stance::Federation {
+                "all, as members of a federation".to_owned()
+            } else if relation.treaties.is_empty() {
+                "none".to_owned()
+            } else {
+                relation.treaties.iter().map(|t| t.to_string()).collect::<Vec<String>>().join(", ")
+            })),
+        ];
+        let total = opinion(game, other, player);
+        res.push(Line::from(vec![
+            Span::from("Their opinion of us: "),
+            Span::from(format!("{total:+.0}")).fg(amount_color(total)).bold(),
+        ]));
+        for (reason, value) in opinion_breakdown(game, other, player) {
+            res.push(Line::from(vec![
+                Span::from(format!("  {reason:<24}")),
+                Span::from(format!("{value:+.0}")).fg(amount_color(value)),
+            ]));
+        }
+        let pending: Vec<String> = game.diplomacy.pending.iter()
+            .filter(|p| p.from == other && p.to == player)
+            .map(|p| p.proposal.describe())
+            .collect();
+        if let Some(proposal) = pending.first() {
+            res.push(Line::from(""));
+            res.push(Line::from(Span::from(format!("They propose {proposal} - a accepts, r rejects")).fg(Color::LightYellow).bold()));
+        }
+        res
+    }
+
+    /// Whether the other empire would take up a proposal, and why
+    fn prospects(game: &GameView, other: EmpireId, proposal: &Proposal) -> Vec<Line<'static>> {
+        if let Err(reason) = check_proposal(game, game.player, other, proposal) {
+            return vec![Line::styled(reason, Style::default().fg(Color::DarkGray))];
+        }
+        if proposal.is_unilateral() {
+            return vec![Line::from("Needs no agreement")];
+        }
+        if game.empires[other].ai.is_none() {
+            return vec![Line::from("Awaits their answer")];
+        }
+        let reasons = acceptance(game, other, game.player, proposal);
+        let total: f32 = reasons.iter().map(|(_, value)| value).sum();
+        let mut res = vec![if total > 0.0 {
+            Line::styled(format!("They would accept ({total:+.0})"), Style::default().fg(Color::LightGreen))
+        } else {
+            Line::styled(format!("They would refuse ({total:+.0})"), Style::default().fg(Color::LightRed))
+        }];
+        res.extend(reasons.into_iter().map(|(reason, value)| Line::from(vec![
+            Span::from(format!("  {reason:<24}")),
+            Span::from(format!("{value:+.0}")).fg(amount_color(value)),
+        ])));
+        res
+    }
+}
+
+impl Component for DiplomacyScreen {
+    fn handle_key_events(&mut self, key: KeyEvent) -> color_eyre::Result<Option<Action>> {
+        if self.mode != Mode::Diplomacy {
+            return Ok(None);
+        }
+        let action = match key.code {
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Panel::Empires => { Panel::Proposals }
+                    Panel::Proposals => { Panel::Empires }
+                };
+                None
+            }
+            KeyCode::Up => { self.move_selection(true); None }
+            KeyCode::Down => { self.move_selection(false); None }
+            KeyCode::Enter => {
+                self.selected_empire()
+                    .zip(self.selected_proposal())
+                    .map(|(to, proposal)| Action::IssueOrder(Order::Propose { to, proposal }))
+            }
+            KeyCode::Char(c @ ('a' | 'r')) => {
+                self.selected_empire().map(|from| Action::IssueOrder(Order::AnswerProposal { from, accept: c == 'a' }))
+            }
+            _ => { None }
+        };
+        Ok(action)
+    }
+
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        match action {
+            Action::GameStateUpdated(game) => { self.game = Some(game) }
+            Action::SwitchMode(mode) => { self.mode = mode }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> color_eyre::Result<()> {
+        if self.mode != Mode::Diplomacy {
+            return Ok(());
+        }
+        let Some(game) = self.game.clone() else { return Ok(()) };
+        let player = game.player;
+
+        let rects = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![
+                Constraint::Length(40),
+                Constraint::Min(0),
+            ])
+            .split(StatusBar::remaining_area(area));
+
+        let contacts = self.contacts();
+        let items: Vec<ListItem> = contacts.iter()
+            .map(|e| {
+                let stance = game.diplomacy.stance(player, *e);
+                let waiting = game.diplomacy.pending.iter().any(|p| p.from == *e && p.to == player);
+                ListItem::new(Line::from(vec![
+                    Span::from(format!("{:<22}", game.empires[*e].name)).fg(game.empires[*e].color),
+                    Span::from(stance.to_string()).fg(stance_color(stance)),
+                    Span::from(if waiting { " !" } else { "" }).fg(Color::LightYellow).bold(),
+                ]))
+            })
+            .collect();
+        let left = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![
+                Constraint::Length(contacts.len().max(1) as u16 + 2),
+                Constraint::Min(0),
+            ])
+            .split(rects[0]);
+        if items.is_empty() {
+            let par = Paragraph::new("No other empire met yet")
+                .block(self.block("Empires".to_owned(), Panel::Empires));
+            f.render_widget(par, left[0]);
+        } else {
+            let list = List::new(items)
+                .block(self.block("Empires".to_owned(), Panel::Empires))
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            f.render_stateful_widget(list, left[0], &mut self.empires_state);
+        }
+        let Some(other) = self.selected_empire() else { return Ok(()) };
+
+        let proposals: Vec<ListItem> = Proposal::ALL.iter()
+            .map(|p| {
+                let style = if check_proposal(&game, player, other, p).is_ok() {
+                    Style::default()
+                } else {
+                    Style::default().fg(Color::DarkGray)
+                };
+                ListItem::new(Line::styled(p.label(), style))
+            })
+            .collect();
+        let list = List::new(proposals)
+            .block(self.block("Proposals - Enter sends".to_owned(), Panel::Proposals))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_stateful_widget(list, left[1], &mut self.proposals_state);
+
+        let right = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![
+                Constraint::Percentage(40),
+                Constraint::Percentage(25),
+                Constraint::Min(0),
+            ])
+            .split(rects[1]);
+        let top = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![
+                Constraint::Min(0),
+                Constraint::Length(60),
+            ])
+            .split(right[0]);
+        let empire = &game.empires[other];
+        let relation = Paragraph::new(Self::relation(&game, other))
+            .block(Block::default().title(empire.name.clone()).borders(Borders::ALL).fg(empire.color))
+            .wrap(Wrap { trim: true });
+        f.render_widget(relation, top[0]);
+        let species = Paragraph::new(species_card(&empire.species, empire.color))
+            .block(Block::default().title("Species").borders(Borders::ALL));
+        f.render_widget(species, top[1]);
+
+        if let Some(proposal) = self.selected_proposal() {
+            let prospects = Paragraph::new(Self::prospects(&game, other, &proposal))
+                .block(Block::default().title(proposal.label()).borders(Borders::ALL))
+                .wrap(Wrap { trim: true });
+            f.render_widget(prospects, right[1]);
+        }
+
+        let history: Vec<Line> = game.diplomacy.history_of(player, other)
+            .rev()
+            .map(|e| Line::from(format!("Turn {}: {}", e.turn, e.text)))
+            .collect();
+        let par = Paragraph::new(history)
+            .block(Block::default().title("History").borders(Borders::ALL))
+            .wrap(Wrap { trim: true });
+        f.render_widget(par, right[2]);
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::prelude::{Line, Span};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Sparkline, Table, TableState};
+use crate::action::Action;
+use crate::components::Component;
+use crate::components::status_bar::StatusBar;
+use crate::game::resources::Resource;
+use crate::game::species::Species;
+use crate::game::state::GameView;
+use crate::mode::Mode;
+use crate::tui::Frame;
+
+/// Stockpile, income and expenses of the player's empire
+pub struct EmpireOverview {
+    mode: Mode,
+    game: Option<GameView>,
+    selected_row: usize,
+    table_state: TableState,
+}
+
+impl Default for EmpireOverview {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EmpireOverview {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Home,
+            game: None,
+            selected_row: 0,
+            table_state: TableState::default(),
+        }
+    }
+
+    fn selected_resource(&self) -> Resource {
+        Resource::ALL[self.selected_row]
+    }
+}
+
+/// Formats an amount with an explicit sign
+fn signed(amount: f32) -> String {
+    // Avoid printing negative zero
+    let amount = if amount == 0.0 { 0.0 } else { amount };
+    format!("{amount:+.1}")
+}
+
+/// Portrait of a species in the colour of its empire, with what sets the species apart beside it
+pub fn species_card(species: &Species, color: Color) -> Vec<Line<'static>> {
+    let info = species.make_info();
+    (0..info.len().max(species.portrait.len()))
+        .map(|i| {
+            let portrait = species.portrait.get(i).map_or(String::new(), |l| l.clone());
+            let width = species.portrait.first().map_or(0, |l| l.chars().count());
+            Line::from(vec![
+                Span::from(format!("{portrait:<width$}  ")).fg(color),
+                Span::from(info.get(i).cloned().unwrap_or_default()),
+            ])
+        })
+        .collect()
+}
+
+fn amount_color(amount: f32) -> Color {
+    if amount > 0.0 {
+        Color::LightGreen
+    } else if amount < 0.0 {
+        Color::LightRed
+    } else {
+        Color::Gray
+    }
+}
+
+impl Component for EmpireOverview {
+    fn handle_key_events(&mut self, key: KeyEvent) -> color_eyre::Result<Option<Action>> {
+        if self.mode != Mode::Empire {
+            return Ok(None);
+        }
+        match key.code {
+            KeyCode::Up => {
+                self.selected_row = (self.selected_row + Resource::ALL.len() - 1) % Resource::ALL.len();
+            }
+            KeyCode::Down => {
+                self.selected_row = (self.selected_row + 1) % Resource::ALL.len();
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        match action {
+            Action::GameStateUpdated(game) => { self.game = Some(game) }
+            Action::SwitchMode(mode) => { self.mode = mode }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> color_eyre::Result<()> {
+        if self.mode != Mode::Empire {
+            return Ok(());
+        }
+        let Some(game) = &self.game else { return Ok(()) };
+        let empire = game.player_empire();
+
+        let rects = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![
+                Constraint::Length(3),
+                Constraint::Length(Resource::ALL.len() as u16 + 3),
+                Constraint::Length(7),
+                Constraint::Min(0),
+            ])
+            .split(StatusBar::remaining_area(area));
+
+        let summary = Paragraph::new(Line::from(vec![
+            Span::from(format!("Capital: {}", game.galaxy.systems[empire.capital].name)),
+            Span::from(format!("   Systems owned: {}", empire.systems.len())),
+            Span::from(format!("   Contacts: {}", empire.contacts.len())),
+            Span::from(format!("   Fleets: {}", game.fleets.iter().filter(|f| f.owner == empire.id).count())),
+            Span::from(format!("   Population: {}", game.galaxy.colonies()
+                .filter_map(|(_, b)| b.colony.as_ref().filter(|c| c.owner == empire.id))
+                .map(|c| c.population)
+                .sum::<u32>())),
+        ]))
+            .block(Block::default().title(empire.name.clone()).borders(Borders::ALL).fg(empire.color));
+        f.render_widget(summary, rects[0]);
+
+        let income = empire.ledger.income();
+        let expenses = empire.ledger.expenses();
+        let rows: Vec<Row> = Resource::ALL.iter()
+            .map(|r| {
+                let trend = empire.trend(*r);
+                let arrow = if trend > 0.0 { "▲" } else if trend < 0.0 { "▼" } else { "=" };
+                Row::new(vec![
+                    Line::from(r.to_string()),
+                    Line::from(format!("{:.1}", empire.stockpile.get(*r))),
+                    Line::styled(signed(income.get(*r)), Style::default().fg(Color::LightGreen)),
+                    Line::styled(signed(-expenses.get(*r)), Style::default().fg(Color::LightRed)),
+                    Line::styled(format!("{arrow} {}", signed(trend)), Style::default().fg(amount_color(trend))),
+                ])
+            })
+            .collect();
+        let table = Table::new(rows, [
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Min(10),
+        ])
+            .header(Row::new(vec!["Resource", "Stockpile", "Income", "Expenses", "Trend"]).bold())
+            .block(Block::default().title("Resources").borders(Borders::ALL))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let middle = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![
+                Constraint::Min(0),
+                Constraint::Length(60),
+            ])
+            .split(rects[1]);
+        self.table_state.select(Some(self.selected_row));
+        f.render_stateful_widget(table, middle[0], &mut self.table_state);
+        let species = Paragraph::new(species_card(&empire.species, empire.color))
+            .block(Block::default().title("Species").borders(Borders::ALL));
+        f.render_widget(species, middle[1]);
+
+        let resource = self.selected_resource();
+        let history: Vec<u64> = empire.history.iter().map(|h| h.get(resource).max(0.0) as u64).collect();
+        let sparkline = Sparkline::default()
+            .block(Block::default().title(format!("{resource} over the last {} turns", history.len())).borders(Borders::ALL))
+            .data(&history)
+            .style(Style::default().fg(Color::LightCyan));
+        f.render_widget(sparkline, rects[2]);
+
+        let breakdown: Vec<Line> = empire.ledger.by_source().iter()
+            .filter(|(_, amounts)| !amounts.is_zero())
+            .map(|(source, amounts)| {
+                let mut spans = vec![Span::from(format!("{source:<24}"))];
+                for (r, amount) in amounts.iter().filter(|(_, a)| *a != 0.0) {
+                    spans.push(Span::from(format!("{r} {} ", signed(amount))).fg(amount_color(amount)));
+                }
+                Line::from(spans)
+            })
+            .collect();
+        let par = Paragraph::new(breakdown)
+            .block(Block::default().title("Breakdown by source").borders(Borders::ALL));
+        f.render_widget(par, rects[3]);
+        Ok(())
+    }
+}
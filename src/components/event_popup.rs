@@ -0,0 +1,144 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::prelude::{Line, Span};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::Text;
+use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap};
+use crate::action::Action;
+use crate::components::Component;
+use crate::game::narrative::Incident;
+use crate::game::order::Order;
+use crate::game::state::GameView;
+use crate::tui::Frame;
+
+/// Width of the popup in columns
+const WIDTH: u16 = 72;
+
+/// Popup that puts a narrative event to the player in every mode, and takes every key until one
+/// of its choices is made
+#[derive(Default)]
+pub struct EventPopup {
+    game: Option<GameView>,
+    /// The incident shown, the oldest of the player waiting for a choice
+    incident: Option<Incident>,
+    choices: ListState,
+}
+
+impl EventPopup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn choose(&self) -> Option<Action> {
+        let incident = self.incident.as_ref()?;
+        Some(Action::IssueOrder(Order::ResolveEvent {
+            event: incident.event.clone(),
+            choice: self.choices.selected()?,
+        }))
+    }
+}
+
+impl Component for EventPopup {
+    fn is_modal(&self) -> bool {
+        self.incident.is_some()
+    }
+
+    fn handle_key_events(&mut self, key: KeyEvent) -> color_eyre::Result<Option<Action>> {
+        let Some(incident) = &self.incident else { return Ok(None) };
+        let count = incident.def().choices.len();
+        let selected = self.choices.selected().unwrap_or(0);
+        let action = match key.code {
+            KeyCode::Up => {
+                self.choices.select(Some((selected + count - 1) % count));
+                None
+            }
+            KeyCode::Down => {
+                self.choices.select(Some((selected + 1) % count));
+                None
+            }
+            KeyCode::Enter => { self.choose() }
+            _ => { None }
+        };
+        Ok(action)
+    }
+
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        if let Action::GameStateUpdated(game) = action {
+            let incident = game.narrative.pending_for(game.player).cloned();
+            if incident != self.incident {
+                self.incident = incident;
+                self.choices.select(Some(0));
+            }
+            self.game = Some(game);
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> color_eyre::Result<()> {
+        let (Some(game), Some(incident)) = (&self.game, &self.incident) else { return Ok(()) };
+        let def = incident.def();
+        let waiting = game.narrative.pending.iter().filter(|i| i.empire == game.player).count();
+
+        let text = incident.text(game);
+        let width = WIDTH.min(area.width);
+        let text_height = (text.chars().count() as u16).div_ceil(width.saturating_sub(4).max(1)) + 1;
+        let height = (text_height + def.choices.len() as u16 * 2 + 5).min(area.height);
+        let popup = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        f.render_widget(Clear, popup);
+
+        let title = if waiting > 1 {
+            format!("{} ({} more waiting)", def.title, waiting - 1)
+        } else {
+            def.title.clone()
+        };
+        let block = Block::default()
+            .title(title)
+            .title_bottom("Up and Down choose, Enter decides")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::LightYellow));
+        let inner = block.inner(popup);
+        f.render_widget(block, popup);
+
+        let rects = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![
+                Constraint::Length(text_height),
+                Constraint::Min(0),
+            ])
+            .margin(1)
+            .split(inner);
+        f.render_widget(Paragraph::new(text).wrap(Wrap { trim: true }), rects[0]);
+
+        let items: Vec<ListItem> = def.choices.iter()
+            .map(|c| ListItem::new(Text::from(vec![
+                Line::from(Span::from(c.label.clone()).bold()),
+                Line::styled(format!("  {}", c.describe()), Style::default().fg(Color::Gray)),
+            ])))
+            .collect();
+        let list = List::new(items)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_stateful_widget(list, rects[1], &mut self.choices);
+        Ok(())
+    }
+}
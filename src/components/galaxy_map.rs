@@ -0,0 +1,434 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::prelude::{Line, Span};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::symbols::Marker;
+use ratatui::widgets::canvas::{Canvas, Circle, Line as CanvasLine, Points};
+use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
+use crate::action::Action;
+use crate::components::Component;
+use crate::components::status_bar::StatusBar;
+use crate::game::combat::Battle;
+use crate::game::empire::EmpireId;
+use crate::game::fleet::{Fleet, FleetId};
+use crate::game::galaxy::SystemId;
+use crate::game::knowledge::KnowledgeLevel;
+use crate::game::order::Order;
+use crate::game::sensors::sensors;
+use crate::game::state::GameView;
+use crate::game::system::System;
+use crate::game::territory::{claim_cost, known_claims, owner_at, Claim};
+use crate::mode::Mode;
+use crate::tui::Frame;
+
+/// Margin around the systems on the map, in light years
+const MAP_MARGIN: f64 = 10.0;
+
+/// Map of every system and hyperlane of the galaxy
+pub struct GalaxyMap {
+    mode: Mode,
+    game: Option<GameView>,
+    selected: SystemId,
+    /// Index of the chosen fleet among the player's fleets
+    fleet: usize,
+    /// Whether the ranges of the player's sensors are drawn
+    show_sensors: bool,
+}
+
+impl Default for GalaxyMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GalaxyMap {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Home,
+            game: None,
+            selected: 0,
+            fleet: 0,
+            show_sensors: false,
+        }
+    }
+
+
+    fn player_fleets(game: &GameView) -> Vec<&Fleet> {
+        game.fleets.iter().filter(|f| f.owner == game.player).collect()
+    }
+
+    fn selected_fleet<'a>(&self, game: &'a GameView) -> Option<&'a Fleet> {
+        Self::player_fleets(game).get(self.fleet).copied()
+    }
+
+    /// Order for the chosen fleet, built from its id
+    fn fleet_order(&self, order: impl Fn(FleetId) -> Order) -> Option<Action> {
+        let fleet = self.selected_fleet(self.game.as_ref()?)?;
+        Some(Action::IssueOrder(order(fleet.id)))
+    }
+
+    fn fleet_position(game: &GameView, fleet: &Fleet) -> (f64, f64) {
+        let (x, y) = fleet.position(&game.galaxy);
+        (x as f64, y as f64)
+    }
+
+    fn fleet_info(game: &GameView, fleet: &Fleet) -> Vec<Line<'static>> {
+        let color = game.player_empire().color;
+        let mut res = vec![
+            Line::from(""),
+            Line::from(Span::from(format!("Fleet: {}", fleet.name)).bold().fg(color)),
+        ];
+        let location = &game.galaxy.systems[fleet.location].name;
+        res.push(Line::from(match (fleet.system(), fleet.route.first()) {
+            (None, Some(next)) => { format!("Between {location} and {}", game.galaxy.systems[*next].name) }
+            _ => { format!("At {location}") }
+        }));
+        res.push(Line::from(format!("Speed: {:.2} ly/day, {} ship(s)", fleet.speed(), fleet.ships.len())));
+        let (range, strength) = fleet.sensors();
+        res.push(Line::from(format!("Sensors: {range} ly, strength {strength}")));
+        if fleet.stealth() > 0.0 {
+            res.push(Line::from(format!("Stealth: {}", fleet.stealth())));
+        }
+        for waypoint in &fleet.waypoints {
+            let eta = fleet.eta(&game.galaxy, *waypoint).unwrap_or_default();
+            res.push(Line::from(format!("-> {} in {eta} days", game.galaxy.systems[*waypoint].name)));
+        }
+        res.push(Line::styled(
+            "f next fleet, m move, w add waypoint, x stop, o build outpost, s sensor ranges",
+            Style::default().fg(Color::DarkGray),
+        ));
+        res
+    }
+
+    fn system_count(&self) -> usize {
+        self.game.as_ref().map_or(0, |g| g.galaxy.systems.len())
+    }
+
+    fn set_game(&mut self, game: GameView) {
+        if self.game.is_none() {
+            self.selected = game.player_empire().capital;
+        }
+        self.game = Some(game);
+    }
+
+    /// Colour of the empire that has a colony in the system, if any
+    fn owner_color(game: &GameView, system: SystemId) -> Option<Color> {
+        let owner = game.player_empire().knowledge.system(system)?.known_system().owner()?;
+        Some(game.empires[owner].color)
+    }
+
+    /// Points along the borders of every empire, sampled at the given steps in light years
+    fn border_points(claims: &[Claim], x: [f64; 2], y: [f64; 2], step: (f64, f64)) -> Vec<(EmpireId, (f64, f64))> {
+        let (columns, rows) = (((x[1] - x[0]) / step.0) as usize, ((y[1] - y[0]) / step.1) as usize);
+        let point = |column: usize, row: usize| (x[0] + column as f64 * step.0, y[0] + row as f64 * step.1);
+        let owners: Vec<Vec<Option<EmpireId>>> = (0..=rows)
+            .map(|row| (0..=columns)
+                .map(|column| {
+                    let (x, y) = point(column, row);
+                    owner_at(claims, (x as f32, y as f32))
+                })
+                .collect())
+            .collect();
+        let mut res = Vec::new();
+        for row in 0..=rows {
+            for column in 0..=columns {
+                let Some(owner) = owners[row][column] else { continue };
+                let edge = [(-1, 0), (1, 0), (0, -1), (0, 1)].iter().any(|(dx, dy)| {
+                    let (c, r) = (column as i64 + dx, row as i64 + dy);
+                    c < 0 || r < 0 || c > columns as i64 || r > rows as i64
+                        || owners[r as usize][c as usize] != Some(owner)
+                });
+                if edge {
+                    res.push((owner, point(column, row)));
+                }
+            }
+        }
+        res
+    }
+
+    /// Whether the player knows of the system
+    fn is_known(game: &GameView, system: SystemId) -> bool {
+        game.player_empire().knowledge.system(system).is_some()
+    }
+
+    /// The next system the player knows of, going forwards or backwards through the galaxy
+    fn step_system(&self, forward: bool) -> SystemId {
+        let Some(game) = &self.game else { return self.selected };
+        let count = self.system_count();
+        (1..count)
+            .map(|i| if forward { (self.selected + i) % count } else { (self.selected + count - i) % count })
+            .find(|s| Self::is_known(game, *s))
+            .unwrap_or(self.selected)
+    }
+
+    fn system_info(&self, game: &GameView) -> Vec<Line<'static>> {
+        let knowledge = &game.player_empire().knowledge;
+        let Some(known) = knowledge.system(self.selected) else {
+            return vec![Line::from("Nothing is known about this system")];
+        };
+        let system = known.known_system();
+        let visible = knowledge.is_visible(self.selected);
+        let mut res = vec![
+            Line::from(format!("Star: {}", system.star.name)),
+            Line::from(format!("Knowledge: {}", known.level)),
+        ];
+        if !visible {
+            res.push(Line::styled(format!("Last seen on turn {}", known.last_seen), Style::default().fg(Color::DarkGray)));
+        }
+        if known.level >= KnowledgeLevel::Explored {
+            res.push(Line::from(format!("Bodies: {}", system.bodies.len())));
+        }
+        match (system.owner(), &system.outpost) {
+            (Some(owner), Some(outpost)) => {
+                let owner = &game.empires[owner];
+                res.push(Line::styled(format!("Outpost of {} since {}", owner.name, outpost.built), Style::default().fg(owner.color)));
+            }
+            (Some(owner), None) => {
+                let owner = &game.empires[owner];
+                res.push(Line::styled(format!("Owned by {}", owner.name), Style::default().fg(owner.color)));
+            }
+            (None, _) => {
+                let cost = claim_cost(game, game.player);
+                res.push(Line::from(format!("Unclaimed, an outpost costs {cost} influence")));
+            }
+        }
+
+        let (x, y) = game.galaxy.positions[self.selected];
+        res.push(Line::from(format!("Position: {x:.0}, {y:.0} ly")));
+        let lanes: Vec<String> = game.galaxy.neighbours(self.selected).iter()
+            .filter(|n| Self::is_known(game, **n))
+            .map(|n| format!("{} ({:.0} ly)", game.galaxy.systems[*n].name, game.galaxy.distance(self.selected, *n)))
+            .collect();
+        res.push(Line::from(format!("Hyperlanes: {}", lanes.join(", "))));
+
+        let colonies: Vec<_> = system.bodies.iter()
+            .filter_map(|b| b.colony.as_ref().map(|c| (b, c)))
+            .collect();
+        if !colonies.is_empty() {
+            res.push(Line::from(""));
+            res.push(Line::from(Span::from("Colonies:").bold()));
+            for (body, colony) in colonies {
+                let owner = &game.empires[colony.owner];
+                res.push(Line::styled(
+                    format!("{} - {} ({} pop)", body.name, owner.name, colony.population),
+                    Style::default().fg(owner.color),
+                ));
+            }
+        }
+
+        let fleets: Vec<_> = game.fleets.iter()
+            .filter(|f| f.system() == Some(self.selected) && game.sees_fleet(game.player, f))
+            .collect();
+        if !fleets.is_empty() {
+            res.push(Line::from(""));
+            res.push(Line::from(Span::from("Fleets:").bold()));
+            for fleet in fleets {
+                let owner = &game.empires[fleet.owner];
+                res.push(Line::styled(
+                    format!("{} - {} ship(s)", fleet.name, fleet.ships.len()),
+                    Style::default().fg(owner.color),
+                ));
+            }
+        }
+
+        if let Some(fleet) = self.selected_fleet(game) {
+            res.extend(Self::fleet_info(game, fleet));
+        }
+
+        // The log of the latest battle in the system, the last shots first
+        let witnessed = |b: &&Battle| b.system == self.selected && b.ships.iter().any(|c| c.owner == game.player);
+        if let Some(battle) = game.battles.iter().rev().find(witnessed) {
+            res.push(Line::from(""));
+            res.push(Line::from(Span::from(format!("Battle on turn {}:", battle.turn)).bold().fg(Color::LightRed)));
+            res.push(Line::from(battle.summary(game)));
+            res.extend(battle.log.iter().rev().map(|l| Line::from(l.clone())));
+        }
+        res
+    }
+}
+
+impl Component for GalaxyMap {
+    fn handle_key_events(&mut self, key: KeyEvent) -> color_eyre::Result<Option<Action>> {
+        if self.mode != Mode::Galaxy || self.system_count() == 0 {
+            return Ok(None);
+        }
+        let count = self.system_count();
+        match key.code {
+            KeyCode::Up | KeyCode::Left => {
+                self.selected = self.step_system(false);
+            }
+            KeyCode::Down | KeyCode::Right => {
+                self.selected = self.step_system(true);
+            }
+            KeyCode::Enter => {
+                return Ok(Some(Action::ShowSystem(self.selected)));
+            }
+            KeyCode::Char('f') => {
+                let fleets = self.game.as_ref().map_or(0, |g| Self::player_fleets(g).len());
+                self.fleet = (self.fleet + 1) % fleets.max(1);
+            }
+            KeyCode::Char('m') => {
+                let destination = self.selected;
+                return Ok(self.fleet_order(|fleet| Order::MoveFleet { fleet, destination }));
+            }
+            KeyCode::Char('w') => {
+                let destination = self.selected;
+                return Ok(self.fleet_order(|fleet| Order::QueueMove { fleet, destination }));
+            }
+            KeyCode::Char('x') => {
+                return Ok(self.fleet_order(|fleet| Order::CancelMove { fleet }));
+            }
+            KeyCode::Char('o') => {
+                return Ok(self.fleet_order(|fleet| Order::BuildOutpost { fleet }));
+            }
+            KeyCode::Char('s') => {
+                self.show_sensors = !self.show_sensors;
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        match action {
+            Action::GameStateUpdated(game) => { self.set_game(game) }
+            Action::SwitchMode(mode) => { self.mode = mode }
+            // Opening a system from the map switches to the system view
+            Action::ShowSystem(_) if self.mode == Mode::Galaxy => {
+                return Ok(Some(Action::SwitchMode(Mode::Home)));
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> color_eyre::Result<()> {
+        if self.mode != Mode::Galaxy {
+            return Ok(());
+        }
+        let Some(game) = self.game.clone() else { return Ok(()) };
+
+        let rects = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![
+                Constraint::Min(0),
+                Constraint::Length(40),
+            ])
+            .split(StatusBar::remaining_area(area));
+
+        let positions = &game.galaxy.positions;
+        let bound = |axis: fn(&(f32, f32)) -> f32| {
+            let min = positions.iter().map(axis).fold(f32::INFINITY, f32::min) as f64;
+            let max = positions.iter().map(axis).fold(f32::NEG_INFINITY, f32::max) as f64;
+            [min - MAP_MARGIN, max + MAP_MARGIN]
+        };
+        let (x_bounds, y_bounds) = (bound(|p| p.0), bound(|p| p.1));
+        // A step for every dot braille draws inside the borders of the map
+        let step = (
+            (x_bounds[1] - x_bounds[0]) / (rects[0].width.saturating_sub(2).max(1) as f64 * 2.0),
+            (y_bounds[1] - y_bounds[0]) / (rects[0].height.saturating_sub(2).max(1) as f64 * 4.0),
+        );
+        let borders = Self::border_points(&known_claims(&game, game.player), x_bounds, y_bounds, step);
+
+        let canvas = Canvas::default()
+            .block(Block::default().title("Galaxy map").borders(Borders::ALL))
+            .marker(Marker::Braille)
+            .x_bounds(x_bounds)
+            .y_bounds(y_bounds)
+            .paint(|ctx| {
+                for empire in &game.empires {
+                    let coords: Vec<(f64, f64)> = borders.iter()
+                        .filter(|(owner, _)| *owner == empire.id)
+                        .map(|(_, p)| *p)
+                        .collect();
+                    ctx.draw(&Points { coords: &coords, color: empire.color });
+                }
+                let lanes = game.galaxy.hyperlanes.iter()
+                    .filter(|(a, b)| Self::is_known(&game, *a) && Self::is_known(&game, *b));
+                for (a, b) in lanes {
+                    let (a, b) = (positions[*a], positions[*b]);
+                    ctx.draw(&CanvasLine {
+                        x1: a.0 as f64,
+                        y1: a.1 as f64,
+                        x2: b.0 as f64,
+                        y2: b.1 as f64,
+                        color: Color::DarkGray,
+                    });
+                }
+                if self.show_sensors {
+                    for sensor in sensors(&game, game.player).into_iter().filter(|s| s.strength > 0.0) {
+                        ctx.draw(&Circle {
+                            x: sensor.position.0 as f64,
+                            y: sensor.position.1 as f64,
+                            radius: (sensor.range * sensor.interference) as f64,
+                            color: Color::Rgb(40, 60, 90),
+                        });
+                    }
+                }
+                // Course of the chosen fleet
+                if let Some(fleet) = self.selected_fleet(&game) {
+                    let mut from = Self::fleet_position(&game, fleet);
+                    for next in &fleet.route {
+                        let to = positions[*next];
+                        ctx.draw(&CanvasLine {
+                            x1: from.0,
+                            y1: from.1,
+                            x2: to.0 as f64,
+                            y2: to.1 as f64,
+                            color: game.player_empire().color,
+                        });
+                        from = (to.0 as f64, to.1 as f64);
+                    }
+                }
+                ctx.layer();
+                let systems = game.galaxy.systems.iter().enumerate()
+                    .filter(|(id, _)| Self::is_known(&game, *id));
+                for (id, system) in systems {
+                    let (x, y) = positions[id];
+                    let color = Self::owner_color(&game, id).unwrap_or(Color::White);
+                    let mut spans = vec![Span::from("● ").fg(color)];
+                    let name = Span::from(system.name.clone()).fg(color);
+                    spans.push(if id == self.selected { name.reversed() } else { name });
+                    // A marker for every empire with fleets there, as far as the player knows
+                    let mut owners: Vec<EmpireId> = game.fleets.iter()
+                        .filter(|f| f.system() == Some(id) && game.sees_fleet(game.player, f))
+                        .map(|f| f.owner)
+                        .collect();
+                    owners.sort();
+                    owners.dedup();
+                    for owner in owners {
+                        spans.push(Span::from(" ▲").fg(game.empires[owner].color));
+                    }
+                    ctx.print(x as f64, y as f64, Line::from(spans));
+                }
+                for fleet in game.fleets.iter().filter(|f| f.system().is_none() && game.sees_fleet(game.player, f)) {
+                    let (x, y) = Self::fleet_position(&game, fleet);
+                    ctx.print(x, y, Span::from("▲").fg(game.empires[fleet.owner].color));
+                }
+            });
+        f.render_widget(canvas, rects[0]);
+
+        let system = &game.galaxy.systems[self.selected];
+        let info = Paragraph::new(self.system_info(&game))
+            .block(Block::default().title(format!("{} - Enter to open", system.name)).borders(Borders::ALL))
+            .wrap(Wrap { trim: true });
+        f.render_widget(info, rects[1]);
+        Ok(())
+    }
+}
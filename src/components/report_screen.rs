@@ -0,0 +1,134 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::Rect;
+use ratatui::prelude::{Line, Span};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
+use crate::action::Action;
+use crate::components::Component;
+use crate::components::status_bar::StatusBar;
+use crate::game::state::GameView;
+use crate::game::turn::{Phase, TurnReport};
+use crate::mode::Mode;
+use crate::tui::Frame;
+
+/// Lines scrolled by Page Up and Page Down
+const PAGE: u16 = 10;
+
+/// Every entry of the last turn report, grouped by the phase that produced it
+pub struct ReportScreen {
+    mode: Mode,
+    game: Option<GameView>,
+    /// Lines scrolled past at the top
+    scroll: u16,
+}
+
+impl Default for ReportScreen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReportScreen {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Home,
+            game: None,
+            scroll: 0,
+        }
+    }
+
+    fn lines(report: &TurnReport) -> Vec<Line<'static>> {
+        let mut res = Vec::new();
+        for phase in Phase::ALL {
+            let entries: Vec<&str> = report.entries.iter()
+                .filter(|e| e.phase == phase)
+                .map(|e| e.text.as_str())
+                .collect();
+            if entries.is_empty() {
+                continue;
+            }
+            if !res.is_empty() {
+                res.push(Line::from(""));
+            }
+            res.push(Line::from(Span::from(format!("{phase} ({})", entries.len())).bold()));
+            res.extend(entries.into_iter().map(|text| Line::from(format!("  {text}"))));
+        }
+        if res.is_empty() {
+            res.push(Line::styled("Nothing happened", Style::default().fg(Color::Gray)));
+        }
+        res
+    }
+}
+
+impl Component for ReportScreen {
+    fn handle_key_events(&mut self, key: KeyEvent) -> color_eyre::Result<Option<Action>> {
+        if self.mode != Mode::Report {
+            return Ok(None);
+        }
+        match key.code {
+            KeyCode::Up => { self.scroll = self.scroll.saturating_sub(1) }
+            KeyCode::Down => { self.scroll = self.scroll.saturating_add(1) }
+            KeyCode::PageUp => { self.scroll = self.scroll.saturating_sub(PAGE) }
+            KeyCode::PageDown => { self.scroll = self.scroll.saturating_add(PAGE) }
+            KeyCode::Home => { self.scroll = 0 }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        match action {
+            Action::GameStateUpdated(game) => {
+                // A new report is read from the top
+                if self.game.as_ref().map(|g| g.turn) != Some(game.turn) {
+                    self.scroll = 0;
+                }
+                self.game = Some(game);
+            }
+            Action::SwitchMode(mode) => { self.mode = mode }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> color_eyre::Result<()> {
+        if self.mode != Mode::Report {
+            return Ok(());
+        }
+        let Some(game) = &self.game else { return Ok(()) };
+        let area = StatusBar::remaining_area(area);
+
+        let (title, lines) = match &game.last_report {
+            Some(report) => {
+                (format!("Turn {} report - Up and Down scroll", report.turn), Self::lines(report))
+            }
+            None => {
+                ("Turn report".to_owned(), vec![Line::from("No turn has been processed yet")])
+            }
+        };
+        // Keep at least the last line in view
+        self.scroll = self.scroll.min(lines.len().saturating_sub(1) as u16);
+        let par = Paragraph::new(lines)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0));
+        f.render_widget(par, area);
+        Ok(())
+    }
+}
@@ -0,0 +1,352 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::prelude::{Line, Span};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::widgets::{Block, Borders, List, ListState, Paragraph, Wrap};
+use crate::action::Action;
+use crate::components::Component;
+use crate::components::status_bar::StatusBar;
+use crate::game::design::{component, hull, ShipDesign, SlotType, COMPONENTS, HULLS, MAX_NAME_LENGTH};
+use crate::game::order::Order;
+use crate::game::state::GameView;
+use crate::mode::Mode;
+use crate::tui::Frame;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Panel {
+    Designs,
+    Draft,
+    Catalogue,
+}
+
+/// Something the draft can be given from the catalogue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Part {
+    Hull(usize),
+    Component(usize),
+}
+
+/// Lets the player put together ship designs from hulls and components, name them and save them
+pub struct ShipDesigner {
+    mode: Mode,
+    game: Option<GameView>,
+    focus: Panel,
+    draft: ShipDesign,
+    /// Whether typed keys go into the name of the draft
+    naming: bool,
+    designs_state: ListState,
+    draft_state: ListState,
+    catalogue_state: ListState,
+}
+
+impl Default for ShipDesigner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShipDesigner {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Home,
+            game: None,
+            focus: Panel::Designs,
+            draft: Self::new_draft(),
+            naming: false,
+            designs_state: ListState::default().with_selected(Some(0)),
+            draft_state: ListState::default().with_selected(Some(0)),
+            catalogue_state: ListState::default().with_selected(Some(0)),
+        }
+    }
+
+    fn new_draft() -> ShipDesign {
+        ShipDesign::new("New design", &HULLS[0].id)
+    }
+
+    fn designs(&self) -> &[ShipDesign] {
+        self.game.as_ref().map_or(&[], |g| &g.player_empire().designs)
+    }
+
+    /// Hulls and the components the player has researched
+    fn catalogue(&self) -> Vec<Part> {
+        let Some(game) = &self.game else { return Vec::new() };
+        let research = &game.player_empire().research;
+        (0..HULLS.len()).map(Part::Hull)
+            .chain(COMPONENTS.iter()
+                .enumerate()
+                .filter(|(_, c)| research.component_unlocked(&c.id))
+                .map(|(i, _)| Part::Component(i)))
+            .collect()
+    }
+
+    fn panel_len(&self, panel: Panel) -> usize {
+        match panel {
+            Panel::Designs => { self.designs().len() }
+            Panel::Draft => { self.draft.components.len() }
+            Panel::Catalogue => { self.catalogue().len() }
+        }
+    }
+
+    fn panel_state(&mut self, panel: Panel) -> &mut ListState {
+        match panel {
+            Panel::Designs => { &mut self.designs_state }
+            Panel::Draft => { &mut self.draft_state }
+            Panel::Catalogue => { &mut self.catalogue_state }
+        }
+    }
+
+    fn move_selection(&mut self, up: bool) {
+        let len = self.panel_len(self.focus);
+        let state = self.panel_state(self.focus);
+        if len == 0 {
+            return;
+        }
+        let selected = state.selected().unwrap_or(0).min(len - 1);
+        state.select(Some(if up { (selected + len - 1) % len } else { (selected + 1) % len }));
+    }
+
+    fn selected(&self, panel: Panel) -> Option<usize> {
+        let state = match panel {
+            Panel::Designs => { &self.designs_state }
+            Panel::Draft => { &self.draft_state }
+            Panel::Catalogue => { &self.catalogue_state }
+        };
+        state.selected().filter(|s| *s < self.panel_len(panel))
+    }
+
+    fn confirm(&mut self) -> Option<Action> {
+        match self.focus {
+            Panel::Designs => {
+                self.draft = self.designs().get(self.selected(Panel::Designs)?)?.clone();
+                self.focus = Panel::Draft;
+            }
+            Panel::Catalogue => {
+                match self.catalogue().get(self.selected(Panel::Catalogue)?)? {
+                    Part::Hull(i) => { self.draft.hull = HULLS[*i].id.clone() }
+                    Part::Component(i) => { self.draft.components.push(COMPONENTS[*i].id.clone()) }
+                }
+            }
+            Panel::Draft => {}
+        }
+        None
+    }
+
+    fn remove(&mut self) -> Option<Action> {
+        match self.focus {
+            Panel::Designs => {
+                let design = self.designs().get(self.selected(Panel::Designs)?)?;
+                Some(Action::IssueOrder(Order::DeleteDesign { name: design.name.clone() }))
+            }
+            Panel::Draft => {
+                let index = self.selected(Panel::Draft)?;
+                self.draft.components.remove(index);
+                None
+            }
+            Panel::Catalogue => { None }
+        }
+    }
+
+    fn save(&self) -> Option<Action> {
+        let game = self.game.as_ref()?;
+        if !self.draft.validate(&game.player_empire().research).is_empty() {
+            return None;
+        }
+        Some(Action::IssueOrder(Order::SaveDesign { design: self.draft.clone() }))
+    }
+
+    fn handle_naming_key(&mut self, key: KeyEvent) -> Option<Action> {
+        match key.code {
+            KeyCode::Char(c) if self.draft.name.chars().count() < MAX_NAME_LENGTH => {
+                self.draft.name.push(c);
+            }
+            KeyCode::Backspace => {
+                self.draft.name.pop();
+            }
+            KeyCode::Enter | KeyCode::Esc => {
+                self.naming = false;
+                return Some(Action::StopTextInput);
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn block(&self, title: String, panel: Panel) -> Block<'static> {
+        let block = Block::default().title(title).borders(Borders::ALL);
+        if self.focus == panel {
+            block.border_style(Style::default().fg(Color::LightCyan))
+        } else {
+            block
+        }
+    }
+
+    fn draw_draft(&mut self, f: &mut Frame<'_>, area: Rect) {
+        let rects = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![
+                Constraint::Length(4 + SlotType::ALL.len() as u16),
+                Constraint::Min(0),
+            ])
+            .split(area);
+
+        let hull = hull(&self.draft.hull);
+        let mut lines = vec![Line::from(vec![
+            Span::from("Name: "),
+            if self.naming {
+                Span::from(format!("{}_", self.draft.name)).fg(Color::LightYellow)
+            } else {
+                Span::from(self.draft.name.clone()).bold()
+            },
+        ])];
+        lines.push(Line::from(format!("Hull: {}", hull.map_or("unknown", |h| h.name.as_str()))));
+        for slot in SlotType::ALL {
+            let (used, available) = (self.draft.used_slots(slot), hull.map_or(0, |h| h.slots(slot)));
+            let style = if used > available { Style::default().fg(Color::LightRed) } else { Style::default() };
+            lines.push(Line::styled(format!("{slot} slots: {used}/{available}"), style));
+        }
+        let title = if self.draft.revision > 0 {
+            format!("Draft (revision {})", self.draft.revision)
+        } else {
+            "Draft".to_owned()
+        };
+        f.render_widget(Paragraph::new(lines).block(self.block(title, Panel::Draft)), rects[0]);
+
+        let items: Vec<String> = self.draft.components.iter()
+            .map(|id| component(id).map_or(id.clone(), |c| format!("{} [{}]", c.name, c.slot)))
+            .collect();
+        let list = List::new(items)
+            .block(self.block("Components - Del removes".to_owned(), Panel::Draft))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_stateful_widget(list, rects[1], &mut self.draft_state);
+    }
+}
+
+impl Component for ShipDesigner {
+    fn handle_key_events(&mut self, key: KeyEvent) -> color_eyre::Result<Option<Action>> {
+        if self.mode != Mode::Designer {
+            return Ok(None);
+        }
+        if self.naming {
+            return Ok(self.handle_naming_key(key));
+        }
+        let action = match key.code {
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Panel::Designs => { Panel::Draft }
+                    Panel::Draft => { Panel::Catalogue }
+                    Panel::Catalogue => { Panel::Designs }
+                };
+                None
+            }
+            KeyCode::Up => { self.move_selection(true); None }
+            KeyCode::Down => { self.move_selection(false); None }
+            KeyCode::Enter => { self.confirm() }
+            KeyCode::Delete | KeyCode::Backspace => { self.remove() }
+            KeyCode::Char('n') => {
+                self.draft = Self::new_draft();
+                None
+            }
+            KeyCode::Char('r') => {
+                self.naming = true;
+                self.draft.name.clear();
+                Some(Action::StartTextInput)
+            }
+            KeyCode::Char('s') => { self.save() }
+            _ => { None }
+        };
+        Ok(action)
+    }
+
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        match action {
+            Action::GameStateUpdated(game) => { self.game = Some(game) }
+            Action::SwitchMode(mode) => {
+                self.mode = mode;
+                if self.naming {
+                    self.naming = false;
+                    return Ok(Some(Action::StopTextInput));
+                }
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> color_eyre::Result<()> {
+        if self.mode != Mode::Designer {
+            return Ok(());
+        }
+        let Some(game) = self.game.clone() else { return Ok(()) };
+
+        let rects = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![
+                Constraint::Percentage(22),
+                Constraint::Percentage(38),
+                Constraint::Min(0),
+            ])
+            .split(StatusBar::remaining_area(area));
+        let side = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![
+                Constraint::Percentage(45),
+                Constraint::Min(0),
+            ])
+            .split(rects[2]);
+
+        let designs: Vec<String> = game.player_empire().designs.iter()
+            .map(|d| format!("{} (rev. {})", d.name, d.revision))
+            .collect();
+        let list = List::new(designs)
+            .block(self.block("Designs - Enter loads".to_owned(), Panel::Designs))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_stateful_widget(list, rects[0], &mut self.designs_state);
+
+        self.draw_draft(f, rects[1]);
+
+        let catalogue: Vec<String> = self.catalogue().iter()
+            .map(|part| match part {
+                Part::Hull(i) => { format!("Hull: {}", HULLS[*i].name) }
+                Part::Component(i) => {
+                    let c = &COMPONENTS[*i];
+                    format!("{} [{}] {}t {:+}pw", c.name, c.slot, c.mass, c.power)
+                }
+            })
+            .collect();
+        let list = List::new(catalogue)
+            .block(self.block("Catalogue - Enter fits".to_owned(), Panel::Catalogue))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_stateful_widget(list, side[0], &mut self.catalogue_state);
+
+        let mut lines: Vec<Line> = self.draft.stats().make_info().into_iter().map(Line::from).collect();
+        let problems = self.draft.validate(&game.player_empire().research);
+        lines.push(Line::from(""));
+        if problems.is_empty() {
+            lines.push(Line::styled("Ready to be saved", Style::default().fg(Color::LightGreen)));
+        }
+        lines.extend(problems.into_iter().map(|p| Line::styled(p, Style::default().fg(Color::LightRed))));
+        lines.push(Line::from(""));
+        lines.push(Line::from("Tab switches panels, n new, r rename, s save"));
+        let par = Paragraph::new(lines)
+            .block(Block::default().title("Statistics").borders(Borders::ALL))
+            .wrap(Wrap { trim: true });
+        f.render_widget(par, side[1]);
+        Ok(())
+    }
+}
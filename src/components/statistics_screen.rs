@@ -0,0 +1,191 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::prelude::{Line, Span};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::symbols::Marker;
+use ratatui::widgets::{Axis, Block, Borders, Chart, Dataset, GraphType, Paragraph, Wrap};
+use strum::Display;
+use crate::action::Action;
+use crate::components::Component;
+use crate::components::status_bar::StatusBar;
+use crate::game::empire::Empire;
+use crate::game::state::GameView;
+use crate::game::victory::{progress, Statistics};
+use crate::mode::Mode;
+use crate::tui::Frame;
+
+/// What the chart shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+enum Metric {
+    Score,
+    Population,
+    Colonies,
+    Systems,
+    Technologies,
+    Ships,
+    Military,
+}
+
+impl Metric {
+    const ALL: [Metric; 7] = [
+        Metric::Score,
+        Metric::Population,
+        Metric::Colonies,
+        Metric::Systems,
+        Metric::Technologies,
+        Metric::Ships,
+        Metric::Military,
+    ];
+
+    fn of(&self, statistics: &Statistics) -> f64 {
+        match self {
+            Metric::Score => { statistics.score as f64 }
+            Metric::Population => { statistics.population as f64 }
+            Metric::Colonies => { statistics.colonies as f64 }
+            Metric::Systems => { statistics.systems as f64 }
+            Metric::Technologies => { statistics.technologies as f64 }
+            Metric::Ships => { statistics.ships as f64 }
+            Metric::Military => { statistics.military as f64 }
+        }
+    }
+}
+
+/// How the empires developed turn by turn, and how close the player is to winning
+pub struct StatisticsScreen {
+    mode: Mode,
+    game: Option<GameView>,
+    metric: usize,
+}
+
+impl Default for StatisticsScreen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StatisticsScreen {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Home,
+            game: None,
+            metric: 0,
+        }
+    }
+
+    /// Empires whose statistics the player knows: its own and those of the empires it met, or
+    /// every empire once the game is over
+    fn empires(game: &GameView) -> Vec<&Empire> {
+        let player = game.player_empire();
+        game.empires.iter()
+            .filter(|e| game.outcome.is_some() || e.id == player.id || player.contacts.contains(&e.id))
+            .collect()
+    }
+
+    fn sidebar(game: &GameView) -> Vec<Line<'static>> {
+        let player = game.player_empire();
+        let mut res = vec![Line::from(Span::from("Victory conditions").bold())];
+        for (victory, text) in progress(game, player.id) {
+            res.push(Line::from(format!("{victory}: {text}")));
+        }
+        if let Some(statistics) = player.statistics.last() {
+            res.push(Line::from(""));
+            res.push(Line::from(Span::from(format!("Score: {:.0}", statistics.score)).bold()));
+            for (source, points) in statistics.score_breakdown() {
+                res.push(Line::from(format!("  {source:<14}{points:>6.0}")));
+            }
+        }
+        res
+    }
+}
+
+impl Component for StatisticsScreen {
+    fn handle_key_events(&mut self, key: KeyEvent) -> color_eyre::Result<Option<Action>> {
+        if self.mode != Mode::Statistics {
+            return Ok(None);
+        }
+        match key.code {
+            KeyCode::Left => {
+                self.metric = (self.metric + Metric::ALL.len() - 1) % Metric::ALL.len();
+            }
+            KeyCode::Right => {
+                self.metric = (self.metric + 1) % Metric::ALL.len();
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        match action {
+            Action::GameStateUpdated(game) => { self.game = Some(game) }
+            Action::SwitchMode(mode) => { self.mode = mode }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> color_eyre::Result<()> {
+        if self.mode != Mode::Statistics {
+            return Ok(());
+        }
+        let Some(game) = self.game.clone() else { return Ok(()) };
+        let metric = Metric::ALL[self.metric];
+
+        let rects = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![
+                Constraint::Min(0),
+                Constraint::Length(48),
+            ])
+            .split(StatusBar::remaining_area(area));
+
+        let empires = Self::empires(&game);
+        let series: Vec<Vec<(f64, f64)>> = empires.iter()
+            .map(|e| e.statistics.iter().map(|s| (s.turn as f64, metric.of(s))).collect())
+            .collect();
+        let top = series.iter().flatten().map(|(_, v)| *v).fold(1.0, f64::max);
+        let last = game.turn.max(1) as f64;
+        let datasets: Vec<Dataset> = empires.iter().zip(series.iter())
+            .map(|(e, data)| Dataset::default()
+                .name(e.name.clone())
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(e.color))
+                .data(data))
+            .collect();
+        let chart = Chart::new(datasets)
+            .block(Block::default().title(format!("{metric} by turn - Left and Right change")).borders(Borders::ALL))
+            .x_axis(Axis::default()
+                .title("Turn")
+                .style(Style::default().fg(Color::Gray))
+                .bounds([1.0, last])
+                .labels(vec![Span::from("1"), Span::from(format!("{last:.0}"))]))
+            .y_axis(Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .bounds([0.0, top])
+                .labels(vec![Span::from("0"), Span::from(format!("{top:.0}"))]));
+        f.render_widget(chart, rects[0]);
+
+        let sidebar = Paragraph::new(Self::sidebar(&game))
+            .block(Block::default().title("Standing").borders(Borders::ALL))
+            .wrap(Wrap { trim: true });
+        f.render_widget(sidebar, rects[1]);
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::prelude::{Line, Span};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::widgets::Paragraph;
+use crate::action::Action;
+use crate::components::Component;
+use crate::game::clock::TimeMode;
+use crate::game::state::GameView;
+use crate::tui::Frame;
+
+/// Single line at the bottom of the screen with the turn, the date and the latest news
+#[derive(Default)]
+pub struct StatusBar {
+    game: Option<GameView>,
+}
+
+impl StatusBar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Area left for the rest of the interface once the status bar is drawn
+    pub fn remaining_area(area: Rect) -> Rect {
+        Self::split(area)[0]
+    }
+
+    fn split(area: Rect) -> std::rc::Rc<[Rect]> {
+        Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
+            .split(area)
+    }
+}
+
+impl Component for StatusBar {
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        if let Action::GameStateUpdated(game) = action {
+            self.game = Some(game);
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> color_eyre::Result<()> {
+        let Some(game) = &self.game else { return Ok(()) };
+        let empire = game.player_empire();
+
+        let mut spans = vec![
+            Span::from(format!(" {} ", empire.name)).fg(Color::Black).bg(empire.color),
+            Span::from(format!(" Turn {} ", game.turn)).bold(),
+            Span::from(format!("| {} ", game.date)),
+        ];
+        if game.clock.mode == TimeMode::RealTime {
+            spans.push(if game.clock.paused {
+                Span::from("| PAUSED ").fg(Color::LightRed).bold()
+            } else {
+                Span::from(format!("| x{} ", game.clock.speed)).fg(Color::LightGreen)
+            });
+        }
+        if let Some(report) = &game.last_report {
+            spans.push(Span::from(format!("| {} event(s) last turn", report.entries.len())).dim());
+            if let Some(entry) = report.entries.last() {
+                spans.push(Span::from(format!(" - {}: {}", entry.phase, entry.text)).dim());
+            }
+        }
+
+        let par = Paragraph::new(Line::from(spans))
+            .style(Style::new().white().on_black());
+        f.render_widget(par, Self::split(area)[1]);
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ratatui::layout::{Alignment, Constraint, Direction, Layout, Rect};
+use ratatui::prelude::{Line, Span};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table};
+use crate::action::Action;
+use crate::components::Component;
+use crate::components::status_bar::StatusBar;
+use crate::game::state::GameView;
+use crate::game::victory::Outcome;
+use crate::mode::Mode;
+use crate::tui::Frame;
+
+/// How the game ended and how every empire fared, shown once the game is over
+#[derive(Default)]
+pub struct Summary {
+    mode: Mode,
+    game: Option<GameView>,
+}
+
+impl Summary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn banner(game: &GameView) -> Vec<Line<'static>> {
+        let (title, color) = match game.outcome {
+            Some(outcome) if outcome.winner() == Some(game.player) => { ("VICTORY", Color::LightGreen) }
+            Some(_) => { ("DEFEAT", Color::LightRed) }
+            None => { ("THE GAME GOES ON", Color::LightYellow) }
+        };
+        let text = match &game.outcome {
+            Some(outcome @ Outcome::Victory { victory, .. }) => { format!("{victory} victory: {}", outcome.describe(game)) }
+            Some(outcome) => { outcome.describe(game) }
+            None => { "No empire has won yet".to_owned() }
+        };
+        vec![
+            Line::from(Span::from(title).fg(color).add_modifier(Modifier::BOLD)),
+            Line::from(text),
+            Line::styled(format!("Turn {} ({}), galaxy seed {}", game.turn, game.date, game.seed), Style::default().dim()),
+        ]
+    }
+}
+
+impl Component for Summary {
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        match action {
+            Action::GameStateUpdated(game) => { self.game = Some(game) }
+            Action::SwitchMode(mode) => { self.mode = mode }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> color_eyre::Result<()> {
+        if self.mode != Mode::Summary {
+            return Ok(());
+        }
+        let Some(game) = &self.game else { return Ok(()) };
+
+        let rects = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![
+                Constraint::Length(5),
+                Constraint::Length(game.empires.len() as u16 + 3),
+                Constraint::Min(0),
+            ])
+            .split(StatusBar::remaining_area(area));
+
+        let banner = Paragraph::new(Self::banner(game))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(banner, rects[0]);
+
+        // Only the empires the player met are named while the game goes on
+        let player = game.player_empire();
+        let known = |id| game.outcome.is_some() || id == player.id || player.contacts.contains(&id);
+        let mut empires: Vec<_> = game.empires.iter().filter(|e| known(e.id)).collect();
+        let score = |e: &crate::game::empire::Empire| e.statistics.last().map_or(0.0, |s| s.score);
+        empires.sort_by(|a, b| score(b).total_cmp(&score(a)));
+        let rows: Vec<Row> = empires.iter().enumerate()
+            .map(|(rank, e)| {
+                let last = e.statistics.last().copied().unwrap_or_default();
+                let peak = e.statistics.iter().map(|s| s.population).max().unwrap_or(0);
+                let status = if game.outcome.and_then(|o| o.winner()) == Some(e.id) {
+                    "Winner".to_owned()
+                } else if let Some(turn) = e.eliminated {
+                    format!("Eliminated on turn {turn}")
+                } else {
+                    "Standing".to_owned()
+                };
+                let style = if e.id == player.id { Style::default().bold() } else { Style::default() };
+                Row::new(vec![
+                    Line::from(format!("{}.", rank + 1)),
+                    Line::styled(e.name.clone(), Style::default().fg(e.color)),
+                    Line::from(status),
+                    Line::from(format!("{:.0}", last.score)),
+                    Line::from(format!("{} (peak {peak})", last.population)),
+                    Line::from(last.colonies.to_string()),
+                    Line::from(last.systems.to_string()),
+                    Line::from(last.technologies.to_string()),
+                    Line::from(e.ships_built.to_string()),
+                ]).style(style)
+            })
+            .collect();
+        let table = Table::new(rows, [
+            Constraint::Length(4),
+            Constraint::Length(22),
+            Constraint::Length(22),
+            Constraint::Length(7),
+            Constraint::Length(16),
+            Constraint::Length(9),
+            Constraint::Length(8),
+            Constraint::Length(13),
+            Constraint::Min(11),
+        ])
+            .header(Row::new(vec!["", "Empire", "Status", "Score", "Population", "Colonies", "Systems", "Technologies", "Ships built"]).bold())
+            .block(Block::default().title("Final standings").borders(Borders::ALL));
+        f.render_widget(table, rects[1]);
+
+        let battles = game.battles.iter().filter(|b| b.ships.iter().any(|c| c.owner == player.id)).count();
+        let mut lines = vec![
+            Line::from(format!("Turns played: {}", game.turn)),
+            Line::from(format!("Empires met: {} of {}", player.contacts.len(), game.empires.len() - 1)),
+            Line::from(format!("Recent battles fought: {battles}")),
+            Line::from(format!(
+                "Diplomatic events: {}",
+                game.diplomacy.history.iter().filter(|e| e.empires.0 == player.id || e.empires.1 == player.id).count()
+            )),
+            Line::from(""),
+            Line::styled("F7 shows how the empires developed, F1 to F6 look around the galaxy, q quits", Style::default().dim()),
+        ];
+        if game.outcome.is_none() {
+            lines.pop();
+        }
+        let par = Paragraph::new(lines)
+            .block(Block::default().title(format!("{} in numbers", player.name)).borders(Borders::ALL));
+        f.render_widget(par, rects[2]);
+        Ok(())
+    }
+}
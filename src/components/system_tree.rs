@@ -20,18 +20,38 @@ use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::prelude::{Alignment, Line, Span};
 use ratatui::style::{Color, Modifier, Style, Stylize};
 use ratatui::text::Text;
+use ratatui::symbols::Marker;
+use ratatui::widgets::canvas::{Canvas, Circle, Context, Line as CanvasLine};
 use ratatui::widgets::{Block, Borders, List, ListDirection, ListState, Paragraph, Wrap};
 use tokio::sync::mpsc::UnboundedSender;
 use crate::action::Action;
 use crate::components::Component;
+use crate::components::status_bar::StatusBar;
 use crate::game::body::Body;
+use crate::game::building::{slots, used_slots, BuildingKind, BUILDINGS};
+use crate::game::colony::{is_being_colonized, Colony};
+use crate::game::fleet::Fleet;
+use crate::game::galaxy::{BodyRef, SystemId, NEBULA_SPEED};
+use crate::game::knowledge::KnowledgeLevel;
+use crate::game::order::Order;
+use crate::game::population::capacity;
+use crate::game::ship::ShipRole;
+use crate::game::state::GameView;
 use crate::game::system::System;
+use crate::mode::Mode;
 use crate::tui::{Event, Frame};
 
 pub struct SystemTree {
-    system_displayed: System,
+    mode: Mode,
+    game: Option<GameView>,
+    system_id: SystemId,
+    system_displayed: Option<System>,
     selected_row: usize,
     list_state: ListState,
+    /// Index of the building chosen for construction
+    blueprint: usize,
+    /// Index of the ship design chosen for the shipyard
+    ship_design: usize,
 }
 
 impl Default for SystemTree {
@@ -43,22 +63,31 @@ impl Default for SystemTree {
 impl SystemTree {
     pub fn new() -> Self {
         Self {
+            mode: Mode::Home,
+            game: None,
+            system_id: 0,
             selected_row: 0,
-            system_displayed: System::generate(),
+            system_displayed: None,
             list_state: ListState::default(),
+            blueprint: 0,
+            ship_design: 0,
         }
     }
 
+    fn body_count(&self) -> usize {
+        self.system_displayed.as_ref().map_or(0, |s| s.bodies.len())
+    }
+
     fn move_selection_up(&mut self) {
         if self.selected_row != 0 {
             self.selected_row -= 1;
         } else {
-            self.selected_row = self.system_displayed.bodies.len()
+            self.selected_row = self.body_count()
         }
     }
 
     fn move_selection_down(&mut self) {
-        if self.system_displayed.bodies.len() == self.selected_row {
+        if self.body_count() == self.selected_row {
             self.selected_row = 0
         } else { 
             self.selected_row += 1
@@ -66,16 +95,211 @@ impl SystemTree {
     }
 
     fn update_tree(&mut self, new_system: System) {
-        self.system_displayed = new_system;
+        self.system_displayed = Some(new_system);
         self.selected_row = 0;
     }
+
+    /// The system as far as the player knows it
+    fn known_system(&self, system_id: SystemId) -> Option<System> {
+        let game = self.game.as_ref()?;
+        game.player_empire().knowledge.system(system_id).map(|k| k.known_system())
+    }
+
+    fn show_system(&mut self, system_id: SystemId) {
+        if let Some(system) = self.known_system(system_id) {
+            self.system_id = system_id;
+            self.update_tree(system);
+        }
+    }
+
+    /// The next system the player knows of, going forwards or backwards through the galaxy
+    fn step_system(&self, forward: bool) -> Option<SystemId> {
+        let game = self.game.as_ref()?;
+        let count = self.system_count();
+        (1..count)
+            .map(|i| if forward { (self.system_id + i) % count } else { (self.system_id + count - i) % count })
+            .find(|s| game.player_empire().knowledge.system(*s).is_some())
+    }
+
+    /// Colonisation order for the selected body, given the player has a colony ship in the system
+    fn colonize_selected(&self) -> Option<Action> {
+        let game = self.game.as_ref()?;
+        let body = self.system_displayed.as_ref()?.bodies.get(self.selected_row.checked_sub(1)?)?;
+        let fleet = game.fleets.iter().find(|f| {
+            f.owner == game.player && f.system() == Some(self.system_id) && f.has_role(&ShipRole::ColonyShip)
+        })?;
+        Some(Action::IssueOrder(Order::Colonize {
+            fleet: fleet.id,
+            target: BodyRef { system: self.system_id, body: body.id },
+        }))
+    }
+
+    /// The selected body, if it is a colony of the player
+    fn selected_player_colony(&self) -> Option<(BodyRef, &Colony)> {
+        let game = self.game.as_ref()?;
+        let body = self.system_displayed.as_ref()?.bodies.get(self.selected_row.checked_sub(1)?)?;
+        let colony = body.colony.as_ref().filter(|c| c.owner == game.player)?;
+        Some((BodyRef { system: self.system_id, body: body.id }, colony))
+    }
+
+    fn build_selected(&self) -> Option<Action> {
+        let (colony, _) = self.selected_player_colony()?;
+        Some(Action::IssueOrder(Order::Build { colony, building: BUILDINGS[self.blueprint].id.clone() }))
+    }
+
+    /// Cancels the last entry of the construction queue of the selected colony
+    fn cancel_selected(&self) -> Option<Action> {
+        let (colony, c) = self.selected_player_colony()?;
+        let index = c.queue.len().checked_sub(1)?;
+        Some(Action::IssueOrder(Order::CancelConstruction { colony, index }))
+    }
+
+    /// Sends the first player fleet in the system to the selected body, or to the star
+    fn fly_to_selected(&self) -> Option<Action> {
+        let game = self.game.as_ref()?;
+        let body = match self.selected_row.checked_sub(1) {
+            Some(index) => { self.system_displayed.as_ref()?.bodies.get(index)?.id }
+            None => { 0 }
+        };
+        let fleet = game.fleets.iter()
+            .find(|f| f.owner == game.player && f.system() == Some(self.system_id) && !f.is_moving())?;
+        Some(Action::IssueOrder(Order::MoveInSystem { fleet: fleet.id, body }))
+    }
+
+    /// Orbits of the system with its bodies and fleets where they currently are
+    fn orbit_map(&self, game: &GameView, system: &System) -> Canvas<'static, impl Fn(&mut Context)> {
+        let days = game.date.days();
+        let extent = system.bodies.iter()
+            .filter_map(|b| b.orbit_radius)
+            .fold(1.0, f32::max) as f64 * 1.1;
+        let fleets: Vec<Fleet> = game.fleets.iter()
+            .filter(|f| f.system() == Some(self.system_id) && game.sees_fleet(game.player, f))
+            .cloned()
+            .collect();
+        let selected = self.selected_row;
+        let (system, game) = (system.clone(), game.clone());
+        Canvas::default()
+            .block(Block::default().title("Orbits - g flies a fleet to the selection").borders(Borders::ALL))
+            .marker(Marker::Braille)
+            .x_bounds([-extent, extent])
+            .y_bounds([-extent, extent])
+            .paint(move |ctx| {
+                for body in &system.bodies {
+                    ctx.draw(&Circle {
+                        x: 0.0,
+                        y: 0.0,
+                        radius: body.orbit_radius.unwrap_or(0.0) as f64,
+                        color: Color::DarkGray,
+                    });
+                }
+                // Where foreign fleets are flying to is not known
+                for fleet in fleets.iter().filter(|f| f.owner == game.player) {
+                    let Some(transfer) = &fleet.transfer else { continue };
+                    let (x, y) = transfer.position();
+                    ctx.draw(&CanvasLine {
+                        x1: x as f64,
+                        y1: y as f64,
+                        x2: transfer.to.0 as f64,
+                        y2: transfer.to.1 as f64,
+                        color: game.empires[fleet.owner].color,
+                    });
+                }
+                ctx.layer();
+                ctx.print(0.0, 0.0, Span::from("*").fg(Color::Yellow));
+                for (i, body) in system.bodies.iter().enumerate() {
+                    let (x, y) = body.orbital_position(days);
+                    let color: Color = body.kind.clone().into();
+                    let name = Span::from(format!("● {}", body.name)).fg(color);
+                    ctx.print(x as f64, y as f64, if i + 1 == selected { name.reversed() } else { name });
+                }
+                for fleet in &fleets {
+                    let (x, y) = fleet.orbital_position(&game.galaxy, days);
+                    ctx.print(x as f64, y as f64, Span::from("▲").fg(game.empires[fleet.owner].color));
+                }
+            })
+    }
+
+    fn design_count(&self) -> usize {
+        self.game.as_ref().map_or(0, |g| g.player_empire().designs.len())
+    }
+
+    fn build_ship_selected(&self) -> Option<Action> {
+        let (colony, _) = self.selected_player_colony()?;
+        let design = self.game.as_ref()?.player_empire().designs.get(self.ship_design)?;
+        Some(Action::IssueOrder(Order::BuildShip { colony, design: design.name.clone() }))
+    }
+
+    /// Cancels the last entry of the shipyard queue of the selected colony
+    fn cancel_ship_selected(&self) -> Option<Action> {
+        let (colony, c) = self.selected_player_colony()?;
+        let index = c.shipyard_queue.len().checked_sub(1)?;
+        Some(Action::IssueOrder(Order::CancelShip { colony, index }))
+    }
+
+    /// Sends the first player fleet in the system with outdated ships to the selected colony's yard
+    fn refit_selected(&self) -> Option<Action> {
+        let game = self.game.as_ref()?;
+        let (colony, _) = self.selected_player_colony()?;
+        let designs = &game.player_empire().designs;
+        let fleet = game.fleets.iter().find(|f| {
+            f.owner == game.player && f.system() == Some(self.system_id)
+                && f.ships.iter().any(|s| s.is_outdated(designs))
+        })?;
+        Some(Action::IssueOrder(Order::RefitFleet { fleet: fleet.id, colony }))
+    }
+
+    fn system_count(&self) -> usize {
+        self.game.as_ref().map_or(0, |g| g.galaxy.systems.len())
+    }
+
+    fn set_game(&mut self, game: GameView) {
+        let first_update = self.game.is_none();
+        let capital = game.player_empire().capital;
+        self.game = Some(game);
+        let system = self.known_system(self.system_id);
+        if first_update {
+            self.show_system(capital);
+        } else {
+            // Keep the selection, the system may have lost bodies in the meantime
+            self.system_displayed = system;
+            self.selected_row = self.selected_row.min(self.body_count());
+        }
+    }
 }
 
 impl Component for SystemTree {
     fn handle_key_events(&mut self, key: KeyEvent) -> color_eyre::Result<Option<Action>> {
+        if self.mode != Mode::Home {
+            return Ok(None);
+        }
         match key.code {
             KeyCode::Up => { Ok(Some(Action::MoveSystemTreeSelectionUp)) }
             KeyCode::Down => { Ok(Some(Action::MoveSystemTreeSelectionDown)) }
+            KeyCode::Left => { Ok(Some(Action::ShowPreviousSystem)) }
+            KeyCode::Right => { Ok(Some(Action::ShowNextSystem)) }
+            KeyCode::Char('c') => { Ok(self.colonize_selected()) }
+            KeyCode::Char('b') => { Ok(self.build_selected()) }
+            KeyCode::Char('x') => { Ok(self.cancel_selected()) }
+            KeyCode::Char('[') => {
+                self.blueprint = (self.blueprint + BUILDINGS.len() - 1) % BUILDINGS.len();
+                Ok(None)
+            }
+            KeyCode::Char(']') => {
+                self.blueprint = (self.blueprint + 1) % BUILDINGS.len();
+                Ok(None)
+            }
+            KeyCode::Char('y') => { Ok(self.build_ship_selected()) }
+            KeyCode::Char('z') => { Ok(self.cancel_ship_selected()) }
+            KeyCode::Char('u') => { Ok(self.refit_selected()) }
+            KeyCode::Char('g') => { Ok(self.fly_to_selected()) }
+            KeyCode::Char('{') if self.design_count() > 0 => {
+                self.ship_design = (self.ship_design + self.design_count() - 1) % self.design_count();
+                Ok(None)
+            }
+            KeyCode::Char('}') if self.design_count() > 0 => {
+                self.ship_design = (self.ship_design + 1) % self.design_count();
+                Ok(None)
+            }
             _ => { Ok(None) }
         }
     }
@@ -88,6 +312,25 @@ impl Component for SystemTree {
             Action::MoveSystemTreeSelectionDown => {
                 self.move_selection_down();
             },
+            Action::ShowSystem(system_id) => {
+                self.show_system(system_id);
+            },
+            Action::ShowPreviousSystem => {
+                if let Some(system) = self.step_system(false) {
+                    self.show_system(system);
+                }
+            },
+            Action::ShowNextSystem => {
+                if let Some(system) = self.step_system(true) {
+                    self.show_system(system);
+                }
+            },
+            Action::GameStateUpdated(game) => {
+                self.set_game(game);
+            },
+            Action::SwitchMode(mode) => {
+                self.mode = mode;
+            },
             _ => {}
         }
         Ok(None)
@@ -100,24 +343,55 @@ impl Component for SystemTree {
                 Constraint::Percentage(20),
                 Constraint::Min(0),
             ])
-            .split(area);
+            .split(StatusBar::remaining_area(area));
+
+        let (Some(system), Some(game)) = (self.system_displayed.clone(), self.game.clone()) else {
+            return Ok(());
+        };
+        if self.mode != Mode::Home {
+            return Ok(());
+        }
 
 
-        let mut bodies = vec![self.system_displayed.star.clone()];
-        bodies.append(&mut self.system_displayed.bodies.clone());
-        let bodies_names: Vec<Text> = bodies.iter()
+        let mut bodies = vec![system.star.clone()];
+        bodies.append(&mut system.bodies.clone());
+        let mut bodies_names: Vec<Text> = bodies.iter()
             .map(|f| {
-                let col: Color = f.clone().kind.into();
-                Text::from(f.name.clone()).fg(col)
+                // Colonised bodies are shown in the colour of their owner
+                match &f.colony {
+                    Some(colony) => {
+                        Text::from(format!("{} ◆", f.name)).fg(game.empires[colony.owner].color).bold()
+                    }
+                    None => {
+                        let col: Color = f.clone().kind.into();
+                        Text::from(f.name.clone()).fg(col)
+                    }
+                }
             })
             .collect();
 
-        let mut selection_list = vec![self.system_displayed.star.clone()];
-        selection_list.append(&mut self.system_displayed.bodies.clone());
+        // Mark the star with a sign for every hazard of the system
+        let hazard_signs: Vec<Span> = system.modifiers().hazards.iter()
+            .map(|h| Span::from(" !").fg(h.color()))
+            .collect();
+        if let Some(Some(line)) = bodies_names.first_mut().map(|t| t.lines.first_mut()) {
+            line.spans.extend(hazard_signs);
+        }
+
+        let mut selection_list = vec![system.star.clone()];
+        selection_list.append(&mut system.bodies.clone());
         let selected =  selection_list.get(self.selected_row).unwrap();
 
+        let knowledge = game.player_empire().knowledge.clone();
+        let Some(known) = knowledge.system(self.system_id) else { return Ok(()) };
+        let visible = knowledge.is_visible(self.system_id);
+        let title = if visible {
+            format!("System tree - {}", system.name)
+        } else {
+            format!("System tree - {} (last seen on turn {})", system.name, known.last_seen)
+        };
         let list = List::new(bodies_names)
-            .block(Block::default().title(format!("System tree - {}", self.system_displayed.star.name)).borders(Borders::ALL))
+            .block(Block::default().title(title).borders(Borders::ALL))
             .style(Style::default().fg(Color::White))
             .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
             .highlight_symbol(">> ")
@@ -126,10 +400,123 @@ impl Component for SystemTree {
 
 
         
-        let text: Vec<Line> = selected.make_info()
+        let mut text: Vec<Line> = known.body_info(selected)
             .iter()
             .map(|f| { Line::from(f.clone()) })
             .collect();
+
+        if self.selected_row == 0 {
+            let modifiers = system.modifiers();
+            text.push(Line::from(""));
+            if known.body_level(selected.id) == KnowledgeLevel::Surveyed {
+                text.extend(system.evolution_info().into_iter().map(Line::from));
+            }
+            if system.in_nebula {
+                text.push(Line::from(format!(
+                    "The system lies inside a nebula, fleets cross its hyperlanes at {:.0}% speed",
+                    NEBULA_SPEED * 100.0
+                )));
+            }
+            if let Some(outpost) = &system.outpost {
+                text.push(Line::from(format!("Outpost of {} since {}", game.empires[outpost.owner].name, outpost.built)));
+            }
+            text.extend(modifiers.make_info().into_iter().map(Line::from));
+            let designs = &game.player_empire().designs;
+            let fleets = game.fleets.iter()
+                .filter(|f| f.system() == Some(self.system_id) && game.sees_fleet(game.player, f));
+            for fleet in fleets {
+                let owner = &game.empires[fleet.owner];
+                text.push(Line::from(""));
+                // Sensors tell who a foreign fleet belongs to and how large it is, not where it
+                // is going or what its ships are
+                if fleet.owner != game.player {
+                    text.push(Line::styled(
+                        format!("{} ({}), {} ships", fleet.name, owner.name, fleet.ships.len()),
+                        Style::default().fg(owner.color),
+                    ));
+                    continue;
+                }
+                let course = match (fleet.waypoints.last(), fleet.arrival(&game.galaxy)) {
+                    (Some(to), Some(days)) => { format!(", bound for {} in {days} days", game.galaxy.systems[*to].name) }
+                    _ => { String::new() }
+                };
+                let body_name = |id| system.body(id).map_or(system.name.clone(), |b| b.name.clone());
+                let position = match (&fleet.transfer, fleet.transfer_eta()) {
+                    (Some(transfer), Some(days)) => { format!(", flying to {} in {days} days", body_name(transfer.target)) }
+                    _ => { format!(", at {}", body_name(fleet.orbit)) }
+                };
+                text.push(Line::styled(
+                    format!("{} ({}), {} ships{position}{course}", fleet.name, owner.name, fleet.ships.len()),
+                    Style::default().fg(owner.color),
+                ));
+                for ship in &fleet.ships {
+                    let outdated = ship.is_outdated(designs);
+                    let damaged = if ship.damage > 0.0 { format!(" ({:.0} damage)", ship.damage) } else { String::new() };
+                    let shield = if ship.shield_loss > 0.0 { format!(" (shield {:.0}/{:.0})", ship.shield(), ship.design.stats().shield) } else { String::new() };
+                    text.push(Line::from(format!(
+                        "- {}: {} r{}{}{damaged}{shield}",
+                        ship.name,
+                        ship.design.name,
+                        ship.design.revision,
+                        if outdated { " (outdated)" } else { "" },
+                    )));
+                }
+            }
+        } else if let Some(colony) = &selected.colony {
+            let owner = &game.empires[colony.owner];
+            text.push(Line::from(""));
+            text.push(Line::styled(
+                format!("Colony of {} ({})", owner.name, owner.species.name),
+                Style::default().fg(owner.color),
+            ));
+            text.extend(colony.make_info(capacity(selected, &owner.species)).into_iter().map(Line::from));
+            text.push(Line::from(format!(
+                "Districts: {}/{}   Buildings: {}/{}",
+                used_slots(selected, BuildingKind::District),
+                slots(selected, BuildingKind::District),
+                used_slots(selected, BuildingKind::Building),
+                slots(selected, BuildingKind::Building),
+            )));
+            if colony.owner == game.player {
+                let def = &BUILDINGS[self.blueprint];
+                let cost: Vec<String> = def.cost.iter()
+                    .filter(|(_, a)| *a > 0.0)
+                    .map(|(r, a)| format!("{a} {r}"))
+                    .collect();
+                text.push(Line::from(""));
+                text.push(Line::styled(
+                    format!("Build: < {} > ({}, {} turns)", def.name, cost.join(", "), def.build_time),
+                    Style::default().fg(Color::LightCyan),
+                ));
+                text.push(Line::from("[ ] choose, b queue, x cancel last"));
+                if colony.has_shipyard() {
+                    text.push(Line::from(""));
+                    if let Some(design) = game.player_empire().designs.get(self.ship_design) {
+                        let cost: Vec<String> = design.stats().cost.iter()
+                            .filter(|(_, a)| *a > 0.0)
+                            .map(|(r, a)| format!("{a} {r}"))
+                            .collect();
+                        text.push(Line::styled(
+                            format!("Shipyard: < {} r{} > ({})", design.name, design.revision, cost.join(", ")),
+                            Style::default().fg(Color::LightCyan),
+                        ));
+                    }
+                    text.extend(colony.shipyard_queue.iter().map(|s| Line::from(format!("- {}", s.describe()))));
+                    text.push(Line::from("{ } choose, y build, z cancel last, u refit fleet here"));
+                }
+            }
+        } else if is_being_colonized(&game, BodyRef { system: self.system_id, body: selected.id }) {
+            text.push(Line::from(""));
+            text.push(Line::from("Colonists are founding a colony here"));
+        } else if known.body_level(selected.id) == KnowledgeLevel::Surveyed && selected.habitability() > 0.0 {
+            let species = &game.player_empire().species;
+            text.push(Line::from(format!(
+                "Habitability for the {}: {:.0}% (room for {})",
+                species.name,
+                species.habitability(selected) * 100.0,
+                capacity(selected, species),
+            )));
+        }
         
         let par = Paragraph::new(text)
             .block(Block::new().title(format!("Information about - {}", selected.name)).borders(Borders::ALL))
@@ -137,9 +524,18 @@ impl Component for SystemTree {
             .alignment(Alignment::Left)
             .wrap(Wrap { trim: true });
 
+        let right = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![
+                Constraint::Min(0),
+                Constraint::Percentage(45),
+            ])
+            .split(rects[1]);
+
         self.list_state.select(Some(self.selected_row));
         f.render_stateful_widget(list, rects[0], &mut self.list_state);
-        f.render_widget(par, rects[1]);
+        f.render_widget(par, right[0]);
+        f.render_widget(self.orbit_map(&game, &system), right[1]);
         Ok(())
     }
 }
\ No newline at end of file
@@ -0,0 +1,251 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::prelude::{Line, Span};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
+use crate::action::Action;
+use crate::components::Component;
+use crate::components::status_bar::StatusBar;
+use crate::game::order::Order;
+use crate::game::resources::Resource;
+use crate::game::state::GameView;
+use crate::game::tech::{tech, Research, TechDef, TECHNOLOGIES};
+use crate::mode::Mode;
+use crate::tui::Frame;
+
+/// Technologies laid out as a graph, one column for every tier of prerequisites
+pub struct TechTree {
+    mode: Mode,
+    game: Option<GameView>,
+    columns: Vec<Vec<&'static TechDef>>,
+    column: usize,
+    row: usize,
+}
+
+impl Default for TechTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TechTree {
+    pub fn new() -> Self {
+        let tiers = TECHNOLOGIES.iter().map(|t| t.tier()).max().map_or(0, |t| t + 1);
+        let mut columns: Vec<Vec<&'static TechDef>> = vec![Vec::new(); tiers];
+        for def in TECHNOLOGIES.iter() {
+            columns[def.tier()].push(def);
+        }
+        for column in columns.iter_mut() {
+            column.sort_by_key(|t| (t.category.to_string(), t.name.clone()));
+        }
+        Self {
+            mode: Mode::Home,
+            game: None,
+            columns,
+            column: 0,
+            row: 0,
+        }
+    }
+
+    fn selected(&self) -> Option<&'static TechDef> {
+        self.columns.get(self.column)?.get(self.row).copied()
+    }
+
+    fn select_column(&mut self, column: usize) {
+        self.column = column;
+        self.row = self.row.min(self.columns[column].len().saturating_sub(1));
+    }
+
+    fn status_style(research: &Research, def: &TechDef) -> Style {
+        if research.knows(&def.id) {
+            Style::default().fg(Color::LightGreen)
+        } else if research.is_queued(&def.id) {
+            Style::default().fg(Color::LightYellow)
+        } else if research.can_research(def) {
+            Style::default().fg(Color::White)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        }
+    }
+
+    fn status(research: &Research, def: &TechDef) -> String {
+        if research.knows(&def.id) {
+            "Researched".to_owned()
+        } else if let Some(position) = research.queue.iter().position(|q| *q == def.id) {
+            format!("Queued #{}", position + 1)
+        } else if research.can_research(def) {
+            "Available".to_owned()
+        } else {
+            "Needs prerequisites".to_owned()
+        }
+    }
+
+    fn details(&self, game: &GameView, def: &TechDef) -> Vec<Line<'static>> {
+        let research = &game.player_empire().research;
+        let mut res = vec![
+            Line::from(format!("{} - {}", def.category, Self::status(research, def)))
+                .style(Self::status_style(research, def)),
+            Line::from(format!("Cost: {:.0} research", def.cost)),
+            Line::from(""),
+            Line::from(def.description.clone()),
+        ];
+
+        let names = |ids: Vec<&TechDef>| ids.iter().map(|t| t.name.clone()).collect::<Vec<String>>().join(", ");
+        if !def.prerequisites.is_empty() {
+            let prerequisites = def.prerequisites.iter().filter_map(|p| tech(p)).collect();
+            res.push(Line::from(""));
+            res.push(Line::from(format!("Requires: {}", names(prerequisites))));
+        }
+        let leads_to = def.leads_to();
+        if !leads_to.is_empty() {
+            res.push(Line::from(format!("Leads to: {}", names(leads_to))));
+        }
+        let unlocks = def.describe_unlocks();
+        if !unlocks.is_empty() {
+            res.push(Line::from(""));
+            res.push(Line::from(Span::from("Unlocks:").bold()));
+            res.extend(unlocks.into_iter().map(|u| Line::from(format!("  {u}"))));
+        }
+        res
+    }
+
+    fn queue(game: &GameView) -> Vec<Line<'static>> {
+        let empire = game.player_empire();
+        let research = &empire.research;
+        let income = empire.ledger.income().get(Resource::Research);
+        let mut res = vec![Line::from(format!("Research income: {income:.1} per turn"))];
+        for (i, id) in research.queue.iter().enumerate() {
+            let Some(def) = tech(id) else { continue };
+            let eta = research.turns_until(id, income).map_or("never".to_owned(), |t| format!("{t} turns"));
+            let progress = if i == 0 {
+                format!(" {:.0}/{:.0}", research.progress, def.cost)
+            } else {
+                String::new()
+            };
+            res.push(Line::from(format!("{}. {}{progress} - {eta}", i + 1, def.name)));
+        }
+        if research.queue.is_empty() {
+            res.push(Line::from("Nothing queued, research is stockpiled"));
+        }
+        res
+    }
+}
+
+impl Component for TechTree {
+    fn handle_key_events(&mut self, key: KeyEvent) -> color_eyre::Result<Option<Action>> {
+        if self.mode != Mode::Research || self.columns.is_empty() {
+            return Ok(None);
+        }
+        match key.code {
+            KeyCode::Left => {
+                self.select_column((self.column + self.columns.len() - 1) % self.columns.len());
+            }
+            KeyCode::Right => {
+                self.select_column((self.column + 1) % self.columns.len());
+            }
+            KeyCode::Up => {
+                let len = self.columns[self.column].len();
+                self.row = (self.row + len - 1) % len;
+            }
+            KeyCode::Down => {
+                self.row = (self.row + 1) % self.columns[self.column].len();
+            }
+            KeyCode::Enter => {
+                let Some(def) = self.selected() else { return Ok(None) };
+                return Ok(Some(Action::IssueOrder(Order::QueueResearch { tech: def.id.clone() })));
+            }
+            KeyCode::Backspace | KeyCode::Delete => {
+                let Some(def) = self.selected() else { return Ok(None) };
+                return Ok(Some(Action::IssueOrder(Order::DequeueResearch { tech: def.id.clone() })));
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        match action {
+            Action::GameStateUpdated(game) => { self.game = Some(game) }
+            Action::SwitchMode(mode) => { self.mode = mode }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> color_eyre::Result<()> {
+        if self.mode != Mode::Research {
+            return Ok(());
+        }
+        let (Some(game), Some(selected)) = (self.game.clone(), self.selected()) else { return Ok(()) };
+        let research = &game.player_empire().research;
+
+        let rects = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![
+                Constraint::Min(0),
+                Constraint::Length(44),
+            ])
+            .split(StatusBar::remaining_area(area));
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Ratio(1, self.columns.len() as u32); self.columns.len()])
+            .split(rects[0]);
+
+        for (i, column) in self.columns.iter().enumerate() {
+            let lines: Vec<Line> = column.iter()
+                .enumerate()
+                .map(|(row, def)| {
+                    // Arrows point from the prerequisites of the selected technology towards it, and
+                    // from it towards the technologies it leads to
+                    let prerequisite = selected.prerequisites.contains(&def.id);
+                    let dependant = def.prerequisites.contains(&selected.id);
+                    let mut style = Self::status_style(research, def);
+                    if i == self.column && row == self.row {
+                        style = style.add_modifier(Modifier::REVERSED);
+                    }
+                    Line::from(vec![
+                        Span::from(if dependant { "▶ " } else { "  " }).fg(Color::LightCyan),
+                        Span::styled(def.name.clone(), style),
+                        Span::from(if prerequisite { " ▶" } else { "" }).fg(Color::LightCyan),
+                    ])
+                })
+                .collect();
+            let par = Paragraph::new(lines)
+                .block(Block::default().title(format!("Tier {}", i + 1)).borders(Borders::ALL));
+            f.render_widget(par, columns[i]);
+        }
+
+        let side = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![
+                Constraint::Min(0),
+                Constraint::Length(research.queue.len().max(1) as u16 + 3),
+            ])
+            .split(rects[1]);
+        let details = Paragraph::new(self.details(&game, selected))
+            .block(Block::default().title(selected.name.clone()).borders(Borders::ALL))
+            .wrap(Wrap { trim: true });
+        f.render_widget(details, side[0]);
+        let queue = Paragraph::new(Self::queue(&game))
+            .block(Block::default().title("Queue - Enter adds, Del removes").borders(Borders::ALL))
+            .wrap(Wrap { trim: true });
+        f.render_widget(queue, side[1]);
+        Ok(())
+    }
+}
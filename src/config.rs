@@ -27,7 +27,7 @@ use serde::{
 };
 use serde_json::Value as JsonValue;
 
-use crate::{action::Action, mode::Mode};
+use crate::{action::Action, game::turn::EventKind, mode::Mode};
 
 const CONFIG: &str = include_str!("../.config/config.json5");
 
@@ -47,6 +47,9 @@ pub struct Config {
   pub keybindings: KeyBindings,
   #[serde(default)]
   pub styles: Styles,
+  /// Kinds of events which pause a real-time game
+  #[serde(default)]
+  pub auto_pause: Option<Vec<EventKind>>,
 }
 
 impl Config {
@@ -87,12 +90,20 @@ impl Config {
     for (mode, default_styles) in default_config.styles.iter() {
       let user_styles = cfg.styles.entry(*mode).or_default();
       for (style_key, style) in default_styles.iter() {
-        user_styles.entry(style_key.clone()).or_insert_with(|| style.clone());
+        user_styles.entry(style_key.clone()).or_insert_with(|| *style);
       }
     }
 
+    if cfg.auto_pause.is_none() {
+      cfg.auto_pause = default_config.auto_pause;
+    }
+
     Ok(cfg)
   }
+
+  pub fn auto_pause(&self) -> &[EventKind] {
+    self.auto_pause.as_deref().unwrap_or_default()
+  }
 }
 
 #[derive(Clone, Debug, Default, Deref, DerefMut)]
@@ -217,7 +228,7 @@ pub fn key_event_to_string(key_event: &KeyEvent) -> String {
       char = format!("f({c})");
       &char
     },
-    KeyCode::Char(c) if c == ' ' => "space",
+    KeyCode::Char(' ') => "space",
     KeyCode::Char(c) => {
       char = c.to_string();
       &char
@@ -443,6 +454,7 @@ mod tests {
   }
 
   #[test]
+  #[allow(clippy::identity_op)]
   fn test_parse_color_rgb() {
     let color = parse_color("rgb123");
     let expected = 16 + 1 * 36 + 2 * 6 + 3;
@@ -465,6 +477,17 @@ mod tests {
     Ok(())
   }
 
+  #[test]
+  fn test_config_auto_pause() -> Result<()> {
+    let c = Config::new()?;
+    assert!(c.auto_pause().contains(&EventKind::StellarWarning));
+    assert_eq!(
+      c.keybindings.get(&Mode::Home).unwrap().get(&parse_key_sequence("<2>").unwrap_or_default()).unwrap(),
+      &Action::SetGameSpeed(2)
+    );
+    Ok(())
+  }
+
   #[test]
   fn test_simple_keys() {
     assert_eq!(parse_key_event("a").unwrap(), KeyEvent::new(KeyCode::Char('a'), KeyModifiers::empty()));
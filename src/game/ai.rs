@@ -0,0 +1,610 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use lazy_static::lazy_static;
+use ratatui::style::Color;
+use serde::Deserialize;
+use strum::{Display, EnumString};
+use crate::game::body::BodyType;
+use crate::game::building::{check_building, BuildingDef};
+use crate::game::colony::stable_star;
+use crate::game::diplomacy::{accepts, check_proposal, military, opinion, Proposal, Stance, Treaty, REJECTED};
+use crate::game::empire::EmpireId;
+use crate::game::fleet::Fleet;
+use crate::game::galaxy::{BodyRef, SystemId};
+use crate::game::knowledge::KnowledgeLevel;
+use crate::game::narrative::{Effect, EventDef};
+use crate::game::order::Order;
+use crate::game::population::capacity;
+use crate::game::resources::{Resource, Resources};
+use crate::game::ship::ShipRole;
+use crate::game::state::GameState;
+use crate::game::system::System;
+use crate::game::tech::{Category, TechDef, TECHNOLOGIES};
+use crate::game::territory::{claim_cost, known_territory};
+use crate::game::turn::ScheduledEvent;
+
+const PERSONALITIES_DATA: &str = include_str!("../../assets/personalities.json5");
+
+lazy_static! {
+    /// Every personality defined in the data files
+    pub static ref PERSONALITIES: Vec<Personality> =
+        json5::from_str(PERSONALITIES_DATA).expect("personality definitions are valid");
+}
+
+/// Names and colours of the empires the computer plays, in the order they join a game
+pub const EMPIRES: [(&str, Color); 6] = [
+    ("Vask Dominion", Color::LightRed),
+    ("Aurelian Compact", Color::Yellow),
+    ("Thessari Collective", Color::LightMagenta),
+    ("Kor Hegemony", Color::LightGreen),
+    ("Ilyan Concord", Color::Cyan),
+    ("Merrow Syndicate", Color::Rgb(255, 150, 60)),
+];
+
+/// Influence a computer empire keeps back from claims when it cares nothing for expansion
+const INFLUENCE_RESERVE: f32 = 20.0;
+
+/// Light years that halve how attractive a planet is to settle
+const COLONY_DISTANCE_SCALE: f32 = 20.0;
+
+/// Turns between the proposals a computer empire makes to each of its contacts
+const DIPLOMACY_INTERVAL: u32 = 5;
+
+/// How much a computer empire has to want a war before it declares one, with the most
+/// aggressive temperament
+const WAR_THRESHOLD: f32 = 40.0;
+
+/// Proposals a computer empire considers making in peace, the most modest first
+const OVERTURES: [Proposal; 6] = [
+    Proposal::Treaty(Treaty::Trade),
+    Proposal::Treaty(Treaty::ResearchSharing),
+    Proposal::Treaty(Treaty::OpenBorders),
+    Proposal::NonAggression,
+    Proposal::Alliance,
+    Proposal::Federation,
+];
+
+/// How a computer empire weighs its options, as defined in the data files
+#[derive(Debug, Clone, Deserialize)]
+pub struct Personality {
+    pub id: String,
+    pub name: String,
+    pub expansion: f32,
+    pub economy: f32,
+    pub research: f32,
+    pub military: f32,
+    pub aggression: f32,
+    /// Technologies of the category are researched first
+    pub favourite: Category,
+}
+
+pub fn personality(id: &str) -> Option<&'static Personality> {
+    PERSONALITIES.iter().find(|p| p.id == id)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    /// Share of its income a computer empire gets on top
+    pub fn income_bonus(&self) -> f32 {
+        match self {
+            Difficulty::Easy => { 0.0 }
+            Difficulty::Normal => { 0.1 }
+            Difficulty::Hard => { 0.3 }
+        }
+    }
+
+    /// Turns between the plans a computer empire makes
+    pub fn planning_interval(&self) -> u32 {
+        match self {
+            Difficulty::Easy => { 2 }
+            Difficulty::Normal | Difficulty::Hard => { 1 }
+        }
+    }
+}
+
+/// The computer player of an empire
+#[derive(Debug, Clone)]
+pub struct Ai {
+    pub personality: &'static Personality,
+    pub difficulty: Difficulty,
+}
+
+impl Ai {
+    pub fn new(personality: &'static Personality, difficulty: Difficulty) -> Self {
+        Self { personality, difficulty }
+    }
+}
+
+/// What a fleet is worth in a fight: its firepower times how much punishment it takes
+pub fn strength(fleet: &Fleet) -> f32 {
+    fleet.ships.iter()
+        .map(|s| {
+            let stats = s.design.stats();
+            stats.damage * (stats.structure + stats.armour + stats.shield - s.damage) / 100.0
+        })
+        .sum()
+}
+
+/// Choice the computer makes in a narrative event: the one its personality values most, the first
+/// of them on a tie
+pub fn event_choice(ai: &Ai, event: &EventDef) -> usize {
+    let personality = ai.personality;
+    let value = |effect: &Effect| match effect {
+        Effect::Resources { amount } => {
+            amount.iter()
+                .map(|(r, a)| if r == Resource::Research { a * personality.research } else { a * personality.economy })
+                .sum()
+        }
+        Effect::Population { change } => { *change as f32 * 50.0 * personality.expansion }
+        Effect::Research { points } => { points * personality.research }
+        Effect::Building { .. } => { 100.0 * personality.economy }
+        // Curiosity, as nobody knows what comes of it
+        Effect::Event { .. } => { 50.0 * personality.research }
+    };
+    let mut res = (0, f32::NEG_INFINITY);
+    for (i, choice) in event.choices.iter().enumerate() {
+        let worth: f32 = choice.effects.iter().map(value).sum();
+        if worth > res.1 {
+            res = (i, worth);
+        }
+    }
+    res.0
+}
+
+/// Orders of one empire for the coming turn, worked out from what the empire knows
+struct Planner<'a> {
+    state: &'a GameState,
+    empire: EmpireId,
+    personality: &'static Personality,
+    /// What is left of the stockpile after the orders planned so far
+    budget: Resources,
+    /// Systems a fleet was already sent to settle or claim
+    targets: Vec<SystemId>,
+    orders: Vec<Order>,
+}
+
+impl<'a> Planner<'a> {
+    fn new(state: &'a GameState, empire: EmpireId, ai: &Ai) -> Self {
+        Self {
+            state,
+            empire,
+            personality: ai.personality,
+            budget: state.empires[empire].stockpile,
+            targets: Vec::new(),
+            orders: Vec::new(),
+        }
+    }
+
+    fn colonies(&self) -> Vec<BodyRef> {
+        self.state.galaxy.colonies()
+            .filter(|(_, b)| b.colony.as_ref().is_some_and(|c| c.owner == self.empire))
+            .map(|(r, _)| r)
+            .collect()
+    }
+
+    fn fleets(&self) -> impl Iterator<Item = &'a Fleet> {
+        let empire = self.empire;
+        self.state.fleets.iter().filter(move |f| f.owner == empire)
+    }
+
+    /// Systems the empire knows, as it last saw them, in the order of their ids
+    fn known_systems(&self) -> Vec<(SystemId, System)> {
+        let knowledge = &self.state.empires[self.empire].knowledge;
+        let mut res: Vec<(SystemId, System)> = knowledge.systems()
+            .filter(|(_, k)| k.level >= KnowledgeLevel::Explored)
+            .map(|(id, k)| (id, k.known_system()))
+            .collect();
+        res.sort_by_key(|(id, _)| *id);
+        res
+    }
+
+    /// Ships of the role the empire has in service and on order
+    fn ship_count(&self, role: ShipRole) -> usize {
+        let serving = self.fleets().flat_map(|f| &f.ships).filter(|s| s.role == role).count();
+        let ordered = self.colonies().iter()
+            .filter_map(|r| self.state.galaxy.body(*r)?.colony.as_ref())
+            .flat_map(|c| &c.shipyard_queue)
+            .filter(|e| ShipRole::of(&e.design) == role)
+            .count();
+        serving + ordered
+    }
+
+    fn research(&mut self) {
+        let research = &self.state.empires[self.empire].research;
+        if !research.queue.is_empty() {
+            return;
+        }
+        let favourite = self.personality.favourite;
+        let wishes = self.wishes();
+        // Technologies unlocking a wished building come first, then the favourite field, then the cheapest
+        let key = |t: &TechDef| (!t.buildings.iter().any(|b| wishes.contains(&b.as_str())), t.category != favourite);
+        let next = TECHNOLOGIES.iter()
+            .filter(|t| research.can_research(t))
+            .min_by(|a, b| key(a).cmp(&key(b)).then(a.cost.total_cmp(&b.cost)));
+        if let Some(tech) = next {
+            self.orders.push(Order::QueueResearch { tech: tech.id.clone() });
+        }
+    }
+
+    /// Buildings in the order the empire wants them, by what it lacks and what it cares for
+    fn wishes(&self) -> Vec<&'static str> {
+        let net = self.state.empires[self.empire].ledger.net();
+        let p = self.personality;
+        let has_shipyard = self.colonies().iter()
+            .filter_map(|r| self.state.galaxy.body(*r)?.colony.as_ref())
+            .any(|c| c.has_shipyard() || c.queue.iter().any(|q| q.building == "shipyard"));
+        let mut res = vec![
+            ("farm", if net.get(Resource::Food) < 0.0 { 10.0 } else { p.expansion }),
+            ("power_plant", if net.get(Resource::Energy) < 0.0 { 10.0 } else { p.economy }),
+            ("mine", if net.get(Resource::Minerals) < 2.0 { 5.0 } else { p.economy * 1.5 }),
+            ("shipyard", if has_shipyard { 0.0 } else { 6.0 }),
+            ("research_lab", p.research * 2.0),
+            ("research_complex", p.research * 2.0),
+            ("foundry", p.economy + p.military),
+            ("starbase", p.military),
+        ];
+        res.sort_by(|a, b| b.1.total_cmp(&a.1));
+        res.into_iter().filter(|(_, w)| *w > 0.0).map(|(id, _)| id).collect()
+    }
+
+    fn economy(&mut self) {
+        let wishes = self.wishes();
+        for colony in self.colonies() {
+            let body = self.state.galaxy.body(colony).unwrap();
+            if !body.colony.as_ref().unwrap().queue.is_empty() {
+                continue;
+            }
+            let affordable = |def: &BuildingDef| self.budget.covers(&def.cost);
+            let choice = wishes.iter()
+                .filter_map(|id| check_building(self.state, self.empire, colony, id).ok())
+                .find(|def| affordable(def));
+            if let Some(def) = choice {
+                self.budget -= def.cost;
+                self.orders.push(Order::Build { colony, building: def.id.clone() });
+            }
+        }
+    }
+
+    fn shipbuilding(&mut self) {
+        let owner = &self.state.empires[self.empire];
+        let p = self.personality;
+        let unsurveyed = owner.knowledge.systems().any(|(_, k)| k.level < KnowledgeLevel::Surveyed);
+        let warships = ((2.0 + self.state.turn as f32 / 10.0) * p.military * 2.0).round() as usize;
+        let colony_ships = 1 + p.expansion.floor() as usize;
+        let mut wanted = Vec::new();
+        if unsurveyed && self.ship_count(ShipRole::Survey) == 0 {
+            wanted.push(ShipRole::Survey);
+        }
+        if self.colony_target().is_some() && self.ship_count(ShipRole::ColonyShip) < colony_ships {
+            wanted.push(ShipRole::ColonyShip);
+        }
+        if self.ship_count(ShipRole::Warship) < warships {
+            wanted.push(ShipRole::Warship);
+        }
+
+        let yards = self.colonies().into_iter().filter(|r| {
+            let colony = self.state.galaxy.body(*r).unwrap().colony.as_ref().unwrap();
+            colony.has_shipyard() && colony.shipyard_queue.is_empty()
+        });
+        for (yard, role) in yards.zip(wanted) {
+            let Some(design) = owner.design_for(role) else { continue };
+            let cost = design.stats().cost;
+            if self.budget.covers(&cost) {
+                self.budget -= cost;
+                self.orders.push(Order::BuildShip { colony: yard, design: design.name.clone() });
+            }
+        }
+    }
+
+    /// The planet the empire would most like to settle next, among the ones it has explored
+    fn colony_target(&self) -> Option<BodyRef> {
+        let owner = &self.state.empires[self.empire];
+        let territory = known_territory(self.state, self.empire);
+        let capital = owner.capital;
+        let species = &owner.species;
+        self.known_systems().iter()
+            .filter(|(id, s)| {
+                s.owner().is_none_or(|o| o == self.empire)
+                    && stable_star(s)
+                    && territory[*id].is_none_or(|o| o == self.empire)
+                    && !self.targets.contains(id)
+            })
+            .flat_map(|(id, s)| s.bodies.iter().map(move |b| (BodyRef { system: *id, body: b.id }, b)))
+            .filter(|(r, b)| {
+                matches!(b.kind, BodyType::Planet(_))
+                    && species.habitability(b) > 0.0
+                    && b.colony.is_none()
+                    && capacity(b, species) > 0
+                    && !self.settling(*r)
+            })
+            .map(|(r, b)| {
+                let distance = self.state.galaxy.distance(capital, r.system);
+                (r, capacity(b, species) as f32 * species.habitability(b) / (1.0 + distance / COLONY_DISTANCE_SCALE))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(r, _)| r)
+    }
+
+    /// Whether colonists of the empire are already founding a colony on the body
+    fn settling(&self, target: BodyRef) -> bool {
+        self.state.schedule.iter().any(|(_, e)| {
+            matches!(e, ScheduledEvent::FoundColony { empire, target: t } if *empire == self.empire && *t == target)
+        })
+    }
+
+    /// Claims the system the fleet is in if the empire can spare the influence
+    fn claim(&mut self, fleet: &Fleet) {
+        let Some(system) = fleet.system() else { return };
+        let owner = &self.state.empires[self.empire];
+        let cost = claim_cost(self.state, self.empire);
+        let reserve = INFLUENCE_RESERVE * (1.0 - self.personality.expansion);
+        let Some(known) = owner.knowledge.system(system) else { return };
+        if known.known_system().owner().is_some()
+            || known.level < KnowledgeLevel::Explored
+            || known_territory(self.state, self.empire)[system].is_some_and(|o| o != self.empire)
+            || self.targets.contains(&system)
+            || self.budget.get(Resource::Influence) < cost + reserve
+        {
+            return;
+        }
+        // Only one claim a turn, as every claim makes the next one dearer
+        self.budget.add_amount(Resource::Influence, -self.budget.get(Resource::Influence));
+        self.targets.push(system);
+        self.orders.push(Order::BuildOutpost { fleet: fleet.id });
+    }
+
+    fn settle(&mut self, fleet: &Fleet) {
+        let Some(target) = self.colony_target() else { return };
+        self.targets.push(target.system);
+        if fleet.system() == Some(target.system) {
+            self.orders.push(Order::Colonize { fleet: fleet.id, target });
+        } else {
+            self.orders.push(Order::MoveFleet { fleet: fleet.id, destination: target.system });
+        }
+    }
+
+    fn explore(&mut self, fleet: &Fleet) {
+        let knowledge = &self.state.empires[self.empire].knowledge;
+        let Some(here) = fleet.system() else { return };
+        if knowledge.level(here) < KnowledgeLevel::Surveyed {
+            return;
+        }
+        let position = self.state.galaxy.positions[here];
+        let next = (0..self.state.galaxy.systems.len())
+            .filter(|s| knowledge.system(*s).is_some_and(|k| k.level < KnowledgeLevel::Surveyed))
+            .map(|s| {
+                let (x, y) = self.state.galaxy.positions[s];
+                (s, (x - position.0).powi(2) + (y - position.1).powi(2))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((destination, _)) = next {
+            self.orders.push(Order::MoveFleet { fleet: fleet.id, destination });
+        }
+    }
+
+    /// Sends a warship fleet against the nearest enemy it can beat, or home when there is none
+    fn wage_war(&mut self, fleet: &Fleet) {
+        let Some(here) = fleet.system() else { return };
+        let owner = &self.state.empires[self.empire];
+        let enemies: Vec<EmpireId> = owner.contacts.iter()
+            .copied()
+            .filter(|e| self.state.hostile(self.empire, *e))
+            .collect();
+        let ours = strength(fleet);
+        let courage = 0.5 + self.personality.aggression;
+        let theirs = |system: SystemId| -> f32 {
+            self.state.fleets.iter()
+                .filter(|f| enemies.contains(&f.owner) && f.system() == Some(system))
+                .filter(|f| owner.knowledge.is_detected(f.id))
+                .map(strength)
+                .sum()
+        };
+        // Enemy fleets the empire knows of first, then the systems of its enemies
+        let fleets = self.state.fleets.iter()
+            .filter(|f| enemies.contains(&f.owner) && owner.knowledge.is_detected(f.id))
+            .filter_map(|f| f.system());
+        let systems = self.known_systems().into_iter()
+            .filter(|(_, s)| s.owner().is_some_and(|o| enemies.contains(&o)))
+            .map(|(id, _)| id);
+        let mut targets: Vec<SystemId> = fleets.chain(systems).collect();
+        targets.sort_by(|a, b| self.state.galaxy.distance(here, *a).total_cmp(&self.state.galaxy.distance(here, *b)));
+        let target = targets.into_iter().find(|t| ours * courage > theirs(*t));
+        match target {
+            Some(target) if target != here => {
+                self.orders.push(Order::MoveFleet { fleet: fleet.id, destination: target });
+            }
+            None if here != owner.capital => {
+                self.orders.push(Order::MoveFleet { fleet: fleet.id, destination: owner.capital });
+            }
+            _ => {}
+        }
+    }
+
+    /// How much the empire wants a war with another: grudges, temperament and being the stronger
+    /// side speak for it, pacts against it
+    fn war_desire(&self, other: EmpireId) -> f32 {
+        let ours = military(self.state, self.empire) + 1.0;
+        let theirs = military(self.state, other) + 1.0;
+        let pact = match self.state.diplomacy.stance(self.empire, other) {
+            Stance::War | Stance::Peace => { 0.0 }
+            Stance::NonAggression => { 30.0 }
+            Stance::Alliance | Stance::Federation => { 100.0 }
+        };
+        self.personality.aggression * 60.0 - opinion(self.state, self.empire, other)
+            + ((ours / theirs - 1.0) * 20.0).clamp(-40.0, 40.0)
+            - pact
+            - WAR_THRESHOLD
+    }
+
+    /// What the empire puts to another this turn, if anything: peace once it had enough of a war,
+    /// war when it wants one, otherwise the first treaty it would accept itself. Nothing for a
+    /// while after the other side turned a proposal down
+    fn proposal(&self, other: EmpireId) -> Option<Proposal> {
+        let (state, empire) = (self.state, self.empire);
+        let rebuffed = state.diplomacy.modifiers(empire, other).iter().any(|m| m.reason == REJECTED);
+        if rebuffed || state.diplomacy.pending.iter().any(|p| p.from == empire && p.to == other) {
+            return None;
+        }
+        if state.diplomacy.stance(empire, other) == Stance::War {
+            return accepts(state, empire, other, &Proposal::Peace).then_some(Proposal::Peace);
+        }
+        if self.war_desire(other) > 0.0 && check_proposal(state, empire, other, &Proposal::DeclareWar).is_ok() {
+            return Some(Proposal::DeclareWar);
+        }
+        OVERTURES.into_iter()
+            .find(|p| check_proposal(state, empire, other, p).is_ok() && accepts(state, empire, other, p))
+    }
+
+    fn diplomacy(&mut self) {
+        if !(self.state.turn + self.empire as u32).is_multiple_of(DIPLOMACY_INTERVAL) {
+            return;
+        }
+        let mut contacts = self.state.empires[self.empire].contacts.clone();
+        contacts.sort();
+        for other in contacts {
+            if let Some(proposal) = self.proposal(other) {
+                self.orders.push(Order::Propose { to: other, proposal });
+            }
+        }
+    }
+
+    fn fleets_orders(&mut self) {
+        let fleets: Vec<&Fleet> = self.fleets().filter(|f| !f.is_moving() && f.transfer.is_none()).collect();
+        for fleet in fleets {
+            self.claim(fleet);
+            if fleet.has_role(&ShipRole::ColonyShip) {
+                self.settle(fleet);
+            } else if fleet.has_role(&ShipRole::Warship) {
+                self.wage_war(fleet);
+            } else if fleet.has_role(&ShipRole::Survey) {
+                self.explore(fleet);
+            }
+        }
+    }
+}
+
+/// Orders the computer gives an empire for the coming turn, nothing for an empire the player
+/// controls or one that is no more. The same state always yields the same orders
+pub fn plan(state: &GameState, empire: EmpireId) -> Vec<Order> {
+    let Some(ai) = &state.empires[empire].ai else { return Vec::new() };
+    if state.empires[empire].eliminated.is_some() {
+        return Vec::new();
+    }
+    if !state.turn.is_multiple_of(ai.difficulty.planning_interval()) {
+        return Vec::new();
+    }
+    let mut planner = Planner::new(state, empire, ai);
+    planner.research();
+    planner.diplomacy();
+    planner.economy();
+    planner.shipbuilding();
+    planner.fleets_orders();
+    planner.orders
+}
+
+/// Lets the computer issue the orders of every empire it plays, through the same orders the
+/// player gives
+pub fn ai_turn(state: &mut GameState) {
+    for empire in 0..state.empires.len() {
+        for order in plan(state, empire) {
+            state.issue_order(empire, order);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::clock::TimeMode;
+    use crate::game::state::Setup;
+    use crate::game::turn::end_turn;
+
+    fn game(seed: u64) -> GameState {
+        GameState::with_setup(seed, TimeMode::Turns, &Setup { opponents: 2, ..Setup::default() })
+    }
+
+    /// Everything about the empires that their plans can change
+    fn fingerprint(state: &GameState) -> Vec<String> {
+        let mut res: Vec<String> = state.empires.iter()
+            .map(|e| format!("{} {:?} {:?} {:?}", e.name, e.stockpile, e.systems, e.research.known))
+            .collect();
+        res.extend(state.fleets.iter().map(|f| format!("{} {} {:?} {}", f.id, f.owner, f.position(&state.galaxy), f.ships.len())));
+        res
+    }
+
+    #[test]
+    fn test_personalities() {
+        assert!(!PERSONALITIES.is_empty());
+        assert!(personality("warlord").is_some());
+        assert!(personality("pacifist").is_none());
+        assert_eq!("hard".parse::<Difficulty>(), Ok(Difficulty::Hard));
+    }
+
+    #[test]
+    fn test_setup() {
+        let state = game(3);
+        assert_eq!(state.empires.len(), 3);
+        assert!(state.empires[state.player].ai.is_none());
+        assert!(plan(&state, state.player).is_empty());
+        for empire in state.empires.iter().filter(|e| e.id != state.player) {
+            assert!(empire.ai.is_some());
+            let capital = &state.galaxy.systems[empire.capital];
+            assert!(capital.bodies.iter().any(|b| b.colony.as_ref().is_some_and(|c| c.owner == empire.id)));
+        }
+    }
+
+    #[test]
+    fn test_plan() {
+        let state = game(3);
+        let orders = plan(&state, 1);
+        assert!(orders.iter().any(|o| matches!(o, Order::QueueResearch { .. })));
+        assert!(orders.iter().any(|o| matches!(o, Order::MoveFleet { .. })));
+        assert_eq!(orders, plan(&state, 1));
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let mut first = game(5);
+        let mut second = game(5);
+        for _ in 0..20 {
+            end_turn(&mut first);
+            end_turn(&mut second);
+        }
+        assert_eq!(fingerprint(&first), fingerprint(&second));
+    }
+
+    #[test]
+    fn test_growth() {
+        let mut state = game(7);
+        for _ in 0..30 {
+            end_turn(&mut state);
+        }
+        for empire in state.empires.iter().filter(|e| e.ai.is_some()) {
+            assert!(!empire.research.known.is_empty(), "{} researched nothing", empire.name);
+            let colonies = state.galaxy.colonies().filter(|(_, b)| b.colony.as_ref().unwrap().owner == empire.id).count();
+            assert!(empire.systems.len() + colonies > 2, "{} did not expand", empire.name);
+        }
+    }
+}
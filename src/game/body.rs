@@ -15,11 +15,33 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use std::fmt::{Display, Formatter};
-use std::fs;
-use std::ops::Deref;
-use rand::{Rng, thread_rng};
+use std::f32::consts::TAU;
+use std::ops::{Deref, RangeInclusive};
+use rand::Rng;
 use rand::seq::IndexedRandom;
 use ratatui::style::Color;
+use serde::Deserialize;
+use crate::game::calendar::DAYS_PER_YEAR;
+use crate::game::colony::Colony;
+
+const NAMELIST: &str = include_str!("../../assets/system_namelist.txt");
+
+/// Picks a random name from the namelist
+pub fn random_name<R: Rng + ?Sized>(rng: &mut R) -> String {
+    let names: Vec<&str> = NAMELIST.lines().filter(|l| !l.is_empty()).collect();
+    String::from(*names.choose(rng).unwrap())
+}
+
+/// Mass of the Sun in the units used by [`Body::mass`] for stars
+pub const SOLAR_MASS: f32 = 1.98e18;
+/// Radius of the Sun in the units used by [`Body::radius`] for stars
+pub const SOLAR_RADIUS: f32 = 6.957e5;
+
+/// Radius of Earth in meters, which planet radii are given in
+pub const EARTH_RADIUS: f32 = 6.371e6;
+
+/// Astronomical unit in meters, a body orbiting at this distance takes a year to go around
+pub const AU: f32 = 1.496e11;
 
 #[derive(Clone)]
 pub enum BodyType {
@@ -27,7 +49,7 @@ pub enum BodyType {
     Planet(PlanetType),
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum StarType {
     O,
     B,
@@ -36,9 +58,15 @@ pub enum StarType {
     G,
     K,
     M,
+    Pulsar,
+    RedGiant,
+    WhiteDwarf,
+    BlackHole,
 }
 
-#[derive(Clone)]
+pub type BodyId = usize;
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
 pub enum PlanetType {
     AsteroidRing,
     Earthlike,
@@ -48,9 +76,9 @@ pub enum PlanetType {
     GasGiant,
 }
 
-impl Into<Color> for BodyType {
-    fn into(self) -> Color {
-        match self {
+impl From<BodyType> for Color {
+    fn from(value: BodyType) -> Self {
+        match value {
             BodyType::Planet(PlanetType::AsteroidRing) => { Color::Rgb(100, 100, 100) }
             BodyType::Planet(PlanetType::Earthlike) => { Color::LightBlue }
             BodyType::Planet(PlanetType::Ice) => { Color::LightCyan }
@@ -65,19 +93,59 @@ impl Into<Color> for BodyType {
             BodyType::Star(StarType::G) => { Color::Indexed(230) }
             BodyType::Star(StarType::K) => { Color::Indexed(216) }
             BodyType::Star(StarType::M) => { Color::Indexed(160) }
+            BodyType::Star(StarType::Pulsar) => { Color::Indexed(141) }
+            BodyType::Star(StarType::RedGiant) => { Color::Indexed(202) }
+            BodyType::Star(StarType::WhiteDwarf) => { Color::Indexed(255) }
+            BodyType::Star(StarType::BlackHole) => { Color::Indexed(240) }
         }
     }
 }
 
-#[derive(Clone)]
+impl PlanetType {
+    pub fn to_str(&self) -> String {
+        match self {
+            PlanetType::AsteroidRing => { String::from("Asteroid ring") }
+            PlanetType::Earthlike => { String::from("Earthlike planet") }
+            PlanetType::Ice => { String::from("Frozen planet") }
+            PlanetType::Rock => { String::from("Rocky planet") }
+            PlanetType::Desert => { String::from("Deserted planet") }
+            PlanetType::GasGiant => { String::from("Gas giant") }
+        }
+    }
+
+    /// How well suited the planet is for a colony, from 0 (uninhabitable) to 1 (perfect)
+    pub fn habitability(&self) -> f32 {
+        match self {
+            PlanetType::AsteroidRing => { 0.1 }
+            PlanetType::Earthlike => { 1.0 }
+            PlanetType::Ice => { 0.3 }
+            PlanetType::Rock => { 0.3 }
+            PlanetType::Desert => { 0.5 }
+            PlanetType::GasGiant => { 0.0 }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum PlanetZone {
     InnerRing,
     HabitableZone,
     OuterRing,
 }
 
+impl PlanetZone {
+    /// Range of orbit radii belonging to this zone, measured from the star's Roche limit
+    pub fn orbit_bounds(&self) -> RangeInclusive<f32> {
+        match self {
+            PlanetZone::InnerRing => { 2.9e10..=6.0e10 }
+            PlanetZone::HabitableZone => { 6.0e10..=1.2e11 }
+            PlanetZone::OuterRing => { 1.2e11..=2.0e11 }
+        }
+    }
+}
+
 impl StarType {
-    fn to_str(&self) -> String {
+    pub fn to_str(&self) -> String {
         match self {
             StarType::O => { "O".to_owned() }
             StarType::B => { "B".to_owned() }
@@ -86,34 +154,44 @@ impl StarType {
             StarType::G => { "G".to_owned() }
             StarType::K => { "K".to_owned() }
             StarType::M => { "M".to_owned() }
+            StarType::Pulsar => { "PSR".to_owned() }
+            StarType::RedGiant => { "RG".to_owned() }
+            StarType::WhiteDwarf => { "WD".to_owned() }
+            StarType::BlackHole => { "BH".to_owned() }
         }
     }
 }
 
 #[derive(Clone)]
 pub struct Body {
+    /// Identifies the body within its system, stays the same when other bodies are destroyed
+    pub id: BodyId,
     pub name: String,
     pub kind: BodyType,
     pub radius: f32,
     pub mass: f32,
     pub orbit_radius: Option<f32>,
-    pub orbit_period: Option<f32>,
+    pub zone: Option<PlanetZone>,
     pub satellites: Vec<Body>,
+    pub colony: Option<Colony>,
 }
 
 impl Body {
-    pub fn generate_star() -> Self {
-        let mut rng = rand::thread_rng();
-
-        let class: StarType = match rng.gen_range(0..=100) {
-            0..1     => StarType::O,  // Oh
-            1..2     => StarType::B,  // Be
-            2..3     => StarType::A,  // A
-            3..6     => StarType::F,  // Fine
-            6..14    => StarType::G,  // Girl
-            14..26   => StarType::K,  // Kiss
-            26..=100 => StarType::M,  // Me
-            _ => unreachable!()
+    pub fn generate_star<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        // Roughly one star in forty is the remnant of a supernova
+        let class: StarType = if rng.gen_ratio(1, 40) {
+            StarType::Pulsar
+        } else {
+            match rng.gen_range(0..=100) {
+                0..1     => StarType::O,  // Oh
+                1..2     => StarType::B,  // Be
+                2..3     => StarType::A,  // A
+                3..6     => StarType::F,  // Fine
+                6..14    => StarType::G,  // Girl
+                14..26   => StarType::K,  // Kiss
+                26..=100 => StarType::M,  // Me
+                _ => unreachable!()
+            }
         };
 
         let mass: f32 = rng.gen_range(
@@ -125,8 +203,12 @@ impl Body {
                 StarType::G => { 0.80..=1.040 }
                 StarType::K => { 0.45..=0.800 }
                 StarType::M => { 0.08..=0.450 }
+                StarType::Pulsar => { 1.10..=2.300 }
+                StarType::RedGiant => { 0.80..=8.000 }
+                StarType::WhiteDwarf => { 0.50..=1.400 }
+                StarType::BlackHole => { 3.00..=20.00 }
             }
-        ) * 1.98 * 10.0f32.powi(18);
+        ) * SOLAR_MASS;
 
         let radius: f32 = rng.gen_range(
             match class {
@@ -137,25 +219,29 @@ impl Body {
                 StarType::G => { 0.96..=1.150 }
                 StarType::K => { 0.70..=0.960 }
                 StarType::M => { 0.10..=0.700 }
+                StarType::Pulsar => { 1.4e-5..=2.2e-5 }
+                StarType::RedGiant => { 50.0..=150.0 }
+                StarType::WhiteDwarf => { 0.008..=0.020 }
+                StarType::BlackHole => { 1.3e-5..=8.5e-5 }
             }
-        ) * 6.957 * 10.0f32.powi(5);
+        ) * SOLAR_RADIUS;
 
         let name = format!("{}-{}", class.to_str(), rng.gen_range(10000..=999999)).to_owned();
 
         Body {
+            id: 0,
             name,
             kind: BodyType::Star(class),
             radius,
             mass,
             orbit_radius: None,
-            orbit_period: None,
+            zone: None,
             satellites: Vec::new(),
+            colony: None,
         }
     }
 
-    pub fn generate_planet(zone: &PlanetZone) -> Self {
-        let mut rng = rand::thread_rng();
-
+    pub fn generate_planet<R: Rng + ?Sized>(zone: &PlanetZone, rng: &mut R) -> Self {
         #[rustfmt::skip]
         let planet_type: PlanetType = match (zone, rng.gen_range(1..=100)) {
             (_,                         1..=5)    => { PlanetType::AsteroidRing },
@@ -177,7 +263,7 @@ impl Body {
         };
 
         let radius = match planet_type {
-            PlanetType::AsteroidRing => { -1.0 }
+            PlanetType::AsteroidRing => { 0.0 }
             PlanetType::Earthlike => { rng.gen_range(7.0..=17.0) * 1000.0 }
             PlanetType::Ice => { rng.gen_range(1.0..=10.0) * 1000.0 }
             PlanetType::Rock => { rng.gen_range(1.0..=10.0) * 1000.0 }
@@ -190,47 +276,81 @@ impl Body {
             _ => { rng.gen_range(3.5..=5.4) * 1000.0 }
         };
         
-        let mass = 4.0 * std::f32::consts::PI * f32::powi(radius, 2) * density;
+        // A ring is spread along its orbit rather than a sphere, so its mass is not derived from
+        // a radius
+        let mass = match planet_type {
+            PlanetType::AsteroidRing => { rng.gen_range(1.0..=30.0) * 10.0f32.powi(15) }
+            _ => { 4.0 * std::f32::consts::PI * f32::powi(radius, 2) * density }
+        };
 
-        let file_contents: String = fs::read_to_string("./assets/system_namelist.txt").unwrap();
-        let names: Vec<&str> = file_contents.split("\n").collect();
-        let name: &str = *names.choose(&mut thread_rng()).unwrap();
+        let name = random_name(rng);
 
         Body {
-            name: String::from(name),
+            id: 0,
+            name,
             kind: BodyType::Planet(planet_type),
             radius,
             mass,
 
             orbit_radius: None,
-            orbit_period: None,
+            zone: Some(zone.clone()),
             satellites: Vec::new(),
+            colony: None,
         }
     }
 
-    pub fn generate_planet_with_count(planet_zone: &PlanetZone, count: i32) -> Vec<Body> {
+    pub fn generate_planet_with_count<R: Rng + ?Sized>(
+        planet_zone: &PlanetZone,
+        count: i32,
+        rng: &mut R,
+    ) -> Vec<Body> {
         let mut res: Vec<Body> = Vec::new();
         for i in 0..count {
-            res.push(Self::generate_planet(planet_zone));
+            res.push(Self::generate_planet(planet_zone, rng));
         }
         res
     }
 
-    fn get_class_as_string(&self) -> String {
+    pub fn get_class_as_string(&self) -> String {
         match self.clone().kind {
+            BodyType::Star(StarType::Pulsar) => { String::from("Pulsar") }
+            BodyType::Star(StarType::RedGiant) => { String::from("Red giant") }
+            BodyType::Star(StarType::WhiteDwarf) => { String::from("White dwarf") }
+            BodyType::Star(StarType::BlackHole) => { String::from("Black hole") }
             BodyType::Star(class) => {
-                String::from(class.to_str() + " class star")
-            }
-            BodyType::Planet(kind) => {
-                match kind {
-                    PlanetType::AsteroidRing => { String::from("Asteroid ring") }
-                    PlanetType::Earthlike => { String::from("Earthlike planet") }
-                    PlanetType::Ice => { String::from("Frozen planet") }
-                    PlanetType::Rock => { String::from("Rocky planet") }
-                    PlanetType::Desert => { String::from("Deserted planet") }
-                    PlanetType::GasGiant => { String::from("Gas giant") }
-                }
+                class.to_str() + " class star"
             }
+            BodyType::Planet(kind) => { kind.to_str() }
+        }
+    }
+
+    /// Radius relative to Earth, between a quarter and twice its size
+    pub fn relative_size(&self) -> f32 {
+        (self.radius / EARTH_RADIUS).clamp(0.25, 2.0)
+    }
+
+    /// Days the body takes to go around its star, `None` for stars
+    pub fn orbit_days(&self) -> Option<f32> {
+        self.orbit_radius.map(|r| DAYS_PER_YEAR as f32 * (r / AU).powf(1.5))
+    }
+
+    /// Position relative to the star in meters, the given number of days into the game. Every body
+    /// starts at an angle derived from its orbit radius, so that no extra state is needed
+    pub fn orbital_position(&self, days: u64) -> (f32, f32) {
+        let (Some(radius), Some(period)) = (self.orbit_radius, self.orbit_days()) else {
+            return (0.0, 0.0);
+        };
+        let start = (radius / 1.0e7).fract();
+        let turns = (days as f32 / period).fract();
+        let angle = (start + turns) * TAU;
+        (radius * angle.cos(), radius * angle.sin())
+    }
+
+    /// Habitability of the body, zero for stars
+    pub fn habitability(&self) -> f32 {
+        match &self.kind {
+            BodyType::Planet(planet_type) => { planet_type.habitability() }
+            BodyType::Star(_) => { 0.0 }
         }
     }
 
@@ -244,4 +364,92 @@ impl Body {
 
         res
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    const SEEDS: u64 = 500;
+
+    #[test]
+    fn test_star_mass_and_radius_positive() {
+        for seed in 0..SEEDS {
+            let star = Body::generate_star(&mut StdRng::seed_from_u64(seed));
+            assert!(star.mass > 0.0, "seed {seed}: star mass {}", star.mass);
+            assert!(star.radius > 0.0, "seed {seed}: star radius {}", star.radius);
+            assert!(star.zone.is_none());
+        }
+    }
+
+    #[test]
+    fn test_planet_mass_positive() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for zone in [PlanetZone::InnerRing, PlanetZone::HabitableZone, PlanetZone::OuterRing] {
+            for _ in 0..SEEDS {
+                let planet = Body::generate_planet(&zone, &mut rng);
+                assert!(planet.mass > 0.0, "{}: mass {}", planet.name, planet.mass);
+                assert!(planet.radius >= 0.0, "{}: radius {}", planet.name, planet.radius);
+                assert_eq!(planet.zone, Some(zone.clone()));
+                assert!(!planet.name.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_asteroid_ring_mass_not_negative() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let rings = (0..SEEDS * 10)
+            .map(|_| Body::generate_planet(&PlanetZone::OuterRing, &mut rng))
+            .filter(|p| matches!(p.kind, BodyType::Planet(PlanetType::AsteroidRing)))
+            .collect::<Vec<_>>();
+        assert!(!rings.is_empty());
+        for ring in rings {
+            assert!(ring.mass >= 0.0, "{}: mass {}", ring.name, ring.mass);
+        }
+    }
+
+    #[test]
+    fn test_planet_types_match_zone() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..SEEDS {
+            let inner = Body::generate_planet(&PlanetZone::InnerRing, &mut rng);
+            assert!(!matches!(
+                inner.kind,
+                BodyType::Planet(PlanetType::GasGiant | PlanetType::Ice | PlanetType::Earthlike)
+            ));
+            let outer = Body::generate_planet(&PlanetZone::OuterRing, &mut rng);
+            assert!(!matches!(outer.kind, BodyType::Planet(PlanetType::Earthlike)));
+        }
+    }
+
+    #[test]
+    fn test_same_seed_same_star() {
+        let a = Body::generate_star(&mut StdRng::seed_from_u64(42));
+        let b = Body::generate_star(&mut StdRng::seed_from_u64(42));
+        assert_eq!(a.make_info(), b.make_info());
+    }
+
+    #[test]
+    fn test_orbital_position() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut planet = Body::generate_planet(&PlanetZone::HabitableZone, &mut rng);
+        planet.orbit_radius = Some(AU);
+        assert!((planet.orbit_days().unwrap() - DAYS_PER_YEAR as f32).abs() < 1.0);
+
+        let start = planet.orbital_position(0);
+        let later = planet.orbital_position(DAYS_PER_YEAR / 4);
+        for (x, y) in [start, later] {
+            assert!(((x * x + y * y).sqrt() / AU - 1.0).abs() < 1e-3);
+        }
+        // A quarter of a year is a quarter of the orbit
+        let dot = (start.0 * later.0 + start.1 * later.1) / (AU * AU);
+        assert!(dot.abs() < 1e-2);
+
+        let star = Body::generate_star(&mut rng);
+        assert_eq!(star.orbital_position(100), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,330 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use strum::Display;
+use crate::game::body::{Body, BodyType, PlanetType};
+use crate::game::empire::EmpireId;
+use crate::game::galaxy::BodyRef;
+use crate::game::population::{Job, Workforce};
+use crate::game::resources::Resources;
+use crate::game::state::GameState;
+use crate::game::turn::{EventKind, Phase, TurnReport};
+
+const BUILDINGS_DATA: &str = include_str!("../../assets/buildings.json5");
+
+lazy_static! {
+    /// Every building and district defined in the data files
+    pub static ref BUILDINGS: Vec<BuildingDef> =
+        json5::from_str(BUILDINGS_DATA).expect("building definitions are valid");
+}
+
+/// Jobs every colony has, before anything is built
+pub const BASE_JOB_SLOTS: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Deserialize)]
+pub enum BuildingKind {
+    District,
+    Building,
+}
+
+/// A kind of building, as defined in the data files
+#[derive(Debug, Clone, Deserialize)]
+pub struct BuildingDef {
+    pub id: String,
+    pub name: String,
+    pub kind: BuildingKind,
+    pub cost: Resources,
+    /// Turns it takes to construct
+    pub build_time: u32,
+    #[serde(default)]
+    pub upkeep: Resources,
+    #[serde(default)]
+    pub production: Resources,
+    /// Job slots by the name of the job
+    #[serde(default)]
+    jobs: HashMap<String, u32>,
+    /// Ids of buildings that have to stand in the colony first
+    #[serde(default)]
+    pub prerequisites: Vec<String>,
+    /// Planet types the building can stand on, every type if `None`
+    #[serde(default)]
+    pub planets: Option<Vec<PlanetType>>,
+    /// Light years
+    #[serde(default)]
+    pub sensor_range: f32,
+    #[serde(default)]
+    pub sensor_strength: f32,
+}
+
+impl BuildingDef {
+    pub fn job_slots(&self) -> Workforce {
+        let mut res = Workforce::default();
+        for (job, slots) in &self.jobs {
+            res.set(job.parse().expect("job names in building definitions are valid"), *slots);
+        }
+        res
+    }
+
+    pub fn allowed_on(&self, planet: &PlanetType) -> bool {
+        self.planets.as_ref().is_none_or(|p| p.contains(planet))
+    }
+}
+
+pub fn building(id: &str) -> Option<&'static BuildingDef> {
+    BUILDINGS.iter().find(|b| b.id == id)
+}
+
+/// A building waiting in the construction queue of a colony
+#[derive(Debug, Clone, PartialEq)]
+pub struct Construction {
+    pub building: String,
+    /// Turns of construction done so far
+    pub progress: u32,
+}
+
+/// Number of buildings of the kind the body has room for
+pub fn slots(body: &Body, kind: BuildingKind) -> u32 {
+    let BodyType::Planet(planet) = &body.kind else { return 0 };
+    let size = body.relative_size();
+    match (kind, planet) {
+        (_, PlanetType::GasGiant) => { 0 }
+        (BuildingKind::District, PlanetType::AsteroidRing) => { 3 }
+        (BuildingKind::Building, PlanetType::AsteroidRing) => { 1 }
+        (BuildingKind::District, _) => {
+            let terrain = match planet {
+                PlanetType::Earthlike => { 1.0 }
+                PlanetType::Desert => { 0.8 }
+                PlanetType::Rock => { 0.7 }
+                _ => { 0.6 }
+            };
+            ((size * 10.0 * terrain).round() as u32).max(2)
+        }
+        (BuildingKind::Building, _) => { ((size * 4.0).round() as u32).clamp(1, 8) }
+    }
+}
+
+/// Buildings of the kind that stand on the body or are queued there
+pub fn used_slots(body: &Body, kind: BuildingKind) -> u32 {
+    let Some(colony) = &body.colony else { return 0 };
+    colony.buildings.iter()
+        .chain(colony.queue.iter().map(|c| &c.building))
+        .filter(|id| building(id).is_some_and(|b| b.kind == kind))
+        .count() as u32
+}
+
+/// Checks that the empire can build the building in its colony, short of paying for it.
+/// Returns the building, or why it can not be built
+pub fn check_building(
+    state: &GameState,
+    empire: EmpireId,
+    target: BodyRef,
+    id: &str,
+) -> Result<&'static BuildingDef, String> {
+    let Some(def) = building(id) else {
+        return Err(format!("There is no such building as {id}"));
+    };
+    let Some(body) = state.galaxy.body(target) else {
+        return Err("The colony no longer exists".to_owned());
+    };
+    let Some(colony) = body.colony.as_ref().filter(|c| c.owner == empire) else {
+        return Err(format!("{} is not a colony of the empire", body.name));
+    };
+    let BodyType::Planet(planet) = &body.kind else {
+        return Err(format!("{} can not hold buildings", body.name));
+    };
+    if !state.empires[empire].research.building_unlocked(id) {
+        return Err(format!("A {} needs a technology that is not researched yet", def.name));
+    }
+    if !def.allowed_on(planet) {
+        return Err(format!("A {} can not be built on {}", def.name, body.name));
+    }
+    if let Some(missing) = def.prerequisites.iter().find(|p| !colony.buildings.contains(p)) {
+        let missing = building(missing).map_or(missing.as_str(), |b| b.name.as_str());
+        return Err(format!("A {} on {} needs a {missing} first", def.name, body.name));
+    }
+    if used_slots(body, def.kind) >= slots(body, def.kind) {
+        return Err(format!("{} has no free {} slots", body.name, def.kind.to_string().to_lowercase()));
+    }
+    Ok(def)
+}
+
+/// Queues a building in a colony of the empire, paying for it from the stockpile. Returns a
+/// message for the report, or why it can not be built
+pub fn queue_building(
+    state: &mut GameState,
+    empire: EmpireId,
+    target: BodyRef,
+    id: &str,
+) -> Result<String, String> {
+    let def = check_building(state, empire, target, id)?;
+    let name = state.galaxy.body(target).unwrap().name.clone();
+
+    let owner = &mut state.empires[empire];
+    if !owner.stockpile.covers(&def.cost) {
+        return Err(format!("{} can not afford a {}", owner.name, def.name));
+    }
+    owner.stockpile -= def.cost;
+
+    let colony = state.galaxy.body_mut(target).unwrap().colony.as_mut().unwrap();
+    colony.queue.push(Construction { building: def.id.clone(), progress: 0 });
+    Ok(format!("Construction of a {} started on {name}", def.name))
+}
+
+/// Removes an entry from the construction queue of a colony and refunds its cost
+pub fn cancel_construction(
+    state: &mut GameState,
+    empire: EmpireId,
+    target: BodyRef,
+    index: usize,
+) -> Result<String, String> {
+    let Some(body) = state.galaxy.body_mut(target) else {
+        return Err("The colony no longer exists".to_owned());
+    };
+    let name = body.name.clone();
+    let Some(colony) = body.colony.as_mut().filter(|c| c.owner == empire) else {
+        return Err(format!("{name} is not a colony of the empire"));
+    };
+    if index >= colony.queue.len() {
+        return Err(format!("Nothing to cancel on {name}"));
+    }
+    let def = building(&colony.queue.remove(index).building).unwrap();
+    state.empires[empire].stockpile += def.cost;
+    Ok(format!("Construction of a {} on {name} was cancelled", def.name))
+}
+
+/// Advances the first entry of every construction queue by a turn
+pub fn construction(state: &mut GameState, report: &mut TurnReport) {
+    let colonies: Vec<BodyRef> = state.galaxy.colonies().map(|(r, _)| r).collect();
+    for target in colonies {
+        let body = state.galaxy.body_mut(target).unwrap();
+        let name = body.name.clone();
+        let colony = body.colony.as_mut().unwrap();
+        let Some(current) = colony.queue.first_mut() else { continue };
+        current.progress += 1;
+        let def = building(&current.building).unwrap();
+        if current.progress >= def.build_time {
+            colony.queue.remove(0);
+            colony.buildings.push(def.id.clone());
+            colony.fill_jobs();
+            if colony.owner == state.player {
+                report.add(Phase::Production, EventKind::BuildingCompleted, format!(
+                    "A {} was completed on {name}",
+                    def.name
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::body::{StarType, EARTH_RADIUS};
+    use crate::game::resources::Resource;
+    use crate::game::turn::end_turn;
+
+    use super::*;
+
+    fn homeworld(seed: u64) -> (GameState, BodyRef) {
+        let mut state = GameState::new(seed);
+        let home = state.galaxy.colonies().next().unwrap().0;
+        // Room for more than the starting buildings
+        let body = state.galaxy.body_mut(home).unwrap();
+        body.kind = BodyType::Planet(PlanetType::Earthlike);
+        body.radius = EARTH_RADIUS;
+        for resource in Resource::ALL {
+            state.empires[0].stockpile.set(resource, 1000.0);
+        }
+        (state, home)
+    }
+
+    #[test]
+    fn test_definitions() {
+        assert!(!BUILDINGS.is_empty());
+        for def in BUILDINGS.iter() {
+            assert!(def.build_time > 0, "{} is built instantly", def.id);
+            for prerequisite in &def.prerequisites {
+                assert!(building(prerequisite).is_some(), "{} needs unknown {prerequisite}", def.id);
+            }
+        }
+        assert_eq!(building("mine").unwrap().job_slots().get(Job::Miner), 3);
+    }
+
+    #[test]
+    fn test_slots() {
+        let (state, home) = homeworld(1);
+        let mut body = state.galaxy.body(home).unwrap().clone();
+        body.kind = BodyType::Planet(PlanetType::Earthlike);
+        body.radius = EARTH_RADIUS;
+        let earth = slots(&body, BuildingKind::District);
+        body.radius = EARTH_RADIUS * 1.5;
+        assert!(slots(&body, BuildingKind::District) > earth);
+        body.kind = BodyType::Planet(PlanetType::Rock);
+        assert!(slots(&body, BuildingKind::District) < earth * 3 / 2);
+        body.kind = BodyType::Planet(PlanetType::GasGiant);
+        assert_eq!(slots(&body, BuildingKind::Building), 0);
+    }
+
+    #[test]
+    fn test_construction() {
+        let (mut state, home) = homeworld(2);
+        let player = state.player;
+        let before = state.player_empire().stockpile;
+        let count = state.galaxy.body(home).unwrap().colony.as_ref().unwrap().buildings.len();
+
+        assert!(queue_building(&mut state, player, home, "research_lab").is_ok());
+        assert_eq!(state.player_empire().stockpile, before - building("research_lab").unwrap().cost);
+
+        let mut completed = false;
+        for _ in 0..building("research_lab").unwrap().build_time {
+            completed |= end_turn(&mut state).entries.iter().any(|e| e.kind == EventKind::BuildingCompleted);
+        }
+        assert!(completed);
+        let colony = state.galaxy.body(home).unwrap().colony.as_ref().unwrap();
+        assert_eq!(colony.buildings.len(), count + 1);
+        assert!(colony.queue.is_empty());
+    }
+
+    #[test]
+    fn test_prerequisites_and_cost() {
+        let (mut state, home) = homeworld(3);
+        let player = state.player;
+        state.galaxy.body_mut(home).unwrap().colony.as_mut().unwrap().buildings.clear();
+        assert!(queue_building(&mut state, player, home, "research_complex").is_err());
+        assert!(queue_building(&mut state, player, home, "no_such_building").is_err());
+
+        state.empires[0].stockpile = Resources::default();
+        assert!(queue_building(&mut state, player, home, "research_lab").is_err());
+
+        // Only planets hold buildings, whatever the state says
+        let (mut state, home) = homeworld(3);
+        state.galaxy.body_mut(home).unwrap().kind = BodyType::Star(StarType::G);
+        assert!(queue_building(&mut state, player, home, "mine").is_err());
+    }
+
+    #[test]
+    fn test_cancel_refunds() {
+        let (mut state, home) = homeworld(4);
+        let player = state.player;
+        let before = state.player_empire().stockpile;
+        queue_building(&mut state, player, home, "mine").unwrap();
+        assert!(cancel_construction(&mut state, player, home, 0).is_ok());
+        assert_eq!(state.player_empire().stockpile, before);
+        assert!(cancel_construction(&mut state, player, home, 0).is_err());
+    }
+}
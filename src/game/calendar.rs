@@ -0,0 +1,162 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt::{Display, Formatter};
+
+/// Days in a month of the game calendar
+pub const DAYS_PER_MONTH: u64 = 30;
+/// Months in a year of the game calendar
+pub const MONTHS_PER_YEAR: u64 = 12;
+/// Days in a year of the game calendar
+pub const DAYS_PER_YEAR: u64 = DAYS_PER_MONTH * MONTHS_PER_YEAR;
+/// Seconds in a day, used to relate game dates to orbital mechanics
+pub const SECONDS_PER_DAY: f64 = 86_400.0;
+/// Year the game starts in
+pub const START_YEAR: u64 = 2200;
+
+/// A day of the game calendar. The calendar has twelve months of thirty days each, so that a turn
+/// is always exactly one month
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    /// Days since the start of the game
+    days: u64,
+}
+
+impl Date {
+    pub fn from_days(days: u64) -> Self {
+        Self { days }
+    }
+
+    /// Days since the start of the game
+    pub fn days(&self) -> u64 {
+        self.days
+    }
+
+    /// Seconds since the start of the game
+    pub fn seconds(&self) -> f64 {
+        self.days as f64 * SECONDS_PER_DAY
+    }
+
+    /// Years since the start of the game, including the fraction of the current one
+    pub fn years(&self) -> f64 {
+        self.days as f64 / DAYS_PER_YEAR as f64
+    }
+
+    pub fn year(&self) -> u64 {
+        START_YEAR + self.days / DAYS_PER_YEAR
+    }
+
+    /// Month of the year, starting from 1
+    pub fn month(&self) -> u64 {
+        self.days % DAYS_PER_YEAR / DAYS_PER_MONTH + 1
+    }
+
+    /// Day of the month, starting from 1
+    pub fn day(&self) -> u64 {
+        self.days % DAYS_PER_MONTH + 1
+    }
+
+    pub fn is_first_of_month(&self) -> bool {
+        self.days.is_multiple_of(DAYS_PER_MONTH)
+    }
+
+    pub fn plus_days(&self, days: u64) -> Self {
+        Self { days: self.days + days }
+    }
+
+    pub fn advance(&mut self, days: u64) {
+        self.days += days;
+    }
+
+    /// Days left until the given date, zero if it has already passed
+    pub fn days_until(&self, other: Date) -> u64 {
+        other.days.saturating_sub(self.days)
+    }
+}
+
+impl Display for Date {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{:02}.{:02}", self.year(), self.month(), self.day())
+    }
+}
+
+/// Things to happen on a given date, such as "in 30 days"
+#[derive(Clone, Debug)]
+pub struct Schedule<T> {
+    entries: Vec<(Date, T)>,
+}
+
+impl<T> Default for Schedule<T> {
+    fn default() -> Self {
+        Self { entries: Vec::new() }
+    }
+}
+
+impl<T> Schedule<T> {
+    pub fn at(&mut self, date: Date, item: T) {
+        // Items due on the same day keep the order they were scheduled in
+        let index = self.entries.partition_point(|(d, _)| *d <= date);
+        self.entries.insert(index, (date, item));
+    }
+
+    pub fn after(&mut self, now: Date, days: u64, item: T) {
+        self.at(now.plus_days(days), item);
+    }
+
+    /// Removes and returns everything due on or before `now`, earliest first
+    pub fn take_due(&mut self, now: Date) -> Vec<T> {
+        let count = self.entries.partition_point(|(d, _)| *d <= now);
+        self.entries.drain(..count).map(|(_, item)| item).collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(Date, T)> {
+        self.entries.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_parts() {
+        let date = Date::default();
+        assert_eq!(date.to_string(), "2200.01.01");
+        assert_eq!(Date::from_days(29).to_string(), "2200.01.30");
+        assert_eq!(Date::from_days(30).to_string(), "2200.02.01");
+        assert_eq!(Date::from_days(DAYS_PER_YEAR + 45).to_string(), "2201.02.16");
+        assert_eq!(Date::from_days(DAYS_PER_YEAR).years(), 1.0);
+        assert_eq!(Date::from_days(2).seconds(), 2.0 * SECONDS_PER_DAY);
+    }
+
+    #[test]
+    fn test_schedule_order() {
+        let now = Date::default();
+        let mut schedule = Schedule::default();
+        schedule.after(now, 30, "b");
+        schedule.after(now, 10, "a");
+        schedule.after(now, 30, "c");
+
+        assert!(schedule.take_due(now.plus_days(9)).is_empty());
+        assert_eq!(schedule.take_due(now.plus_days(10)), vec!["a"]);
+        assert_eq!(schedule.take_due(now.plus_days(100)), vec!["b", "c"]);
+        assert!(schedule.is_empty());
+    }
+}
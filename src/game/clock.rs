@@ -0,0 +1,140 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::game::state::GameState;
+use crate::game::turn::{advance_days, EventKind, TurnReport};
+
+/// In-game days passing each real second at speed x1
+pub const DAYS_PER_SECOND: f64 = 1.0;
+
+/// Speed multipliers the real-time clock can run at
+pub const SPEEDS: [u32; 3] = [1, 2, 5];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TimeMode {
+    /// Time only moves when the player ends the turn
+    Turns,
+    /// Time flows continuously unless the game is paused
+    RealTime,
+}
+
+/// Controls how game time moves forward
+#[derive(Clone, Debug)]
+pub struct Clock {
+    pub mode: TimeMode,
+    pub paused: bool,
+    pub speed: u32,
+    /// Fraction of a day accumulated by the previous ticks
+    carry: f64,
+}
+
+impl Clock {
+    pub fn new(mode: TimeMode) -> Self {
+        Self {
+            mode,
+            // A real-time game starts paused to give the player a chance to look around
+            paused: true,
+            speed: SPEEDS[0],
+            carry: 0.0,
+        }
+    }
+
+    pub fn set_speed(&mut self, speed: u32) {
+        if SPEEDS.contains(&speed) {
+            self.speed = speed;
+        }
+    }
+
+    /// Lets `seconds` of real time pass and returns how many whole days passed in the game
+    pub fn advance(&mut self, seconds: f64) -> u64 {
+        if self.paused || self.mode != TimeMode::RealTime {
+            return 0;
+        }
+        self.carry += seconds * DAYS_PER_SECOND * f64::from(self.speed);
+        let days = self.carry.floor();
+        self.carry -= days;
+        days as u64
+    }
+}
+
+/// Advances a real-time game by `seconds` of real time. The clock pauses itself when one of the
+/// turns processed in the meantime reported an event of a kind listed in `auto_pause`
+pub fn tick(state: &mut GameState, seconds: f64, auto_pause: &[EventKind]) -> Vec<TurnReport> {
+    // Time stands still once the game is over
+    if state.outcome.is_some() {
+        return Vec::new();
+    }
+    let days = state.clock.advance(seconds);
+    let seen = state.pending_report.entries.len();
+    let reports = advance_days(state, days);
+    // Entries of the turn still in progress count as well, so that the game pauses right away
+    let pending = &state.pending_report.entries[if reports.is_empty() { seen } else { 0 }..];
+    let pause = reports.iter()
+        .flat_map(|r| r.entries.iter())
+        .chain(pending.iter())
+        .any(|e| auto_pause.contains(&e.kind));
+    if pause {
+        state.clock.paused = true;
+    }
+    reports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_speed() {
+        let mut clock = Clock::new(TimeMode::RealTime);
+        assert_eq!(clock.advance(10.0), 0);
+
+        clock.paused = false;
+        assert_eq!(clock.advance(0.5), 0);
+        assert_eq!(clock.advance(0.5), 1);
+
+        clock.set_speed(5);
+        assert_eq!(clock.advance(2.0), 10);
+        clock.set_speed(3);
+        assert_eq!(clock.speed, 5);
+    }
+
+    #[test]
+    fn test_turn_mode_ignores_ticks() {
+        let mut clock = Clock::new(TimeMode::Turns);
+        clock.paused = false;
+        assert_eq!(clock.advance(100.0), 0);
+    }
+
+    #[test]
+    fn test_auto_pause() {
+        let mut state = GameState::new(0);
+        state.clock = Clock::new(TimeMode::RealTime);
+        state.clock.paused = false;
+        state.clock.set_speed(5);
+        let mut seconds = 0.0;
+        let mut paused_by = None;
+        while seconds < 20000.0 {
+            let reports = tick(&mut state, 1.0, &[EventKind::SolarFlare]);
+            seconds += 1.0;
+            if state.clock.paused {
+                paused_by = reports.last().cloned();
+                break;
+            }
+        }
+        let report = paused_by.expect("a solar flare should happen eventually");
+        assert!(report.entries.iter().any(|e| e.kind == EventKind::SolarFlare));
+    }
+}
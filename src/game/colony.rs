@@ -0,0 +1,349 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::game::body::{BodyId, BodyType, PlanetType};
+use crate::game::calendar::Date;
+use crate::game::empire::EmpireId;
+use crate::game::fleet::FleetId;
+use crate::game::galaxy::BodyRef;
+use crate::game::building::{building, Construction, BASE_JOB_SLOTS, BUILDINGS};
+use crate::game::population::{capacity, Job, Workforce, FOOD_PER_POP};
+use crate::game::resources::Resources;
+use crate::game::ship::ShipRole;
+use crate::game::shipyard::{ShipConstruction, SHIPYARD};
+use crate::game::species::Species;
+use crate::game::state::GameState;
+use crate::game::system::System;
+use crate::game::tech::{upkeep_bonus, Modifier};
+use crate::game::turn::ScheduledEvent;
+
+/// Days it takes to found a colony on a perfectly habitable planet
+const BASE_FOUNDING_DAYS: f32 = 120.0;
+
+/// Population a freshly founded colony starts with
+pub const STARTING_POPULATION: u32 = 1;
+
+/// Population of the colony every empire starts the game with, if the homeworld can hold it
+pub const HOMEWORLD_POPULATION: u32 = 10;
+
+/// Buildings standing on the homeworld at the start of the game
+pub const HOMEWORLD_BUILDINGS: [&str; 5] = ["farm", "farm", "mine", "power_plant", "research_lab"];
+
+/// Light years every colony sees, before any building improves its sensors
+pub const COLONY_SENSOR_RANGE: f32 = 10.0;
+
+pub const COLONY_SENSOR_STRENGTH: f32 = 1.0;
+
+/// Game years a star must stay in its current stage for its system to be worth settling
+pub const SETTLEMENT_HORIZON: f64 = 1000.0;
+
+/// A settlement of an empire on a planet
+#[derive(Clone, Debug)]
+pub struct Colony {
+    pub owner: EmpireId,
+    pub founded: Date,
+    /// Number of population units
+    pub population: u32,
+    /// Progress towards the next population unit, negative when the colony is shrinking
+    pub growth: f32,
+    pub workforce: Workforce,
+    /// Ids of the buildings standing in the colony
+    pub buildings: Vec<String>,
+    pub queue: Vec<Construction>,
+    pub shipyard_queue: Vec<ShipConstruction>,
+}
+
+impl Colony {
+    pub fn new(owner: EmpireId, founded: Date) -> Self {
+        let mut res = Self {
+            owner,
+            founded,
+            population: 0,
+            growth: 0.0,
+            workforce: Workforce::default(),
+            buildings: Vec::new(),
+            queue: Vec::new(),
+            shipyard_queue: Vec::new(),
+        };
+        for _ in 0..STARTING_POPULATION {
+            res.add_pop();
+        }
+        res
+    }
+
+    pub fn has_shipyard(&self) -> bool {
+        self.buildings.iter().any(|b| b == SHIPYARD)
+    }
+
+    /// Range and strength of the best sensors of the colony, its own or those of a building
+    pub fn sensors(&self) -> (f32, f32) {
+        self.buildings.iter()
+            .filter_map(|id| building(id))
+            .fold((COLONY_SENSOR_RANGE, COLONY_SENSOR_STRENGTH), |(range, strength), b| {
+                (range.max(b.sensor_range), strength.max(b.sensor_strength))
+            })
+    }
+
+    /// Adds a population unit, which takes the least staffed job with a free slot
+    pub fn add_pop(&mut self) {
+        self.population += 1;
+        self.workforce.hire(&self.job_slots());
+    }
+
+    /// Jobs offered by the buildings of the colony
+    pub fn job_slots(&self) -> Workforce {
+        let mut res = Workforce::default();
+        for job in Job::ALL {
+            res.set(job, BASE_JOB_SLOTS);
+        }
+        for def in self.buildings.iter().filter_map(|id| building(id)) {
+            for (job, slots) in def.job_slots().iter() {
+                res.set(job, res.get(job) + slots);
+            }
+        }
+        res
+    }
+
+    /// Puts the unemployed into free job slots
+    pub fn fill_jobs(&mut self) {
+        let slots = self.job_slots();
+        while self.unemployed() > 0 && self.workforce.hire(&slots) {}
+    }
+
+    /// Upkeep of every building of the colony
+    pub fn upkeep(&self, modifiers: &[Modifier]) -> Resources {
+        self.buildings.iter()
+            .filter_map(|id| building(id))
+            .fold(Resources::default(), |a, b| a + b.upkeep) * (1.0 + upkeep_bonus(modifiers)).max(0.0)
+    }
+
+    /// Removes a population unit, the unemployed leave first
+    pub fn remove_pop(&mut self) {
+        if self.population == 0 {
+            return;
+        }
+        if self.unemployed() == 0 {
+            self.workforce.fire();
+        }
+        self.population -= 1;
+    }
+
+    pub fn unemployed(&self) -> u32 {
+        self.population.saturating_sub(self.workforce.total())
+    }
+
+    /// Resources the colony produces each turn
+    pub fn production(&self, planet: &PlanetType, modifiers: &[Modifier]) -> Resources {
+        self.buildings.iter()
+            .filter_map(|id| building(id))
+            .fold(self.workforce.production(planet, modifiers), |a, b| a + b.production)
+    }
+
+    /// Food the population eats each turn
+    pub fn food_consumption(&self) -> f32 {
+        self.population as f32 * FOOD_PER_POP
+    }
+
+    pub fn make_info(&self, capacity: u32) -> Vec<String> {
+        let mut res: Vec<String> = Vec::new();
+        res.push(format!("Founded: {}", self.founded));
+        res.push(format!("Population: {}/{capacity} ({:+.0}% to next)", self.population, self.growth * 100.0));
+        for (job, workers) in self.workforce.iter() {
+            res.push(format!("  {job}s: {workers}"));
+        }
+        res.push(format!("  Unemployed: {}", self.unemployed()));
+        let (range, strength) = self.sensors();
+        res.push(format!("Sensors: {range} ly, strength {strength}"));
+        if !self.buildings.is_empty() {
+            res.push("Buildings:".to_owned());
+            for def in BUILDINGS.iter() {
+                let count = self.buildings.iter().filter(|b| **b == def.id).count();
+                if count > 0 {
+                    res.push(format!("  {} x{count}", def.name));
+                }
+            }
+        }
+        if !self.queue.is_empty() {
+            res.push("Construction queue:".to_owned());
+            for construction in &self.queue {
+                let def = building(&construction.building).unwrap();
+                res.push(format!("  {} ({}/{} turns)", def.name, construction.progress, def.build_time));
+            }
+        }
+        res
+    }
+}
+
+/// Days it takes to found a colony on a planet of the given habitability
+pub fn founding_days(habitability: f32) -> u64 {
+    (BASE_FOUNDING_DAYS / habitability.max(0.05)).round() as u64
+}
+
+/// The planet of the system that can hold the most population of the species, where an empire
+/// of the species starting there has its homeworld
+pub fn homeworld_candidate(system: &System, species: &Species) -> Option<BodyId> {
+    system.bodies.iter()
+        .filter(|b| capacity(b, species) > 0)
+        .max_by_key(|b| capacity(b, species))
+        .map(|b| b.id)
+}
+
+/// Whether the system has a planet that can hold a full homeworld population of the species
+pub fn suits_homeworld(system: &System, species: &Species) -> bool {
+    homeworld_candidate(system, species)
+        .and_then(|id| system.body(id))
+        .is_some_and(|b| capacity(b, species) >= HOMEWORLD_POPULATION)
+}
+
+/// Whether the star will stay as it is long enough to be worth settling around
+pub fn stable_star(system: &System) -> bool {
+    system.years_left().is_none_or(|years| years >= SETTLEMENT_HORIZON)
+}
+
+/// Whether a colony is already being founded on the body
+pub fn is_being_colonized(state: &GameState, target: BodyRef) -> bool {
+    state.schedule.iter().any(|(_, e)| matches!(e, ScheduledEvent::FoundColony { target: t, .. } if *t == target))
+}
+
+/// Lands a colony ship of the fleet on the target and schedules the founding of the colony.
+/// Returns a message for the report, or why the colony ship could not land
+pub fn start_colonization(
+    state: &mut GameState,
+    empire: EmpireId,
+    fleet: FleetId,
+    target: BodyRef,
+) -> Result<String, String> {
+    let Some(body) = state.galaxy.body(target) else {
+        return Err("The colonisation target no longer exists".to_owned());
+    };
+    let name = body.name.clone();
+    let habitability = state.empires[empire].species.habitability(body);
+    if !matches!(body.kind, BodyType::Planet(_)) || habitability <= 0.0 {
+        return Err(format!("{name} can not be colonised"));
+    }
+    if body.colony.is_some() || is_being_colonized(state, target) {
+        return Err(format!("{name} is already colonised"));
+    }
+
+    let Some(f) = state.fleets.iter_mut().find(|f| f.id == fleet && f.owner == empire) else {
+        return Err("The colony fleet no longer exists".to_owned());
+    };
+    if f.system() != Some(target.system) {
+        return Err(format!("{} has to be in the system of {name} to colonise it", f.name));
+    }
+    if f.take_ship(&ShipRole::ColonyShip).is_none() {
+        return Err(format!("{} has no colony ship", f.name));
+    }
+    state.remove_empty_fleets();
+
+    let days = founding_days(habitability);
+    let now = state.date;
+    state.schedule.after(now, days, ScheduledEvent::FoundColony { empire, target });
+    Ok(format!("Colonists landed on {name}, the colony will be founded in {days} days"))
+}
+
+/// Founds a colony scheduled by [`start_colonization`]
+pub fn found_colony(state: &mut GameState, empire: EmpireId, target: BodyRef) -> Result<String, String> {
+    let date = state.date;
+    let Some(body) = state.galaxy.body_mut(target) else {
+        return Err("The colonists were lost along with the planet they settled".to_owned());
+    };
+    if body.colony.is_some() {
+        return Err(format!("Colonists on {} were driven off", body.name));
+    }
+    body.colony = Some(Colony::new(empire, date));
+    let name = body.name.clone();
+
+    let owner = &mut state.empires[empire];
+    if !owner.systems.contains(&target.system) {
+        owner.systems.push(target.system);
+    }
+    Ok(format!("{} founded a colony on {name}", owner.name))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::order::Order;
+    use crate::game::population::capacity;
+    use crate::game::turn::{end_turn, EventKind};
+
+    use super::*;
+
+    /// Puts the home fleet next to an uncolonised habitable planet
+    fn setup(seed: u64) -> (GameState, BodyRef) {
+        let mut state = GameState::new(seed);
+        let species = &state.player_empire().species;
+        let target = state.galaxy.systems.iter().enumerate()
+            .find_map(|(system, s)| {
+                s.bodies.iter()
+                    .find(|b| species.habitability(b) > 0.0 && b.colony.is_none())
+                    .map(|b| BodyRef { system, body: b.id })
+            })
+            .unwrap();
+        state.fleets[0].location = target.system;
+        (state, target)
+    }
+
+    #[test]
+    fn test_homeworld() {
+        let state = GameState::new(5);
+        let colonies: Vec<_> = state.galaxy.colonies().collect();
+        assert_eq!(colonies.len(), 1);
+        assert_eq!(colonies[0].0.system, state.player_empire().capital);
+        let population = colonies[0].1.colony.as_ref().unwrap().population;
+        assert_eq!(population, HOMEWORLD_POPULATION.min(capacity(colonies[0].1, &state.player_empire().species)));
+    }
+
+    #[test]
+    fn test_founding_takes_longer_on_hostile_planets() {
+        assert!(founding_days(1.0) < founding_days(0.5));
+        assert!(founding_days(0.5) < founding_days(0.1));
+    }
+
+    #[test]
+    fn test_colonization() {
+        let (mut state, target) = setup(6);
+        let fleet = state.fleets[0].id;
+        state.issue_order(state.player, Order::Colonize { fleet, target });
+        let report = end_turn(&mut state);
+
+        assert!(report.entries.iter().any(|e| e.kind == EventKind::ColonizationStarted));
+        assert!(!state.fleets[0].has_role(&ShipRole::ColonyShip));
+        assert!(is_being_colonized(&state, target));
+
+        let days = founding_days(state.player_empire().species.habitability(state.galaxy.body(target).unwrap()));
+        let mut founded = false;
+        for _ in 0..days / 30 + 1 {
+            founded |= end_turn(&mut state).entries.iter().any(|e| e.kind == EventKind::ColonyFounded);
+        }
+        assert!(founded);
+        let colony = state.galaxy.body(target).unwrap().colony.as_ref().unwrap();
+        assert_eq!(colony.owner, state.player);
+        assert!(state.player_empire().systems.contains(&target.system));
+    }
+
+    #[test]
+    fn test_colonization_needs_colony_ship() {
+        let (mut state, target) = setup(7);
+        state.fleets[0].take_ship(&ShipRole::ColonyShip);
+        let fleet = state.fleets[0].id;
+        state.issue_order(state.player, Order::Colonize { fleet, target });
+        let report = end_turn(&mut state);
+
+        assert!(report.entries.iter().any(|e| e.kind == EventKind::OrderRejected));
+        assert!(!is_being_colonized(&state, target));
+    }
+}
@@ -0,0 +1,481 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use rand::Rng;
+use crate::game::design::{component, DamageType};
+use crate::game::empire::EmpireId;
+use crate::game::fleet::{plot_course, Fleet, FleetId};
+use crate::game::galaxy::SystemId;
+use crate::game::state::GameState;
+use crate::game::turn::{EventKind, Phase, TurnReport};
+
+/// Rounds a battle lasts at most, fleets still standing afterwards disengage
+pub const MAX_ROUNDS: u32 = 10;
+
+/// Battles the game keeps the full log of
+const KEPT_BATTLES: usize = 10;
+
+/// Share of its armour and structure a damaged ship repairs every turn in a system where its
+/// empire has a shipyard
+const REPAIR_PER_TURN: f32 = 0.25;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Weapon {
+    pub name: String,
+    pub damage: f32,
+    pub damage_type: DamageType,
+    pub accuracy: f32,
+}
+
+/// A ship taking part in a battle
+#[derive(Debug, Clone, PartialEq)]
+pub struct Combatant {
+    pub fleet: FleetId,
+    pub owner: EmpireId,
+    /// Index of the ship in its fleet
+    pub ship: usize,
+    pub name: String,
+    pub shield: f32,
+    pub armour: f32,
+    pub structure: f32,
+    pub evasion: f32,
+    pub weapons: Vec<Weapon>,
+}
+
+impl Combatant {
+    fn of(fleet: &Fleet, ship: usize) -> Self {
+        let s = &fleet.ships[ship];
+        let stats = s.design.stats();
+        let weapons = s.design.components.iter()
+            .filter_map(|c| component(c))
+            .filter(|c| c.damage > 0.0)
+            .map(|c| Weapon {
+                name: c.name.clone(),
+                damage: c.damage,
+                damage_type: c.damage_type,
+                accuracy: c.accuracy,
+            })
+            .collect();
+        // Damage from earlier battles has worn through the armour first
+        let armour_lost = s.damage.min(stats.armour);
+        Self {
+            fleet: fleet.id,
+            owner: fleet.owner,
+            ship,
+            name: s.name.clone(),
+            shield: s.shield(),
+            armour: stats.armour - armour_lost,
+            structure: stats.structure - (s.damage - armour_lost),
+            evasion: stats.evasion,
+            weapons,
+        }
+    }
+
+    pub fn is_destroyed(&self) -> bool {
+        self.structure <= 0.0
+    }
+
+    /// Armour and structure left, which is what a fleet's strength is measured by
+    fn health(&self) -> f32 {
+        self.armour.max(0.0) + self.structure.max(0.0)
+    }
+
+    /// Takes a hit, which shields and then armour soak up before the structure suffers. Returns
+    /// the armour and structure lost
+    fn take_hit(&mut self, damage: f32, damage_type: DamageType) -> f32 {
+        let before = self.health();
+        let mut left = damage;
+        let layers = [
+            (&mut self.shield, damage_type.against_shields()),
+            (&mut self.armour, damage_type.against_armour()),
+            (&mut self.structure, 1.0),
+        ];
+        for (layer, multiplier) in layers {
+            let effective = left * multiplier;
+            if effective <= *layer {
+                *layer -= effective;
+                left = 0.0;
+                break;
+            }
+            left -= *layer / multiplier;
+            *layer = 0.0;
+        }
+        // Whatever is left tears through the wreck
+        self.structure -= left;
+        before - self.health()
+    }
+}
+
+/// Outcome of a battle
+#[derive(Debug, Clone, PartialEq)]
+pub struct Battle {
+    pub system: SystemId,
+    pub turn: u32,
+    pub rounds: u32,
+    /// Every shot and its result, round by round
+    pub log: Vec<String>,
+    /// Every ship as it came out of the battle
+    pub ships: Vec<Combatant>,
+    pub retreated: Vec<FleetId>,
+}
+
+impl Battle {
+    pub fn destroyed(&self) -> impl Iterator<Item = &Combatant> {
+        self.ships.iter().filter(|c| c.is_destroyed())
+    }
+
+    /// One line about how the battle went
+    pub fn summary(&self, state: &GameState) -> String {
+        let mut sides: Vec<EmpireId> = self.ships.iter().map(|c| c.owner).collect();
+        sides.sort();
+        sides.dedup();
+        let losses: Vec<String> = sides.iter()
+            .map(|e| format!(
+                "{} lost {} ship(s)",
+                state.empires[*e].name,
+                self.destroyed().filter(|c| c.owner == *e).count()
+            ))
+            .collect();
+        format!(
+            "Battle of {} lasted {} round(s): {}",
+            state.galaxy.systems[self.system].name,
+            self.rounds,
+            losses.join(", ")
+        )
+    }
+}
+
+/// Fights a battle between the fleets, every ship firing every weapon at a random hostile ship
+/// each round. Fleets that lose more than their retreat threshold of their strength leave the
+/// battle. Given the same fleets and random generator the battle always goes the same way
+pub fn resolve<R: Rng + ?Sized>(
+    system: SystemId,
+    fleets: &[Fleet],
+    hostile: impl Fn(EmpireId, EmpireId) -> bool,
+    rng: &mut R,
+) -> Battle {
+    let mut ships: Vec<Combatant> = fleets.iter()
+        .flat_map(|f| (0..f.ships.len()).map(move |i| Combatant::of(f, i)))
+        .collect();
+    let strength = |ships: &[Combatant], fleet: FleetId| -> f32 {
+        ships.iter().filter(|c| c.fleet == fleet).map(Combatant::health).sum()
+    };
+    let starting: Vec<f32> = fleets.iter().map(|f| strength(&ships, f.id)).collect();
+    let mut retreated: Vec<FleetId> = Vec::new();
+    let mut log = Vec::new();
+    let mut rounds = 0;
+
+    let in_battle = |c: &Combatant, retreated: &[FleetId]| !c.is_destroyed() && !retreated.contains(&c.fleet);
+    while rounds < MAX_ROUNDS {
+        let engaged = |retreated: &[FleetId]| ships.iter().any(|a| in_battle(a, retreated)
+            && ships.iter().any(|b| in_battle(b, retreated) && hostile(a.owner, b.owner)));
+        if !engaged(&retreated) {
+            break;
+        }
+        rounds += 1;
+        log.push(format!("Round {rounds}"));
+
+        // Everyone fires before the casualties of the round are counted
+        let firing: Vec<usize> = (0..ships.len()).filter(|i| in_battle(&ships[*i], &retreated)).collect();
+        for attacker in firing.iter().copied() {
+            for weapon in ships[attacker].weapons.clone() {
+                let targets: Vec<usize> = (0..ships.len())
+                    .filter(|t| in_battle(&ships[*t], &retreated) && hostile(ships[attacker].owner, ships[*t].owner))
+                    .collect();
+                if targets.is_empty() {
+                    break;
+                }
+                let target = targets[rng.gen_range(0..targets.len())];
+                let chance = (weapon.accuracy * (1.0 - ships[target].evasion)).clamp(0.0, 1.0);
+                if rng.gen_bool(chance as f64) {
+                    let lost = ships[target].take_hit(weapon.damage, weapon.damage_type);
+                    log.push(format!(
+                        "{}'s {} hits {} ({lost:.0} damage)",
+                        ships[attacker].name,
+                        weapon.name,
+                        ships[target].name
+                    ));
+                } else {
+                    log.push(format!("{}'s {} misses {}", ships[attacker].name, weapon.name, ships[target].name));
+                }
+            }
+        }
+
+        for c in firing.iter().map(|i| &ships[*i]).filter(|c| c.is_destroyed()) {
+            log.push(format!("{} is destroyed", c.name));
+        }
+        for (fleet, start) in fleets.iter().zip(&starting) {
+            let lost = 1.0 - strength(&ships, fleet.id) / start.max(f32::EPSILON);
+            let alive = ships.iter().any(|c| c.fleet == fleet.id && !c.is_destroyed());
+            if alive && !retreated.contains(&fleet.id) && fleet.retreat_threshold < 1.0 && lost >= fleet.retreat_threshold {
+                log.push(format!("{} retreats", fleet.name));
+                retreated.push(fleet.id);
+            }
+        }
+    }
+
+    Battle { system, turn: 0, rounds, log, ships, retreated }
+}
+
+/// Systems where fleets of hostile empires meet, every system once
+fn battlefields(state: &GameState) -> Vec<SystemId> {
+    let mut res: Vec<SystemId> = Vec::new();
+    for a in &state.fleets {
+        let Some(system) = a.system() else { continue };
+        let meets_enemy = state.fleets.iter()
+            .any(|b| b.system() == Some(system) && state.hostile(a.owner, b.owner));
+        if meets_enemy && !res.contains(&system) {
+            res.push(system);
+        }
+    }
+    res.sort();
+    res
+}
+
+/// Resolves every battle of the turn, removes the destroyed ships and sends the fleets that
+/// retreated home. Afterwards ships at a shipyard of their empire are repaired
+pub fn combat(state: &mut GameState, report: &mut TurnReport) {
+    for system in battlefields(state) {
+        let fleets: Vec<Fleet> = state.fleets.iter()
+            .filter(|f| f.system() == Some(system) && !f.ships.is_empty())
+            .cloned()
+            .collect();
+        let empires = state.empires.len();
+        let wars: Vec<(EmpireId, EmpireId)> = (0..empires)
+            .flat_map(|a| (0..empires).map(move |b| (a, b)))
+            .filter(|(a, b)| state.hostile(*a, *b))
+            .collect();
+        let mut battle = resolve(system, &fleets, |a, b| wars.contains(&(a, b)), state.rng());
+        battle.turn = state.turn;
+        // The player only hears of the battles it fought in
+        if battle.ships.iter().any(|c| c.owner == state.player) {
+            report.add(Phase::Combat, EventKind::Battle, battle.summary(state));
+            apply(state, &battle, report);
+        } else {
+            apply(state, &battle, &mut TurnReport::default());
+        }
+        state.battles.push(battle);
+        if state.battles.len() > KEPT_BATTLES {
+            state.battles.remove(0);
+        }
+    }
+    repairs(state);
+}
+
+/// Carries the outcome of a battle over to the fleets that fought it
+fn apply(state: &mut GameState, battle: &Battle, report: &mut TurnReport) {
+    for fleet in state.fleets.iter_mut() {
+        let mut ships: Vec<&Combatant> = battle.ships.iter().filter(|c| c.fleet == fleet.id).collect();
+        // Highest index first, so that removing a ship does not shift the others
+        ships.sort_by_key(|c| std::cmp::Reverse(c.ship));
+        for c in ships {
+            if c.is_destroyed() {
+                fleet.ships.remove(c.ship);
+            } else {
+                let ship = &mut fleet.ships[c.ship];
+                let stats = ship.design.stats();
+                ship.damage = stats.armour + stats.structure - c.health();
+            }
+        }
+    }
+    let destroyed: Vec<(FleetId, String)> = state.fleets.iter()
+        .filter(|f| f.ships.is_empty() && battle.ships.iter().any(|c| c.fleet == f.id))
+        .map(|f| (f.id, f.name.clone()))
+        .collect();
+    for (_, name) in &destroyed {
+        report.add(Phase::Combat, EventKind::FleetDestroyed, format!("{name} was destroyed"));
+    }
+    state.fleets.retain(|f| !destroyed.iter().any(|(id, _)| *id == f.id));
+
+    for fleet in &battle.retreated {
+        let Some(f) = state.fleets.iter().find(|f| f.id == *fleet) else { continue };
+        let Some(to) = retreat_destination(state, f) else { continue };
+        let (owner, name) = (f.owner, f.name.clone());
+        if plot_course(state, owner, *fleet, to, false).is_ok() {
+            report.add(Phase::Combat, EventKind::Battle, format!(
+                "{name} retreats towards {}",
+                state.galaxy.systems[to].name
+            ));
+        }
+    }
+}
+
+/// Neighbouring system a fleet falls back to, the next one on the way to its capital
+fn retreat_destination(state: &GameState, fleet: &Fleet) -> Option<SystemId> {
+    let capital = state.empires[fleet.owner].capital;
+    let towards_capital = state.galaxy.find_route(fleet.location, capital, fleet.speed())
+        .and_then(|r| r.first().copied());
+    towards_capital.or_else(|| state.galaxy.neighbours(fleet.location).into_iter().min())
+}
+
+/// Repairs the ships that are in a system where their empire has a shipyard
+fn repairs(state: &mut GameState) {
+    let yards: Vec<(SystemId, EmpireId)> = state.galaxy.colonies()
+        .filter_map(|(r, b)| b.colony.as_ref().filter(|c| c.has_shipyard()).map(|c| (r.system, c.owner)))
+        .collect();
+    for fleet in state.fleets.iter_mut() {
+        if fleet.system().is_none_or(|s| !yards.contains(&(s, fleet.owner))) {
+            continue;
+        }
+        for ship in fleet.ships.iter_mut() {
+            let stats = ship.design.stats();
+            let repair = (stats.armour + stats.structure) * REPAIR_PER_TURN;
+            ship.damage = (ship.damage - repair).max(0.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use ratatui::style::Color;
+    use crate::game::design::ShipDesign;
+    use crate::game::diplomacy::Stance;
+    use crate::game::empire::Empire;
+    use crate::game::ship::Ship;
+    use crate::game::turn::end_turn;
+    use super::*;
+
+    fn design(components: &[&str]) -> ShipDesign {
+        ShipDesign {
+            name: "Test".to_owned(),
+            hull: "corvette".to_owned(),
+            components: components.iter().map(|c| c.to_string()).collect(),
+            revision: 0,
+        }
+    }
+
+    fn fleet(id: FleetId, owner: EmpireId, ships: usize, design: &ShipDesign) -> Fleet {
+        let mut res = Fleet::new(id, &format!("Fleet {id}"), owner, 0);
+        res.ships = (0..ships).map(|i| Ship::new(&format!("Ship {id}-{i}"), design)).collect();
+        res
+    }
+
+    fn gunships() -> ShipDesign {
+        design(&["fission_reactor", "chemical_thruster", "mass_driver", "mass_driver", "steel_armour"])
+    }
+
+    fn lasers() -> ShipDesign {
+        design(&["fusion_reactor", "chemical_thruster", "laser", "laser", "deflector"])
+    }
+
+    #[test]
+    fn test_damage_layers() {
+        let mut ship = Combatant::of(&fleet(0, 0, 1, &lasers()), 0);
+        assert_eq!((ship.shield, ship.armour, ship.structure), (40.0, 0.0, 100.0));
+        // Kinetic rounds overload the shield
+        assert_eq!(ship.take_hit(20.0, DamageType::Kinetic), 0.0);
+        assert_eq!(ship.shield, 10.0);
+        // Energy beams lose half their damage to what is left of it
+        assert_eq!(ship.take_hit(30.0, DamageType::Energy), 10.0);
+        assert_eq!((ship.shield, ship.structure), (0.0, 90.0));
+
+        let mut ship = Combatant::of(&fleet(0, 0, 1, &gunships()), 0);
+        assert_eq!(ship.take_hit(20.0, DamageType::Kinetic), 10.0);
+        assert_eq!(ship.armour, 30.0);
+        assert_eq!(ship.take_hit(1000.0, DamageType::Energy), 130.0);
+        assert!(ship.is_destroyed());
+    }
+
+    #[test]
+    fn test_radiation_weakens_shields() {
+        use crate::game::body::{BodyType, StarType};
+        use crate::game::turn::{events, TurnReport, DAYS_PER_TURN};
+
+        let mut state = GameState::new(3);
+        let home = state.fleets[0].location;
+        let mut defenders = fleet(state.next_fleet_id(), 0, 1, &lasers());
+        defenders.location = home;
+        state.fleets.push(defenders);
+        let full = Combatant::of(state.fleets.last().unwrap(), 0).shield;
+
+        let system = &mut state.galaxy.systems[home];
+        system.star.kind = BodyType::Star(StarType::Pulsar);
+        let damage = system.modifiers().shield_damage;
+        events(&mut state, &mut TurnReport::default(), DAYS_PER_TURN);
+        let weakened = Combatant::of(state.fleets.last().unwrap(), 0).shield;
+        assert!(weakened < full);
+        assert_eq!(weakened, (full * (1.0 - damage * DAYS_PER_TURN as f32)).max(0.0));
+
+        // Out of the radiation the shields recharge
+        state.galaxy.systems[home].star.kind = BodyType::Star(StarType::G);
+        state.galaxy.systems[home].in_nebula = false;
+        for _ in 0..5 {
+            events(&mut state, &mut TurnReport::default(), DAYS_PER_TURN);
+        }
+        assert_eq!(Combatant::of(state.fleets.last().unwrap(), 0).shield, full);
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let fleets = [fleet(0, 0, 3, &gunships()), fleet(1, 1, 3, &lasers())];
+        let battle = |seed| resolve(0, &fleets, |a, b| a != b, &mut StdRng::seed_from_u64(seed));
+        assert_eq!(battle(7), battle(7));
+        assert_ne!(battle(7).log, battle(8).log);
+    }
+
+    #[test]
+    fn test_fixed_seed() {
+        let mut fleets = [fleet(0, 0, 3, &gunships()), fleet(1, 1, 3, &lasers())];
+        for f in fleets.iter_mut() {
+            f.retreat_threshold = 1.0;
+        }
+        let battle = resolve(0, &fleets, |a, b| a != b, &mut StdRng::seed_from_u64(42));
+        let lost = |owner| battle.destroyed().filter(|c| c.owner == owner).count();
+        // Lasers burn through the armour of the gunships faster than their rounds wear the deflectors down
+        assert_eq!(battle.rounds, MAX_ROUNDS);
+        assert_eq!((lost(0), lost(1)), (2, 0));
+        assert_eq!(battle.log.len(), 126);
+        assert_eq!(battle.log[0], "Round 1");
+        assert!(battle.retreated.is_empty());
+    }
+
+    #[test]
+    fn test_retreat() {
+        let mut fleets = [fleet(0, 0, 1, &gunships()), fleet(1, 1, 5, &lasers())];
+        fleets[0].retreat_threshold = 0.1;
+        let battle = resolve(0, &fleets, |a, b| a != b, &mut StdRng::seed_from_u64(1));
+        assert_eq!(battle.retreated, vec![0]);
+        assert!(battle.destroyed().next().is_none());
+        assert!(battle.log.iter().any(|l| l == "Fleet 0 retreats"));
+
+        // Allies do not fight
+        let battle = resolve(0, &fleets, |_, _| false, &mut StdRng::seed_from_u64(1));
+        assert_eq!(battle.rounds, 0);
+    }
+
+    #[test]
+    fn test_combat_phase() {
+        let mut state = GameState::new(3);
+        let home = state.fleets[0].location;
+        state.empires.push(Empire::new(1, "Raiders", Color::Red, home));
+        state.diplomacy.set_stance(0, 1, Stance::War, 0);
+        let mut raiders = fleet(state.next_fleet_id(), 1, 4, &lasers());
+        raiders.location = home;
+        raiders.retreat_threshold = 1.0;
+        state.fleets.push(raiders);
+        let before: usize = state.fleets.iter().map(|f| f.ships.len()).sum();
+
+        let report = end_turn(&mut state);
+        assert!(report.entries.iter().any(|e| e.kind == EventKind::Battle));
+        assert_eq!(state.battles.len(), 1);
+        let battle = &state.battles[0];
+        let after: usize = state.fleets.iter().map(|f| f.ships.len()).sum();
+        assert_eq!(after, before - battle.destroyed().count());
+        assert!(state.fleets.iter().all(|f| !f.ships.is_empty()));
+    }
+}
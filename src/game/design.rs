@@ -0,0 +1,440 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+use crate::game::empire::EmpireId;
+use crate::game::resources::Resources;
+use crate::game::state::GameState;
+use crate::game::tech::Research;
+
+const HULLS_DATA: &str = include_str!("../../assets/hulls.json5");
+const COMPONENTS_DATA: &str = include_str!("../../assets/components.json5");
+const DESIGNS_DATA: &str = include_str!("../../assets/designs.json5");
+
+lazy_static! {
+    /// Every hull class defined in the data files
+    pub static ref HULLS: Vec<Hull> = json5::from_str(HULLS_DATA).expect("hull definitions are valid");
+    /// Every ship component defined in the data files
+    pub static ref COMPONENTS: Vec<Component> =
+        json5::from_str(COMPONENTS_DATA).expect("component definitions are valid");
+    /// Designs every empire starts with
+    pub static ref STARTING_DESIGNS: Vec<ShipDesign> =
+        json5::from_str(DESIGNS_DATA).expect("starting designs are valid");
+}
+
+/// Speed in light years per day of a ship with one unit of thrust per tonne
+pub const SPEED_SCALE: f32 = 0.5;
+
+/// Longest name a design can have
+pub const MAX_NAME_LENGTH: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString, Deserialize)]
+pub enum SlotType {
+    Core,
+    Engine,
+    Weapon,
+    Defense,
+    Utility,
+}
+
+impl SlotType {
+    pub const ALL: [SlotType; 5] = [
+        SlotType::Core,
+        SlotType::Engine,
+        SlotType::Weapon,
+        SlotType::Defense,
+        SlotType::Utility,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Deserialize)]
+pub enum ComponentKind {
+    Engine,
+    Reactor,
+    Weapon,
+    Shield,
+    Armour,
+    Sensor,
+    Stealth,
+    Cargo,
+    ColonyModule,
+}
+
+/// How a weapon does its damage, which decides how well shields and armour stop it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, Deserialize)]
+pub enum DamageType {
+    /// Slugs and missiles, which overload shields but glance off armour
+    #[default]
+    Kinetic,
+    /// Lasers and particle beams, which shields deflect but burn through armour
+    Energy,
+}
+
+impl DamageType {
+    /// Multiplier of the damage dealt to shields
+    pub fn against_shields(&self) -> f32 {
+        match self {
+            DamageType::Kinetic => { 1.5 }
+            DamageType::Energy => { 0.5 }
+        }
+    }
+
+    /// Multiplier of the damage dealt to armour
+    pub fn against_armour(&self) -> f32 {
+        match self {
+            DamageType::Kinetic => { 0.5 }
+            DamageType::Energy => { 1.5 }
+        }
+    }
+}
+
+/// A hull class, as defined in the data files
+#[derive(Debug, Clone, Deserialize)]
+pub struct Hull {
+    pub id: String,
+    pub name: String,
+    pub mass: f32,
+    pub structure: f32,
+    pub evasion: f32,
+    pub cost: Resources,
+    /// Number of slots by the name of the slot type
+    slots: HashMap<String, u32>,
+}
+
+impl Hull {
+    pub fn slots(&self, slot: SlotType) -> u32 {
+        self.slots.iter()
+            .filter(|(s, _)| s.parse() == Ok(slot))
+            .map(|(_, n)| *n)
+            .sum()
+    }
+}
+
+/// A ship component, as defined in the data files
+#[derive(Debug, Clone, Deserialize)]
+pub struct Component {
+    pub id: String,
+    pub name: String,
+    pub kind: ComponentKind,
+    pub slot: SlotType,
+    pub mass: f32,
+    /// Produced when positive, drawn when negative
+    pub power: f32,
+    pub cost: Resources,
+    #[serde(default)]
+    pub thrust: f32,
+    #[serde(default)]
+    pub damage: f32,
+    #[serde(default)]
+    pub damage_type: DamageType,
+    /// Chance to hit a target that does not evade
+    #[serde(default)]
+    pub accuracy: f32,
+    #[serde(default)]
+    pub shield: f32,
+    #[serde(default)]
+    pub armour: f32,
+    /// Light years
+    #[serde(default)]
+    pub sensor_range: f32,
+    #[serde(default)]
+    pub sensor_strength: f32,
+    /// Sensors need at least this strength to pick up the ship
+    #[serde(default)]
+    pub stealth: f32,
+    #[serde(default)]
+    pub cargo: f32,
+    /// Population units carried to found a colony
+    #[serde(default)]
+    pub colonists: u32,
+}
+
+pub fn hull(id: &str) -> Option<&'static Hull> {
+    HULLS.iter().find(|h| h.id == id)
+}
+
+pub fn component(id: &str) -> Option<&'static Component> {
+    COMPONENTS.iter().find(|c| c.id == id)
+}
+
+/// A hull together with the components fitted to it, under a name chosen by the player
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShipDesign {
+    pub name: String,
+    pub hull: String,
+    pub components: Vec<String>,
+    /// Grows every time a design is saved again under the same name
+    #[serde(default)]
+    pub revision: u32,
+}
+
+impl ShipDesign {
+    pub fn new(name: &str, hull: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            hull: hull.to_owned(),
+            components: Vec::new(),
+            revision: 0,
+        }
+    }
+
+    /// Components fitted into slots of the given type
+    pub fn used_slots(&self, slot: SlotType) -> u32 {
+        self.components.iter()
+            .filter_map(|c| component(c))
+            .filter(|c| c.slot == slot)
+            .count() as u32
+    }
+
+    pub fn stats(&self) -> DesignStats {
+        DesignStats::of(self)
+    }
+
+    /// Everything that keeps the design from being built. Components and hulls have to be known
+    /// to the empire, the slots of the hull must not be overfilled and the reactors have to power
+    /// every component
+    pub fn validate(&self, research: &Research) -> Vec<String> {
+        let mut res: Vec<String> = Vec::new();
+        let name = self.name.trim();
+        if name.is_empty() {
+            res.push("The design has no name".to_owned());
+        } else if name.chars().count() > MAX_NAME_LENGTH {
+            res.push(format!("The name is longer than {MAX_NAME_LENGTH} characters"));
+        }
+
+        let Some(hull) = hull(&self.hull) else {
+            res.push(format!("There is no such hull as {}", self.hull));
+            return res;
+        };
+        for id in &self.components {
+            match component(id) {
+                None => { res.push(format!("There is no such component as {id}")) }
+                Some(c) if !research.component_unlocked(id) => {
+                    res.push(format!("{} is not researched yet", c.name));
+                }
+                _ => {}
+            }
+        }
+        for slot in SlotType::ALL {
+            let (used, available) = (self.used_slots(slot), hull.slots(slot));
+            if used > available {
+                res.push(format!("{used} {slot} components, but the hull has {available} slots"));
+            }
+        }
+        let stats = self.stats();
+        if stats.power_output < stats.power_draw {
+            res.push(format!("Reactors produce {} power, components draw {}", stats.power_output, stats.power_draw));
+        }
+        res
+    }
+}
+
+/// Totals of a design
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DesignStats {
+    pub mass: f32,
+    pub cost: Resources,
+    pub power_output: f32,
+    pub power_draw: f32,
+    pub structure: f32,
+    pub evasion: f32,
+    pub thrust: f32,
+    /// Light years per day
+    pub speed: f32,
+    /// Damage of every weapon together, if every shot hits
+    pub damage: f32,
+    pub shield: f32,
+    pub armour: f32,
+    pub sensor_range: f32,
+    pub sensor_strength: f32,
+    pub stealth: f32,
+    pub cargo: f32,
+    pub colonists: u32,
+}
+
+impl DesignStats {
+    /// Adds up the hull and every known component of the design
+    pub fn of(design: &ShipDesign) -> Self {
+        let mut res = Self::default();
+        if let Some(hull) = hull(&design.hull) {
+            res.mass = hull.mass;
+            res.cost = hull.cost;
+            res.structure = hull.structure;
+            res.evasion = hull.evasion;
+        }
+        for c in design.components.iter().filter_map(|c| component(c)) {
+            res.mass += c.mass;
+            res.cost += c.cost;
+            if c.power > 0.0 {
+                res.power_output += c.power;
+            } else {
+                res.power_draw -= c.power;
+            }
+            res.thrust += c.thrust;
+            res.damage += c.damage;
+            res.shield += c.shield;
+            res.armour += c.armour;
+            res.sensor_range = res.sensor_range.max(c.sensor_range);
+            res.sensor_strength = res.sensor_strength.max(c.sensor_strength);
+            res.stealth = res.stealth.max(c.stealth);
+            res.cargo += c.cargo;
+            res.colonists += c.colonists;
+        }
+        if res.mass > 0.0 {
+            res.speed = SPEED_SCALE * res.thrust / res.mass;
+        }
+        res
+    }
+
+    pub fn make_info(&self) -> Vec<String> {
+        let mut res: Vec<String> = Vec::new();
+        let cost: Vec<String> = self.cost.iter()
+            .filter(|(_, a)| *a > 0.0)
+            .map(|(r, a)| format!("{a} {r}"))
+            .collect();
+        res.push(format!("Cost: {}", cost.join(", ")));
+        res.push(format!("Mass: {} t", self.mass));
+        res.push(format!("Power: {}/{}", self.power_draw, self.power_output));
+        res.push(format!("Speed: {:.2} ly/day", self.speed));
+        res.push(format!("Structure: {}   Armour: {}   Shield: {}", self.structure, self.armour, self.shield));
+        res.push(format!("Evasion: {:.0}%", self.evasion * 100.0));
+        if self.damage > 0.0 {
+            res.push(format!("Damage: {} per round", self.damage));
+        }
+        if self.sensor_range > 0.0 {
+            res.push(format!("Sensors: {} ly, strength {}", self.sensor_range, self.sensor_strength));
+        }
+        if self.stealth > 0.0 {
+            res.push(format!("Stealth: {}", self.stealth));
+        }
+        if self.cargo > 0.0 {
+            res.push(format!("Cargo: {}", self.cargo));
+        }
+        if self.colonists > 0 {
+            res.push(format!("Colonists: {}", self.colonists));
+        }
+        res
+    }
+}
+
+/// Saves a design of an empire. A design saved under the name of an existing one replaces it as
+/// its next revision
+pub fn save_design(state: &mut GameState, empire: EmpireId, design: ShipDesign) -> Result<String, String> {
+    let owner = &mut state.empires[empire];
+    let problems = design.validate(&owner.research);
+    if let Some(problem) = problems.first() {
+        return Err(format!("{} can not be saved: {problem}", design.name));
+    }
+    let mut design = ShipDesign { name: design.name.trim().to_owned(), ..design };
+    match owner.designs.iter_mut().find(|d| d.name == design.name) {
+        Some(existing) => {
+            design.revision = existing.revision + 1;
+            *existing = design;
+            Ok(format!("{} was saved as revision {}", existing.name, existing.revision))
+        }
+        None => {
+            design.revision = 0;
+            let text = format!("{} was saved", design.name);
+            owner.designs.push(design);
+            Ok(text)
+        }
+    }
+}
+
+pub fn delete_design(state: &mut GameState, empire: EmpireId, name: &str) -> Result<String, String> {
+    let designs = &mut state.empires[empire].designs;
+    let Some(index) = designs.iter().position(|d| d.name == name) else {
+        return Err(format!("There is no design called {name}"));
+    };
+    designs.remove(index);
+    Ok(format!("{name} was deleted"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::tech::TECHNOLOGIES;
+
+    use super::*;
+
+    #[test]
+    fn test_definitions() {
+        assert!(!HULLS.is_empty());
+        assert!(!COMPONENTS.is_empty());
+        for tech in TECHNOLOGIES.iter() {
+            for id in &tech.components {
+                assert!(component(id).is_some(), "{} unlocks unknown {id}", tech.id);
+            }
+        }
+        let research = Research::default();
+        for design in STARTING_DESIGNS.iter() {
+            assert_eq!(design.validate(&research), Vec::<String>::new(), "{} is invalid", design.name);
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut design = ShipDesign::new("Test", "corvette");
+        design.components = vec!["fission_reactor".to_owned(), "chemical_thruster".to_owned()];
+        let stats = design.stats();
+        assert_eq!(stats.mass, 150.0);
+        assert_eq!(stats.power_output, 10.0);
+        assert_eq!(stats.power_draw, 2.0);
+        assert_eq!(stats.speed, SPEED_SCALE * 200.0 / 150.0);
+        assert_eq!(stats.cost, hull("corvette").unwrap().cost + component("fission_reactor").unwrap().cost
+            + component("chemical_thruster").unwrap().cost);
+    }
+
+    #[test]
+    fn test_validation() {
+        let research = Research::default();
+        let mut design = ShipDesign::new("Test", "corvette");
+        design.components = vec!["mass_driver".to_owned(); 3];
+        let problems = design.validate(&research);
+        assert!(problems.iter().any(|p| p.contains("Weapon")));
+        assert!(problems.iter().any(|p| p.contains("power")));
+
+        design.components = vec!["laser".to_owned()];
+        assert!(design.validate(&research).iter().any(|p| p.contains("not researched")));
+
+        design.components = vec!["fission_reactor".to_owned(), "mass_driver".to_owned()];
+        design.name = " ".to_owned();
+        assert_eq!(design.validate(&research).len(), 1);
+    }
+
+    #[test]
+    fn test_save_revisions() {
+        let mut state = GameState::new(0);
+        let player = state.player;
+        let count = state.player_empire().designs.len();
+        let mut design = ShipDesign::new("Lancer", "frigate");
+        design.components = vec!["fission_reactor".to_owned(), "mass_driver".to_owned()];
+
+        assert!(save_design(&mut state, player, design.clone()).is_ok());
+        design.components.push("mass_driver".to_owned());
+        assert!(save_design(&mut state, player, design.clone()).is_ok());
+        let designs = &state.player_empire().designs;
+        assert_eq!(designs.len(), count + 1);
+        assert_eq!(designs.last().unwrap().revision, 1);
+
+        design.components.push("no_such_component".to_owned());
+        assert!(save_design(&mut state, player, design).is_err());
+        assert!(delete_design(&mut state, player, "Lancer").is_ok());
+        assert_eq!(state.player_empire().designs.len(), count);
+    }
+}
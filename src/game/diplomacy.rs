@@ -0,0 +1,725 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use crate::game::ai::strength;
+use crate::game::empire::EmpireId;
+use crate::game::ship::ShipRole;
+use crate::game::state::GameState;
+use crate::game::territory::territory;
+use crate::game::turn::{EventKind, Phase, TurnReport};
+
+/// Opinion of another empire stays within plus and minus this
+pub const MAX_OPINION: f32 = 100.0;
+
+/// Turns a proposal waits for the answer of the player before it lapses
+pub const PROPOSAL_TURNS: u32 = 3;
+
+/// Energy every trade agreement earns each side a turn
+pub const TRADE_INCOME: f32 = 3.0;
+
+/// Share of the research income of a partner that research sharing adds
+pub const RESEARCH_SHARE: f32 = 0.1;
+
+/// Opinion modifier of an empire whose proposal was turned down, which keeps it from making
+/// another until the modifier fades
+pub const REJECTED: &str = "Rejected our proposal";
+
+/// Opinion modifier both sides get when they make peace, for as many turns as its value. No war
+/// can be declared while it lasts
+const TRUCE: &str = "Truce";
+
+const TRUCE_TURNS: f32 = 30.0;
+
+/// Opinion of a species for every ethic it shares with another, and lost for every ethic of the
+/// other it opposes
+const ETHICS_OPINION: f32 = 10.0;
+
+/// Opinion lost every turn warships of an empire spend in the borders of another
+const TRESPASS_OPINION: f32 = -2.0;
+
+/// Trespassing costs no more opinion than this
+const TRESPASS_LIMIT: f32 = -20.0;
+
+/// How two empires stand towards each other, from the worst to the closest
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Display, Serialize, Deserialize)]
+pub enum Stance {
+    War,
+    #[default]
+    Peace,
+    #[strum(to_string = "Non-aggression pact")]
+    NonAggression,
+    Alliance,
+    Federation,
+}
+
+/// An agreement two empires at peace may sign on top of their stance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Serialize, Deserialize)]
+pub enum Treaty {
+    /// Energy for both sides
+    #[strum(to_string = "Trade agreement")]
+    Trade,
+    /// Each side gets a share of the research of the other
+    #[strum(to_string = "Research sharing")]
+    ResearchSharing,
+    /// Warships may pass through the borders of the other side without giving offence
+    #[strum(to_string = "Open borders")]
+    OpenBorders,
+}
+
+impl Treaty {
+    pub const ALL: [Treaty; 3] = [Treaty::Trade, Treaty::ResearchSharing, Treaty::OpenBorders];
+
+    fn describe(&self) -> &'static str {
+        match self {
+            Treaty::Trade => { "a trade agreement" }
+            Treaty::ResearchSharing => { "a research sharing treaty" }
+            Treaty::OpenBorders => { "an open borders treaty" }
+        }
+    }
+}
+
+/// Something one empire puts to another. Declaring war and ending a treaty need no answer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Proposal {
+    DeclareWar,
+    Peace,
+    NonAggression,
+    Alliance,
+    Federation,
+    Treaty(Treaty),
+    CancelTreaty(Treaty),
+}
+
+impl Proposal {
+    /// Everything an empire may put to another, in the order the diplomacy screen lists them
+    pub const ALL: [Proposal; 11] = [
+        Proposal::DeclareWar,
+        Proposal::Peace,
+        Proposal::NonAggression,
+        Proposal::Alliance,
+        Proposal::Federation,
+        Proposal::Treaty(Treaty::Trade),
+        Proposal::Treaty(Treaty::ResearchSharing),
+        Proposal::Treaty(Treaty::OpenBorders),
+        Proposal::CancelTreaty(Treaty::Trade),
+        Proposal::CancelTreaty(Treaty::ResearchSharing),
+        Proposal::CancelTreaty(Treaty::OpenBorders),
+    ];
+
+    /// Whether the proposal takes effect without the other side agreeing to it
+    pub fn is_unilateral(&self) -> bool {
+        matches!(self, Proposal::DeclareWar | Proposal::CancelTreaty(_))
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Proposal::DeclareWar => { "a declaration of war".to_owned() }
+            Proposal::Peace => { "peace".to_owned() }
+            Proposal::NonAggression => { "a non-aggression pact".to_owned() }
+            Proposal::Alliance => { "an alliance".to_owned() }
+            Proposal::Federation => { "a federation".to_owned() }
+            Proposal::Treaty(treaty) => { treaty.describe().to_owned() }
+            Proposal::CancelTreaty(treaty) => { format!("the end of {}", treaty.describe()) }
+        }
+    }
+
+    /// What the player does by choosing the proposal
+    pub fn label(&self) -> String {
+        match self {
+            Proposal::DeclareWar => { "Declare war".to_owned() }
+            Proposal::CancelTreaty(treaty) => { format!("End {}", treaty.describe()) }
+            _ => { format!("Propose {}", self.describe()) }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Relation {
+    pub stance: Stance,
+    /// Turn the stance was taken
+    pub since: u32,
+    pub treaties: Vec<Treaty>,
+}
+
+/// A reason for an empire to think better or worse of another, which fades with time
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpinionModifier {
+    pub reason: String,
+    pub value: f32,
+    /// Amount the value moves towards zero every turn
+    pub decay: f32,
+}
+
+/// A proposal waiting for the answer of the player
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingProposal {
+    pub from: EmpireId,
+    pub to: EmpireId,
+    pub proposal: Proposal,
+    /// Turn the proposal was made
+    pub turn: u32,
+}
+
+/// Something that happened between two empires
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiplomaticEvent {
+    pub turn: u32,
+    pub empires: (EmpireId, EmpireId),
+    pub text: String,
+}
+
+/// Relations between all the empires of a game
+#[derive(Debug, Clone, Default)]
+pub struct Diplomacy {
+    /// By the pair of empires, the lower id first
+    relations: BTreeMap<(EmpireId, EmpireId), Relation>,
+    /// By the empire holding the opinion and the empire it is of
+    modifiers: BTreeMap<(EmpireId, EmpireId), Vec<OpinionModifier>>,
+    pub pending: Vec<PendingProposal>,
+    /// Oldest first
+    pub history: Vec<DiplomaticEvent>,
+}
+
+fn key(a: EmpireId, b: EmpireId) -> (EmpireId, EmpireId) {
+    (a.min(b), a.max(b))
+}
+
+impl Diplomacy {
+    /// Relation of two empires, peace for those that never dealt with each other
+    pub fn relation(&self, a: EmpireId, b: EmpireId) -> Relation {
+        self.relations.get(&key(a, b)).cloned().unwrap_or_default()
+    }
+
+    pub fn stance(&self, a: EmpireId, b: EmpireId) -> Stance {
+        self.relations.get(&key(a, b)).map_or(Stance::Peace, |r| r.stance)
+    }
+
+    pub fn set_stance(&mut self, a: EmpireId, b: EmpireId, stance: Stance, turn: u32) {
+        let relation = self.relations.entry(key(a, b)).or_default();
+        relation.stance = stance;
+        relation.since = turn;
+        if stance == Stance::War {
+            relation.treaties.clear();
+        }
+    }
+
+    /// Whether the treaty holds between the empires. A federation includes every treaty
+    pub fn has_treaty(&self, a: EmpireId, b: EmpireId, treaty: Treaty) -> bool {
+        self.relations.get(&key(a, b))
+            .is_some_and(|r| r.stance == Stance::Federation || r.treaties.contains(&treaty))
+    }
+
+    /// Empires the empire has signed the treaty with, in the order of their ids
+    pub fn partners(&self, empire: EmpireId, treaty: Treaty) -> Vec<EmpireId> {
+        self.relations.keys()
+            .filter(|(a, b)| *a == empire || *b == empire)
+            .map(|(a, b)| if *a == empire { *b } else { *a })
+            .filter(|other| self.has_treaty(empire, *other, treaty))
+            .collect()
+    }
+
+    /// Whether the empires see what the sensors of the other see
+    pub fn shares_sensors(&self, a: EmpireId, b: EmpireId) -> bool {
+        self.stance(a, b) >= Stance::Alliance
+    }
+
+    /// Adds to the opinion `of` has of `towards`, on top of what it already holds for the reason
+    pub fn add_modifier(&mut self, of: EmpireId, towards: EmpireId, reason: &str, value: f32, decay: f32) {
+        let modifiers = self.modifiers.entry((of, towards)).or_default();
+        match modifiers.iter_mut().find(|m| m.reason == reason) {
+            Some(modifier) => {
+                modifier.value = (modifier.value + value).clamp(-MAX_OPINION, MAX_OPINION);
+                modifier.decay = decay;
+            }
+            None => {
+                modifiers.push(OpinionModifier { reason: reason.to_owned(), value, decay });
+            }
+        }
+    }
+
+    pub fn modifiers(&self, of: EmpireId, towards: EmpireId) -> &[OpinionModifier] {
+        self.modifiers.get(&(of, towards)).map_or(&[], |m| m.as_slice())
+    }
+
+    fn modifier(&self, of: EmpireId, towards: EmpireId, reason: &str) -> f32 {
+        self.modifiers(of, towards).iter().find(|m| m.reason == reason).map_or(0.0, |m| m.value)
+    }
+
+    pub fn record(&mut self, turn: u32, a: EmpireId, b: EmpireId, text: String) {
+        self.history.push(DiplomaticEvent { turn, empires: key(a, b), text });
+    }
+
+    /// What happened between the two empires, oldest first
+    pub fn history_of(&self, a: EmpireId, b: EmpireId) -> impl DoubleEndedIterator<Item = &DiplomaticEvent> {
+        self.history.iter().filter(move |e| e.empires == key(a, b))
+    }
+
+    /// Moves every opinion modifier towards zero, dropping those that faded away
+    fn decay(&mut self) {
+        for modifiers in self.modifiers.values_mut() {
+            for modifier in modifiers.iter_mut() {
+                modifier.value = if modifier.value > 0.0 {
+                    (modifier.value - modifier.decay).max(0.0)
+                } else {
+                    (modifier.value + modifier.decay).min(0.0)
+                };
+            }
+            modifiers.retain(|m| m.value != 0.0);
+        }
+    }
+}
+
+/// Military might of an empire, the strength of all its fleets
+pub fn military(state: &GameState, empire: EmpireId) -> f32 {
+    state.fleets.iter().filter(|f| f.owner == empire).map(strength).sum()
+}
+
+/// Whether some third empire is at war with both
+fn common_enemy(state: &GameState, a: EmpireId, b: EmpireId) -> bool {
+    let diplomacy = &state.diplomacy;
+    (0..state.empires.len())
+        .filter(|e| *e != a && *e != b)
+        .any(|e| diplomacy.stance(a, e) == Stance::War && diplomacy.stance(b, e) == Stance::War)
+}
+
+/// How aggressive the empire is from 0 to 1, the middle for an empire the player controls
+fn aggression(state: &GameState, empire: EmpireId) -> f32 {
+    state.empires[empire].ai.as_ref().map_or(0.5, |ai| ai.personality.aggression)
+}
+
+/// Every reason for the opinion `of` has of `towards`, with how much it counts
+pub fn opinion_breakdown(state: &GameState, of: EmpireId, towards: EmpireId) -> Vec<(String, f32)> {
+    let diplomacy = &state.diplomacy;
+    let relation = diplomacy.relation(of, towards);
+    let mut res: Vec<(String, f32)> = Vec::new();
+    if state.empires[of].ai.is_some() {
+        res.push(("Temperament".to_owned(), (0.5 - aggression(state, of)) * 20.0));
+    }
+    res.push(match relation.stance {
+        Stance::War => { ("At war".to_owned(), -50.0) }
+        Stance::Peace => { ("At peace".to_owned(), 0.0) }
+        Stance::NonAggression => { ("Non-aggression pact".to_owned(), 10.0) }
+        Stance::Alliance => { ("Allies".to_owned(), 25.0) }
+        Stance::Federation => { ("Federation".to_owned(), 40.0) }
+    });
+    if !relation.treaties.is_empty() && relation.stance != Stance::Federation {
+        res.push(("Treaties".to_owned(), 5.0 * relation.treaties.len() as f32));
+    }
+    if state.empires[of].contacts.contains(&towards) && relation.stance < Stance::Alliance {
+        res.push(("Shared border".to_owned(), -10.0));
+    }
+    if common_enemy(state, of, towards) {
+        res.push(("Common enemy".to_owned(), 15.0));
+    }
+    let (shared, opposed) = state.empires[of].species.ethics_with(&state.empires[towards].species);
+    res.push(("Shared ethics".to_owned(), ETHICS_OPINION * shared as f32));
+    res.push(("Opposed ethics".to_owned(), -ETHICS_OPINION * opposed as f32));
+    res.extend(diplomacy.modifiers(of, towards).iter().map(|m| (m.reason.clone(), m.value)));
+    res.retain(|(_, value)| *value != 0.0);
+    res
+}
+
+/// What `of` thinks of `towards`, from `-MAX_OPINION` to `MAX_OPINION`
+pub fn opinion(state: &GameState, of: EmpireId, towards: EmpireId) -> f32 {
+    opinion_breakdown(state, of, towards).iter()
+        .map(|(_, value)| value)
+        .sum::<f32>()
+        .clamp(-MAX_OPINION, MAX_OPINION)
+}
+
+/// Whether `from` may put the proposal to `to` as things stand
+pub fn check_proposal(state: &GameState, from: EmpireId, to: EmpireId, proposal: &Proposal) -> Result<(), String> {
+    let Some(other) = state.empires.get(to).filter(|_| to != from) else {
+        return Err("There is no such empire to deal with".to_owned());
+    };
+    if !state.empires[from].contacts.contains(&to) {
+        return Err(format!("{} has not been met yet", other.name));
+    }
+    let relation = state.diplomacy.relation(from, to);
+    let stance = relation.stance;
+    match proposal {
+        Proposal::DeclareWar if stance == Stance::War => {
+            Err(format!("Already at war with {}", other.name))
+        }
+        Proposal::DeclareWar if state.diplomacy.modifier(from, to, TRUCE) > 0.0 => {
+            Err(format!("A truce with {} holds for {:.0} more turns", other.name, state.diplomacy.modifier(from, to, TRUCE)))
+        }
+        Proposal::Peace if stance != Stance::War => {
+            Err(format!("Not at war with {}", other.name))
+        }
+        Proposal::NonAggression if stance != Stance::Peace => {
+            Err(format!("A non-aggression pact needs peace, not {}", stance.to_string().to_lowercase()))
+        }
+        Proposal::Alliance if !matches!(stance, Stance::Peace | Stance::NonAggression) => {
+            Err(format!("An alliance needs peace, not {}", stance.to_string().to_lowercase()))
+        }
+        Proposal::Federation if stance != Stance::Alliance => {
+            Err("A federation can only grow out of an alliance".to_owned())
+        }
+        Proposal::Treaty(_) if stance == Stance::War => {
+            Err(format!("At war with {}", other.name))
+        }
+        Proposal::Treaty(treaty) if state.diplomacy.has_treaty(from, to, *treaty) => {
+            Err(format!("{treaty} already holds"))
+        }
+        Proposal::CancelTreaty(treaty) if !relation.treaties.contains(treaty) => {
+            Err(format!("There is no {} to end", treaty.to_string().to_lowercase()))
+        }
+        _ => { Ok(()) }
+    }
+}
+
+/// Every reason for `empire` to accept a proposal from `from`, with how much it counts. The
+/// proposal is accepted when they add up to more than zero
+pub fn acceptance(state: &GameState, empire: EmpireId, from: EmpireId, proposal: &Proposal) -> Vec<(String, f32)> {
+    let personality = state.empires[empire].ai.as_ref().map(|ai| ai.personality);
+    let economy = personality.map_or(0.5, |p| p.economy);
+    let research = personality.map_or(0.5, |p| p.research);
+    let aggression = aggression(state, empire);
+    let relation = state.diplomacy.relation(empire, from);
+    let turns = state.turn.saturating_sub(relation.since) as f32;
+    // Weaker empires look for friends, stronger ones can do without
+    let balance = ((military(state, from) + 1.0) / (military(state, empire) + 1.0) - 1.0).clamp(-1.5, 1.5) * 20.0;
+
+    let mut res = vec![("Opinion".to_owned(), opinion(state, empire, from) * 0.5)];
+    match proposal {
+        Proposal::DeclareWar | Proposal::CancelTreaty(_) => {}
+        Proposal::Peace => {
+            res.push(("Base".to_owned(), -10.0));
+            res.push(("War weariness".to_owned(), turns.min(30.0) * 2.0));
+            res.push(("Balance of power".to_owned(), balance));
+            res.push(("Temperament".to_owned(), (0.5 - aggression) * 30.0));
+        }
+        Proposal::NonAggression => {
+            res.push(("Balance of power".to_owned(), balance));
+            res.push(("Temperament".to_owned(), (0.5 - aggression) * 20.0));
+        }
+        Proposal::Alliance => {
+            res.push(("Base".to_owned(), -30.0));
+            res.push(("Balance of power".to_owned(), balance));
+            if common_enemy(state, empire, from) {
+                res.push(("Common enemy".to_owned(), 20.0));
+            }
+        }
+        Proposal::Federation => {
+            res.push(("Base".to_owned(), -60.0));
+            res.push(("Years of alliance".to_owned(), turns.min(30.0)));
+        }
+        Proposal::Treaty(Treaty::Trade) => {
+            res.push(("Base".to_owned(), 5.0));
+            res.push(("Interest in trade".to_owned(), economy * 10.0));
+        }
+        Proposal::Treaty(Treaty::ResearchSharing) => {
+            res.push(("Base".to_owned(), -5.0));
+            res.push(("Interest in research".to_owned(), research * 10.0));
+            // Sharing with an empire that knows less gives away more than it brings
+            let ahead = state.empires[empire].research.known.len() as f32 - state.empires[from].research.known.len() as f32;
+            res.push(("Technology gap".to_owned(), -(ahead.max(0.0) * 3.0).min(30.0)));
+        }
+        Proposal::Treaty(Treaty::OpenBorders) => {
+            res.push(("Base".to_owned(), -5.0));
+        }
+    }
+    res.retain(|(_, value)| *value != 0.0);
+    res
+}
+
+/// Whether `empire` accepts the proposal from `from`
+pub fn accepts(state: &GameState, empire: EmpireId, from: EmpireId, proposal: &Proposal) -> bool {
+    acceptance(state, empire, from, proposal).iter().map(|(_, value)| value).sum::<f32>() > 0.0
+}
+
+/// Carries out a proposal both sides agreed to, or one that needs no agreement. Returns what
+/// happened, which also goes into the history of the two empires
+fn enact(state: &mut GameState, from: EmpireId, to: EmpireId, proposal: &Proposal) -> String {
+    let turn = state.turn;
+    let (a, b) = (state.empires[from].name.clone(), state.empires[to].name.clone());
+    let diplomacy = &mut state.diplomacy;
+    let text = match proposal {
+        Proposal::DeclareWar => {
+            let before = diplomacy.stance(from, to);
+            diplomacy.set_stance(from, to, Stance::War, turn);
+            diplomacy.add_modifier(to, from, "Declared war on us", -40.0, 1.0);
+            if before >= Stance::NonAggression {
+                diplomacy.add_modifier(to, from, "Broke a pact", -30.0, 0.5);
+            }
+            format!("{a} declared war on {b}")
+        }
+        Proposal::Peace => {
+            diplomacy.set_stance(from, to, Stance::Peace, turn);
+            // Both sides are in no hurry to fight again
+            diplomacy.add_modifier(from, to, TRUCE, TRUCE_TURNS, 1.0);
+            diplomacy.add_modifier(to, from, TRUCE, TRUCE_TURNS, 1.0);
+            format!("{a} and {b} made peace")
+        }
+        Proposal::NonAggression => {
+            diplomacy.set_stance(from, to, Stance::NonAggression, turn);
+            format!("{a} and {b} signed a non-aggression pact")
+        }
+        Proposal::Alliance => {
+            diplomacy.set_stance(from, to, Stance::Alliance, turn);
+            format!("{a} and {b} formed an alliance")
+        }
+        Proposal::Federation => {
+            diplomacy.set_stance(from, to, Stance::Federation, turn);
+            format!("{a} and {b} founded a federation")
+        }
+        Proposal::Treaty(treaty) => {
+            diplomacy.relations.entry(key(from, to)).or_default().treaties.push(*treaty);
+            format!("{a} and {b} signed {}", treaty.describe())
+        }
+        Proposal::CancelTreaty(treaty) => {
+            diplomacy.relations.entry(key(from, to)).or_default().treaties.retain(|t| t != treaty);
+            diplomacy.add_modifier(to, from, "Ended a treaty", -10.0, 0.5);
+            format!("{a} ended {} with {b}", treaty.describe())
+        }
+    };
+    diplomacy.record(turn, from, to, text.clone());
+
+    // The allies of an empire that is attacked join the war
+    if *proposal == Proposal::DeclareWar {
+        let allies: Vec<EmpireId> = (0..state.empires.len())
+            .filter(|e| *e != from && *e != to && state.diplomacy.stance(*e, to) >= Stance::Alliance)
+            .filter(|e| state.diplomacy.stance(*e, from) == Stance::Peace)
+            .collect();
+        for ally in allies {
+            let diplomacy = &mut state.diplomacy;
+            diplomacy.set_stance(ally, from, Stance::War, turn);
+            diplomacy.add_modifier(ally, from, "Attacked an ally", -20.0, 1.0);
+            let text = format!("{} joined the war against {a} on the side of {b}", state.empires[ally].name);
+            diplomacy.record(turn, ally, from, text);
+        }
+    }
+    text
+}
+
+fn report_kind(proposal: &Proposal) -> EventKind {
+    match proposal {
+        Proposal::DeclareWar => { EventKind::WarDeclared }
+        Proposal::CancelTreaty(_) => { EventKind::TreatyCancelled }
+        _ => { EventKind::TreatySigned }
+    }
+}
+
+/// Puts a proposal of `from` to `to`. The computer answers at once, the player gets until
+/// [`PROPOSAL_TURNS`] have passed. Returns what came of it for the report, or why the proposal can
+/// not be made
+pub fn propose(state: &mut GameState, from: EmpireId, to: EmpireId, proposal: Proposal) -> Result<(EventKind, String), String> {
+    check_proposal(state, from, to, &proposal)?;
+    if proposal.is_unilateral() || (state.empires[to].ai.is_some() && accepts(state, to, from, &proposal)) {
+        return Ok((report_kind(&proposal), enact(state, from, to, &proposal)));
+    }
+    let (a, b) = (state.empires[from].name.clone(), state.empires[to].name.clone());
+    if state.empires[to].ai.is_some() {
+        let text = format!("{b} rejected {} with {a}", proposal.describe());
+        state.diplomacy.add_modifier(from, to, REJECTED, -5.0, 0.5);
+        state.diplomacy.record(state.turn, from, to, text.clone());
+        return Ok((EventKind::ProposalRejected, text));
+    }
+    if state.diplomacy.pending.iter().any(|p| p.from == from && p.to == to && p.proposal == proposal) {
+        return Err(format!("{b} is still considering {}", proposal.describe()));
+    }
+    state.diplomacy.pending.push(PendingProposal { from, to, proposal, turn: state.turn });
+    Ok((EventKind::ProposalReceived, format!("{a} proposes {} to {b}", proposal.describe())))
+}
+
+/// Answers the oldest open proposal `from` made to `empire`. Returns what came of it for the
+/// report, or why there is nothing to answer
+pub fn answer_proposal(state: &mut GameState, empire: EmpireId, from: EmpireId, accept: bool) -> Result<(EventKind, String), String> {
+    let index = state.diplomacy.pending.iter()
+        .position(|p| p.from == from && p.to == empire)
+        .ok_or_else(|| format!("{} has proposed nothing", state.empires[from].name))?;
+    let pending = state.diplomacy.pending.remove(index);
+    // Things may have changed since the proposal was made
+    check_proposal(state, from, empire, &pending.proposal)?;
+    if accept {
+        return Ok((report_kind(&pending.proposal), enact(state, from, empire, &pending.proposal)));
+    }
+    let text = format!("{} rejected {} with {}", state.empires[empire].name, pending.proposal.describe(), state.empires[from].name);
+    state.diplomacy.add_modifier(from, empire, REJECTED, -5.0, 0.5);
+    state.diplomacy.record(state.turn, from, empire, text.clone());
+    Ok((EventKind::ProposalRejected, text))
+}
+
+/// Works out how the relations between the empires changed over the turn: old grudges fade,
+/// warships in foreign borders give offence and unanswered proposals lapse
+pub fn relations(state: &mut GameState, report: &mut TurnReport) {
+    state.diplomacy.decay();
+
+    let territory = territory(state);
+    let mut trespassers: Vec<(EmpireId, EmpireId)> = state.fleets.iter()
+        .filter(|f| f.has_role(&ShipRole::Warship))
+        .filter_map(|f| Some((territory[f.system()?]?, f.owner)))
+        .filter(|(owner, fleet)| owner != fleet)
+        .collect();
+    trespassers.sort_unstable();
+    trespassers.dedup();
+    for (owner, trespasser) in trespassers {
+        let diplomacy = &mut state.diplomacy;
+        let stance = diplomacy.stance(owner, trespasser);
+        if matches!(stance, Stance::Peace | Stance::NonAggression)
+            && !diplomacy.has_treaty(owner, trespasser, Treaty::OpenBorders)
+            && diplomacy.modifier(owner, trespasser, "Trespassing") > TRESPASS_LIMIT
+        {
+            diplomacy.add_modifier(owner, trespasser, "Trespassing", TRESPASS_OPINION, 0.5);
+        }
+    }
+
+    let turn = state.turn;
+    let (lapsed, pending) = std::mem::take(&mut state.diplomacy.pending).into_iter()
+        .partition(|p| turn >= p.turn + PROPOSAL_TURNS);
+    state.diplomacy.pending = pending;
+    for proposal in lapsed {
+        let text = format!(
+            "{} let {} from {} lapse",
+            state.empires[proposal.to].name,
+            proposal.proposal.describe(),
+            state.empires[proposal.from].name
+        );
+        state.diplomacy.add_modifier(proposal.from, proposal.to, "Ignored our proposal", -5.0, 0.5);
+        state.diplomacy.record(turn, proposal.from, proposal.to, text.clone());
+        if proposal.to == state.player || proposal.from == state.player {
+            report.add(Phase::Diplomacy, EventKind::ProposalRejected, text);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::clock::TimeMode;
+    use crate::game::economy::{compute_ledger, RESEARCH_SHARING};
+    use crate::game::resources::Resource;
+    use crate::game::state::Setup;
+    use super::*;
+
+    /// A game of the player and two computer empires that all met each other
+    fn game() -> GameState {
+        let mut state = GameState::with_setup(1, TimeMode::Turns, &Setup { opponents: 2, ..Setup::default() });
+        for empire in state.empires.iter_mut() {
+            empire.contacts = (0..3).filter(|e| *e != empire.id).collect();
+        }
+        state
+    }
+
+    #[test]
+    fn test_check_proposal() {
+        let mut state = game();
+        assert!(check_proposal(&state, 0, 0, &Proposal::Peace).is_err());
+        assert!(check_proposal(&state, 0, 1, &Proposal::Peace).is_err());
+        assert!(check_proposal(&state, 0, 1, &Proposal::Federation).is_err());
+        assert!(check_proposal(&state, 0, 1, &Proposal::NonAggression).is_ok());
+        assert!(check_proposal(&state, 0, 1, &Proposal::CancelTreaty(Treaty::Trade)).is_err());
+        state.empires[0].contacts.clear();
+        assert!(check_proposal(&state, 0, 1, &Proposal::DeclareWar).is_err());
+    }
+
+    #[test]
+    fn test_war_and_peace() {
+        let mut state = game();
+        state.diplomacy.relations.entry(key(0, 1)).or_default().treaties.push(Treaty::Trade);
+        assert!(!state.hostile(0, 1));
+
+        let (kind, _) = propose(&mut state, 0, 1, Proposal::DeclareWar).unwrap();
+        assert_eq!(kind, EventKind::WarDeclared);
+        assert!(state.hostile(0, 1));
+        assert!(state.diplomacy.relation(0, 1).treaties.is_empty());
+        assert!(opinion_breakdown(&state, 1, 0).iter().any(|(reason, _)| reason == "Declared war on us"));
+        assert!(opinion(&state, 1, 0) < 0.0);
+
+        // Long wars wear everyone down
+        state.turn += 40;
+        state.diplomacy.modifiers.clear();
+        assert!(accepts(&state, 1, 0, &Proposal::Peace));
+        propose(&mut state, 0, 1, Proposal::Peace).unwrap();
+        assert_eq!(state.diplomacy.stance(0, 1), Stance::Peace);
+        assert!(check_proposal(&state, 1, 0, &Proposal::DeclareWar).is_err());
+        assert_eq!(state.diplomacy.history_of(0, 1).count(), 2);
+    }
+
+    #[test]
+    fn test_allies_join_war() {
+        let mut state = game();
+        state.diplomacy.set_stance(1, 2, Stance::Alliance, 0);
+        assert!(state.diplomacy.shares_sensors(1, 2));
+        propose(&mut state, 0, 1, Proposal::DeclareWar).unwrap();
+        assert!(state.hostile(2, 0));
+        assert!(opinion(&state, 2, 0) < 0.0);
+    }
+
+    #[test]
+    fn test_proposal_to_player() {
+        let mut state = game();
+        let (kind, _) = propose(&mut state, 1, 0, Proposal::Treaty(Treaty::Trade)).unwrap();
+        assert_eq!(kind, EventKind::ProposalReceived);
+        assert!(propose(&mut state, 1, 0, Proposal::Treaty(Treaty::Trade)).is_err());
+        answer_proposal(&mut state, 0, 1, true).unwrap();
+        assert!(state.diplomacy.has_treaty(1, 0, Treaty::Trade));
+        assert_eq!(state.diplomacy.partners(0, Treaty::Trade), vec![1]);
+        assert!(answer_proposal(&mut state, 0, 1, true).is_err());
+
+        let energy = |state: &GameState| compute_ledger(state, 0).by_source().into_iter()
+            .find(|(source, _)| source == "Trade agreements")
+            .map_or(0.0, |(_, amounts)| amounts.get(Resource::Energy));
+        assert_eq!(energy(&state), TRADE_INCOME);
+
+        // Unanswered proposals lapse and leave a grudge
+        propose(&mut state, 2, 0, Proposal::NonAggression).unwrap();
+        for _ in 0..PROPOSAL_TURNS {
+            state.turn += 1;
+            relations(&mut state, &mut TurnReport::default());
+        }
+        assert!(state.diplomacy.pending.is_empty());
+        assert!(state.diplomacy.modifiers(2, 0).iter().any(|m| m.reason == "Ignored our proposal"));
+    }
+
+    #[test]
+    fn test_research_sharing_settles() {
+        let mut state = game();
+        state.diplomacy.relations.entry(key(0, 1)).or_default().treaties.push(Treaty::ResearchSharing);
+        let shared = |state: &GameState, empire: EmpireId| state.empires[empire].ledger.by_source().into_iter()
+            .find(|(s, _)| s == RESEARCH_SHARING)
+            .map_or(0.0, |(_, r)| r.get(Resource::Research));
+        let mut history = Vec::new();
+        for _ in 0..10 {
+            let ledgers = [compute_ledger(&state, 0), compute_ledger(&state, 1)];
+            for (empire, ledger) in ledgers.into_iter().enumerate() {
+                state.empires[empire].ledger = ledger;
+            }
+            history.push((shared(&state, 0), shared(&state, 1)));
+        }
+        // Each gets its share of what the other researched itself, which does not grow by what
+        // was shared back
+        let own = |empire: EmpireId| state.empires[empire].ledger.income_except(RESEARCH_SHARING).get(Resource::Research);
+        assert!(own(0) > 0.0 && own(1) > 0.0);
+        assert_eq!(history[9], (own(1) * RESEARCH_SHARE, own(0) * RESEARCH_SHARE));
+        assert_eq!(history[1], history[9]);
+    }
+
+    #[test]
+    fn test_modifiers_fade() {
+        let mut diplomacy = Diplomacy::default();
+        diplomacy.add_modifier(0, 1, "Insult", -3.0, 1.0);
+        diplomacy.add_modifier(0, 1, "Insult", -1.0, 1.0);
+        assert_eq!(diplomacy.modifier(0, 1, "Insult"), -4.0);
+        for _ in 0..4 {
+            diplomacy.decay();
+        }
+        assert!(diplomacy.modifiers(0, 1).is_empty());
+    }
+}
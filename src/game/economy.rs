@@ -0,0 +1,106 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::game::body::BodyType;
+use crate::game::diplomacy::{Treaty, RESEARCH_SHARE, TRADE_INCOME};
+use crate::game::empire::EmpireId;
+use crate::game::resources::{Ledger, Resource, Resources};
+use crate::game::state::GameState;
+
+/// Income of the capital system, available from the start of the game
+fn capital_income() -> Resources {
+    Resources::from_pairs(&[
+        (Resource::Energy, 10.0),
+        (Resource::Minerals, 8.0),
+        (Resource::Alloys, 2.0),
+        (Resource::Food, 5.0),
+        (Resource::Research, 5.0),
+        (Resource::Influence, 2.0),
+    ])
+}
+
+/// Income of every owned system other than the capital
+fn system_income() -> Resources {
+    Resources::from_pairs(&[(Resource::Energy, 2.0), (Resource::Minerals, 2.0)])
+}
+
+/// Ledger source of the research partners share
+pub const RESEARCH_SHARING: &str = "Research sharing";
+
+/// Energy upkeep of a fleet
+const FLEET_UPKEEP: f32 = 2.0;
+
+/// Works out the income and expenses of an empire for the current turn
+pub fn compute_ledger(state: &GameState, empire: EmpireId) -> Ledger {
+    let mut ledger = Ledger::default();
+    let owner = &state.empires[empire];
+
+    // The capital pays only for as long as the empire holds a colony there
+    let capital_held = state.galaxy.systems[owner.capital].bodies.iter()
+        .any(|b| b.colony.as_ref().is_some_and(|c| c.owner == empire));
+    if capital_held {
+        ledger.record_income("Capital", &capital_income());
+    }
+    let systems = owner.systems.iter().filter(|s| **s != owner.capital).count();
+    ledger.record_income("Owned systems", &(system_income() * systems as f32));
+
+    let modifiers = owner.modifiers();
+    for (_, body) in state.galaxy.colonies() {
+        let (Some(colony), BodyType::Planet(planet)) = (&body.colony, &body.kind) else { continue };
+        if colony.owner == empire {
+            ledger.record_income("Jobs", &colony.production(planet, &modifiers));
+            ledger.record("Population upkeep", Resource::Food, -colony.food_consumption());
+            ledger.record_expense("Building upkeep", &colony.upkeep(&modifiers));
+        }
+    }
+
+    let fleets = state.fleets.iter().filter(|f| f.owner == empire).count();
+    ledger.record("Fleet upkeep", Resource::Energy, -FLEET_UPKEEP * fleets as f32);
+
+    let traders = state.diplomacy.partners(empire, Treaty::Trade).len();
+    ledger.record("Trade agreements", Resource::Energy, TRADE_INCOME * traders as f32);
+
+    if let Some(ai) = &owner.ai {
+        ledger.record_income("Difficulty", &(ledger.income() * ai.difficulty.income_bonus()));
+    }
+
+    // Partners share from what they researched themselves on the last turn, what was shared with
+    // them is not passed on
+    let shared: f32 = state.diplomacy.partners(empire, Treaty::ResearchSharing).iter()
+        .map(|p| state.empires[*p].ledger.income_except(RESEARCH_SHARING).get(Resource::Research) * RESEARCH_SHARE)
+        .sum();
+    ledger.record(RESEARCH_SHARING, Resource::Research, shared);
+
+    ledger
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capital_income_needs_a_colony() {
+        let mut state = GameState::new(3);
+        let has_capital = |state: &GameState| compute_ledger(state, 0).by_source().iter().any(|(s, _)| s == "Capital");
+        assert!(has_capital(&state));
+
+        let capital = state.empires[0].capital;
+        for body in state.galaxy.systems[capital].bodies.iter_mut() {
+            body.colony = None;
+        }
+        assert!(!has_capital(&state));
+    }
+}
@@ -0,0 +1,135 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ratatui::style::Color;
+use crate::game::ai::Ai;
+use crate::game::design::{ShipDesign, STARTING_DESIGNS};
+use crate::game::galaxy::SystemId;
+use crate::game::knowledge::Knowledge;
+use crate::game::ship::ShipRole;
+use crate::game::resources::{Ledger, Resource, Resources};
+use crate::game::species::Species;
+use crate::game::tech::{Modifier, Research};
+use crate::game::victory::Statistics;
+
+pub type EmpireId = usize;
+
+/// Number of past turns kept to show how the stockpile develops
+pub const HISTORY_LENGTH: usize = 24;
+
+#[derive(Clone)]
+pub struct Empire {
+    pub id: EmpireId,
+    pub name: String,
+    pub color: Color,
+    pub capital: SystemId,
+    pub species: Species,
+    pub systems: Vec<SystemId>,
+    pub stockpile: Resources,
+    /// Income and expenses of the last processed turn
+    pub ledger: Ledger,
+    /// Stockpile at the end of each of the last turns, oldest first
+    pub history: Vec<Resources>,
+    pub research: Research,
+    pub designs: Vec<ShipDesign>,
+    /// Number of ships the empire has built, used to name new ones
+    pub ships_built: u32,
+    pub knowledge: Knowledge,
+    /// Empires whose borders met those of the empire
+    pub contacts: Vec<EmpireId>,
+    /// The computer player of the empire, `None` when a person plays it
+    pub ai: Option<Ai>,
+    /// Where the empire stood at the end of every turn, oldest first
+    pub statistics: Vec<Statistics>,
+    /// Turn the empire lost its last colony
+    pub eliminated: Option<u32>,
+}
+
+impl Empire {
+    pub fn new(id: EmpireId, name: &str, color: Color, capital: SystemId) -> Self {
+        Self {
+            id,
+            name: name.to_owned(),
+            color,
+            capital,
+            species: Species::default(),
+            systems: vec![capital],
+            stockpile: Resources::from_pairs(&[
+                (Resource::Energy, 100.0),
+                (Resource::Minerals, 100.0),
+                (Resource::Alloys, 50.0),
+                (Resource::Food, 50.0),
+                (Resource::Influence, 25.0),
+            ]),
+            ledger: Ledger::default(),
+            history: Vec::new(),
+            research: Research::default(),
+            designs: STARTING_DESIGNS.clone(),
+            ships_built: 0,
+            knowledge: Knowledge::default(),
+            contacts: Vec::new(),
+            ai: None,
+            statistics: Vec::new(),
+            eliminated: None,
+        }
+    }
+
+    /// Lasting bonuses of the technologies the empire knows and the traits of its species
+    pub fn modifiers(&self) -> Vec<Modifier> {
+        let mut res = self.research.modifiers();
+        res.extend(self.species.modifiers());
+        res
+    }
+
+    pub fn design(&self, name: &str) -> Option<&ShipDesign> {
+        self.designs.iter().find(|d| d.name == name)
+    }
+
+    /// The first design of the empire that fills the role
+    pub fn design_for(&self, role: ShipRole) -> Option<&ShipDesign> {
+        self.designs.iter().find(|d| ShipRole::of(d) == role)
+    }
+
+    /// Applies the ledger of a turn to the stockpile. Returns the resources the empire could not
+    /// pay for, which are dropped to zero
+    pub fn apply_ledger(&mut self, ledger: Ledger) -> Vec<Resource> {
+        self.stockpile += ledger.net();
+        self.ledger = ledger;
+
+        let mut deficits = Vec::new();
+        let stockpile = self.stockpile;
+        for (resource, amount) in stockpile.iter() {
+            if amount < 0.0 {
+                deficits.push(resource);
+                self.stockpile.set(resource, 0.0);
+            }
+        }
+
+        self.history.push(self.stockpile);
+        if self.history.len() > HISTORY_LENGTH {
+            self.history.remove(0);
+        }
+        deficits
+    }
+
+    /// Change of a resource's stockpile over the last turn
+    pub fn trend(&self, resource: Resource) -> f32 {
+        match self.history.as_slice() {
+            [.., before, last] => { last.get(resource) - before.get(resource) }
+            _ => { self.ledger.net().get(resource) }
+        }
+    }
+}
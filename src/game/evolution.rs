@@ -0,0 +1,315 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use rand::Rng;
+use crate::game::body::{Body, BodyType, PlanetZone, StarType, SOLAR_MASS, SOLAR_RADIUS};
+use crate::game::system::System;
+
+/// How many years a star ages during one year of game time. Real stars live for millions of
+/// years, so without speeding them up nothing would ever happen during a game
+pub const STELLAR_TIME_SCALE: f64 = 100_000.0;
+
+/// Game years of advance notice given before a star leaves its current stage
+pub const WARNING_YEARS: f64 = 25.0;
+
+/// Stars can not be older than the universe
+const MAX_STAR_AGE: f64 = 13.8e9;
+
+/// Fraction of the main sequence lifetime a star spends as a red giant
+const RED_GIANT_DURATION: f64 = 0.1;
+
+/// Stars heavier than this (in solar masses) end in a supernova instead of a white dwarf
+const SUPERNOVA_MASS: f32 = 8.0;
+
+/// Supernova remnants heavier than this (in solar masses) collapse into a black hole
+const BLACK_HOLE_MASS: f32 = 25.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StellarStage {
+    MainSequence,
+    RedGiant,
+    Remnant,
+}
+
+impl StellarStage {
+    pub fn of(class: &StarType) -> Self {
+        match class {
+            StarType::RedGiant => { StellarStage::RedGiant }
+            StarType::Pulsar | StarType::WhiteDwarf | StarType::BlackHole => { StellarStage::Remnant }
+            _ => { StellarStage::MainSequence }
+        }
+    }
+
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            StellarStage::MainSequence => { "Main sequence" }
+            StellarStage::RedGiant => { "Red giant" }
+            StellarStage::Remnant => { "Stellar remnant" }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StellarEvent {
+    /// The star will leave its current stage in about `years_left` game years
+    Warning { star: String, next: StellarStage, years_left: f64 },
+    /// The star swelled into a red giant and swallowed the listed planets
+    RedGiant { star: String, engulfed: Vec<String> },
+    /// The star shed its outer layers and left a white dwarf behind
+    WhiteDwarf { star: String },
+    /// The star exploded, leaving a remnant of type `remnant` and destroying the listed planets
+    Supernova { star: String, remnant: StarType, destroyed: Vec<String> },
+}
+
+impl StellarEvent {
+    pub fn describe(&self) -> String {
+        match self {
+            StellarEvent::Warning { star, next, years_left } => {
+                format!("{star} will become a {} in about {years_left:.0} years", next.to_str().to_lowercase())
+            }
+            StellarEvent::RedGiant { star, engulfed } => {
+                format!("{star} has become a red giant, engulfing {} planet(s)", engulfed.len())
+            }
+            StellarEvent::WhiteDwarf { star } => {
+                format!("{star} has collapsed into a white dwarf")
+            }
+            StellarEvent::Supernova { star, destroyed, .. } => {
+                format!("{star} went supernova, destroying {} planet(s)", destroyed.len())
+            }
+        }
+    }
+}
+
+/// Main sequence lifetime of a star of the given mass (in kg), in years
+pub fn main_sequence_lifetime(mass: f32) -> f64 {
+    1.0e10 * f64::from(mass / SOLAR_MASS).powf(-2.5)
+}
+
+/// Picks a random age for a freshly generated star, somewhere within its current stage
+pub fn generate_age<R: Rng + ?Sized>(class: &StarType, mass: f32, rng: &mut R) -> f64 {
+    let lifetime = main_sequence_lifetime(mass);
+    match StellarStage::of(class) {
+        StellarStage::MainSequence => { rng.gen_range(0.0..lifetime.min(MAX_STAR_AGE)) }
+        StellarStage::RedGiant => { lifetime * rng.gen_range(1.0..1.0 + RED_GIANT_DURATION) }
+        StellarStage::Remnant => { lifetime * (1.0 + RED_GIANT_DURATION) }
+    }
+}
+
+impl System {
+    pub fn stage(&self) -> StellarStage {
+        match &self.star.kind {
+            BodyType::Star(class) => { StellarStage::of(class) }
+            _ => { StellarStage::Remnant }
+        }
+    }
+
+    /// Star age at which it leaves its current stage, if it ever does
+    fn next_transition(&self) -> Option<(StellarStage, f64)> {
+        let lifetime = main_sequence_lifetime(self.star.mass);
+        match self.stage() {
+            StellarStage::MainSequence => { Some((StellarStage::RedGiant, lifetime)) }
+            StellarStage::RedGiant => { Some((StellarStage::Remnant, lifetime * (1.0 + RED_GIANT_DURATION))) }
+            StellarStage::Remnant => { None }
+        }
+    }
+
+    /// Ages the star by `game_years` of game time and applies the consequences to the system.
+    /// Returns what happened along with the bodies the star destroyed
+    pub fn advance_age(&mut self, game_years: f64) -> (Vec<StellarEvent>, Vec<Body>) {
+        let mut res = Vec::new();
+        let mut lost = Vec::new();
+        self.star_age += game_years * STELLAR_TIME_SCALE;
+
+        while let Some((next, age)) = self.next_transition() {
+            if self.star_age < age {
+                let years_left = (age - self.star_age) / STELLAR_TIME_SCALE;
+                if years_left <= WARNING_YEARS && !self.evolution_warned {
+                    self.evolution_warned = true;
+                    res.push(StellarEvent::Warning { star: self.star.name.clone(), next, years_left });
+                }
+                break;
+            }
+
+            self.evolution_warned = false;
+            res.push(match next {
+                StellarStage::RedGiant => { self.become_red_giant(&mut lost) }
+                _ => { self.collapse(&mut lost) }
+            });
+        }
+
+        (res, lost)
+    }
+
+    fn become_red_giant(&mut self, lost: &mut Vec<Body>) -> StellarEvent {
+        let solar_masses = self.star.mass / SOLAR_MASS;
+        // Supergiants swell much more than their lighter siblings
+        let growth = if solar_masses >= SUPERNOVA_MASS { 300.0 } else { 100.0 };
+        self.star.radius = self.star.radius.max(growth * SOLAR_RADIUS);
+        self.star.kind = BodyType::Star(StarType::RedGiant);
+
+        // Star radii are stored in km while orbit radii are in m
+        let surface = self.star.radius * 1000.0;
+        let (engulfed, kept): (Vec<_>, Vec<_>) = self.bodies.drain(..)
+            .partition(|b| b.orbit_radius.unwrap_or(0.0) <= surface);
+        self.bodies = kept;
+        self.update_zones();
+
+        let event = StellarEvent::RedGiant {
+            star: self.star.name.clone(),
+            engulfed: engulfed.iter().map(|b| b.name.clone()).collect(),
+        };
+        lost.extend(engulfed);
+        event
+    }
+
+    fn collapse(&mut self, lost: &mut Vec<Body>) -> StellarEvent {
+        let solar_masses = self.star.mass / SOLAR_MASS;
+        if solar_masses < SUPERNOVA_MASS {
+            self.star.kind = BodyType::Star(StarType::WhiteDwarf);
+            self.star.mass = solar_masses.min(1.4) * 0.6 * SOLAR_MASS;
+            self.star.radius = 0.01 * SOLAR_RADIUS;
+            self.update_zones();
+            return StellarEvent::WhiteDwarf { star: self.star.name.clone() };
+        }
+
+        let remnant = if solar_masses >= BLACK_HOLE_MASS { StarType::BlackHole } else { StarType::Pulsar };
+        self.star.kind = BodyType::Star(remnant.clone());
+        self.star.mass = match remnant {
+            StarType::BlackHole => { solar_masses * 0.3 }
+            _ => { 1.4 }
+        } * SOLAR_MASS;
+        self.star.radius = 2.0e-5 * SOLAR_RADIUS;
+
+        // Only the planets far out in the system survive the blast
+        let (destroyed, kept): (Vec<_>, Vec<_>) = self.bodies.drain(..)
+            .partition(|b| b.zone != Some(PlanetZone::OuterRing));
+        self.bodies = kept;
+        self.update_zones();
+
+        let event = StellarEvent::Supernova {
+            star: self.star.name.clone(),
+            remnant,
+            destroyed: destroyed.iter().map(|b| b.name.clone()).collect(),
+        };
+        lost.extend(destroyed);
+        event
+    }
+
+    /// Re-evaluates which zone every planet is in after the star has changed
+    fn update_zones(&mut self) {
+        let zones: Vec<PlanetZone> = self.bodies.iter()
+            .map(|b| self.zone_at(b.orbit_radius.unwrap_or(0.0)))
+            .collect();
+        for (body, zone) in self.bodies.iter_mut().zip(zones) {
+            body.zone = Some(zone);
+        }
+    }
+
+    /// Game years until the star leaves its current stage, if it ever does
+    pub fn years_left(&self) -> Option<f64> {
+        self.next_transition().map(|(_, age)| (age - self.star_age).max(0.0) / STELLAR_TIME_SCALE)
+    }
+
+    pub fn evolution_info(&self) -> Vec<String> {
+        let mut res: Vec<String> = Vec::new();
+        res.push(format!("Stage: {}", self.stage().to_str()));
+        res.push(format!("Age: {:.1} Myr", self.star_age / 1.0e6));
+        if let (Some((next, _)), Some(years)) = (self.next_transition(), self.years_left()) {
+            res.push(format!("{} in: {:.0} years", next.to_str(), years));
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A system around a star of the given mass, in the middle of its main sequence lifetime
+    fn system_with_star(solar_masses: f32) -> System {
+        let mut system = System::from_seed(3);
+        system.star.kind = BodyType::Star(StarType::B);
+        system.star.mass = solar_masses * SOLAR_MASS;
+        system.star.radius = 5.0 * SOLAR_RADIUS;
+        system.star_age = main_sequence_lifetime(system.star.mass) / 2.0;
+        system.evolution_warned = false;
+        system
+    }
+
+    fn years_until_transition(system: &System) -> f64 {
+        let (_, age) = system.next_transition().unwrap();
+        (age - system.star_age) / STELLAR_TIME_SCALE
+    }
+
+    #[test]
+    fn test_warning_before_red_giant() {
+        let mut system = system_with_star(4.0);
+        let years = years_until_transition(&system);
+
+        assert!(system.advance_age(years - WARNING_YEARS - 1.0).0.is_empty());
+        let (events, _) = system.advance_age(2.0);
+        assert!(matches!(events.as_slice(), [StellarEvent::Warning { next: StellarStage::RedGiant, .. }]));
+        // The warning is only given once
+        assert!(system.advance_age(1.0).0.is_empty());
+        assert_eq!(system.stage(), StellarStage::MainSequence);
+    }
+
+    #[test]
+    fn test_red_giant_engulfs_inner_planets() {
+        let mut system = system_with_star(4.0);
+        let planets = system.bodies.len();
+        let hz_start = *system.zone_bounds(&PlanetZone::HabitableZone).start();
+
+        let (events, lost) = system.advance_age(years_until_transition(&system) + 1.0);
+        let engulfed = match events.last() {
+            Some(StellarEvent::RedGiant { engulfed, .. }) => { engulfed.len() }
+            _ => { panic!("expected the star to become a red giant, got {events:?}") }
+        };
+        assert_eq!(lost.len(), engulfed);
+
+        assert_eq!(system.stage(), StellarStage::RedGiant);
+        assert_eq!(system.bodies.len(), planets - engulfed);
+        assert!(system.bodies.iter().all(|b| b.orbit_radius.unwrap() > system.star.radius * 1000.0));
+        assert!(*system.zone_bounds(&PlanetZone::HabitableZone).start() > hz_start);
+    }
+
+    #[test]
+    fn test_light_star_becomes_white_dwarf() {
+        let mut system = system_with_star(4.0);
+        system.advance_age(years_until_transition(&system) + 1.0);
+        let (events, _) = system.advance_age(years_until_transition(&system) + 1.0);
+
+        assert!(matches!(events.last(), Some(StellarEvent::WhiteDwarf { .. })));
+        assert!(matches!(system.star.kind, BodyType::Star(StarType::WhiteDwarf)));
+        assert!(system.advance_age(1.0e6).0.is_empty());
+    }
+
+    #[test]
+    fn test_supernova_remnants() {
+        for (mass, remnant) in [(12.0, StarType::Pulsar), (40.0, StarType::BlackHole)] {
+            let mut system = system_with_star(mass);
+            // Skip the whole red giant phase at once
+            let (events, _) = system.advance_age(main_sequence_lifetime(system.star.mass) / STELLAR_TIME_SCALE);
+
+            assert!(matches!(events.first(), Some(StellarEvent::RedGiant { .. })));
+            match events.last() {
+                Some(StellarEvent::Supernova { remnant: r, .. }) => { assert_eq!(*r, remnant) }
+                _ => { panic!("expected a supernova, got {events:?}") }
+            }
+            assert_eq!(system.stage(), StellarStage::Remnant);
+        }
+    }
+}
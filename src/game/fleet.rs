@@ -0,0 +1,509 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::game::body::BodyId;
+use crate::game::empire::EmpireId;
+use crate::game::galaxy::{Galaxy, SystemId};
+use crate::game::ship::{Ship, ShipRole};
+use crate::game::state::GameState;
+use crate::game::turn::{EventKind, Phase, TurnReport};
+
+pub type FleetId = usize;
+
+/// Share of its strength a new fleet can lose in a battle before it retreats
+pub const DEFAULT_RETREAT_THRESHOLD: f32 = 0.5;
+
+/// Meters a day a ship covers inside a system for every light year a day of its hyperlane speed
+pub const IN_SYSTEM_SPEED: f32 = 3.0e10;
+
+/// Flight of a fleet between two bodies of a system. The fleet heads for the point where the
+/// target was when it set off, in a straight line
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transfer {
+    pub target: BodyId,
+    /// Where the fleet set off from, in meters from the star
+    pub from: (f32, f32),
+    pub to: (f32, f32),
+    /// Meters flown so far
+    pub travelled: f32,
+}
+
+impl Transfer {
+    pub fn distance(&self) -> f32 {
+        ((self.to.0 - self.from.0).powi(2) + (self.to.1 - self.from.1).powi(2)).sqrt()
+    }
+
+    /// Current position of the fleet, in meters from the star
+    pub fn position(&self) -> (f32, f32) {
+        let t = if self.distance() > 0.0 { self.travelled / self.distance() } else { 1.0 };
+        (self.from.0 + (self.to.0 - self.from.0) * t, self.from.1 + (self.to.1 - self.from.1) * t)
+    }
+}
+
+#[derive(Clone)]
+pub struct Fleet {
+    pub id: FleetId,
+    pub name: String,
+    pub owner: EmpireId,
+    /// The system the fleet is in, or the one it last left when it is travelling along a lane
+    pub location: SystemId,
+    /// Systems the fleet still has to pass through, the next one first
+    pub route: Vec<SystemId>,
+    /// Systems the fleet was ordered to in turn, the route leads through all of them
+    pub waypoints: Vec<SystemId>,
+    /// Light years travelled along the lane to the next system of the route
+    pub progress: f32,
+    /// Body of the system the fleet is at, the star when it has just arrived
+    pub orbit: BodyId,
+    pub transfer: Option<Transfer>,
+    /// Share of its strength the fleet can lose in a battle before it retreats, 1 to fight on
+    /// until the last ship
+    pub retreat_threshold: f32,
+    pub ships: Vec<Ship>,
+}
+
+impl Fleet {
+    pub fn new(id: FleetId, name: &str, owner: EmpireId, location: SystemId) -> Self {
+        Self {
+            id,
+            name: name.to_owned(),
+            owner,
+            location,
+            route: Vec::new(),
+            waypoints: Vec::new(),
+            progress: 0.0,
+            orbit: 0,
+            transfer: None,
+            retreat_threshold: DEFAULT_RETREAT_THRESHOLD,
+            ships: Vec::new(),
+        }
+    }
+
+    pub fn has_role(&self, role: &ShipRole) -> bool {
+        self.ships.iter().any(|s| s.role == *role)
+    }
+
+    /// Removes a ship of the given role from the fleet and returns it
+    pub fn take_ship(&mut self, role: &ShipRole) -> Option<Ship> {
+        let index = self.ships.iter().position(|s| s.role == *role)?;
+        Some(self.ships.remove(index))
+    }
+
+    /// The system the fleet is in, `None` while it is between systems
+    pub fn system(&self) -> Option<SystemId> {
+        (self.progress == 0.0).then_some(self.location)
+    }
+
+    /// Whether the fleet has somewhere to go
+    pub fn is_moving(&self) -> bool {
+        !self.route.is_empty()
+    }
+
+    /// Light years a day the fleet travels, which is the speed of its slowest ship
+    pub fn speed(&self) -> f32 {
+        self.ships.iter()
+            .map(|s| s.design.stats().speed)
+            .min_by(f32::total_cmp)
+            .unwrap_or(0.0)
+    }
+
+    /// Meters a day the fleet covers inside a system
+    pub fn in_system_speed(&self) -> f32 {
+        self.speed() * IN_SYSTEM_SPEED
+    }
+
+    /// Days until the fleet reaches the body it is flying to inside the system
+    pub fn transfer_eta(&self) -> Option<u64> {
+        let transfer = self.transfer.as_ref()?;
+        let speed = self.in_system_speed();
+        if speed <= 0.0 {
+            return None;
+        }
+        Some(((transfer.distance() - transfer.travelled) / speed).ceil() as u64)
+    }
+
+    /// Where the fleet is inside its system, in meters from the star
+    pub fn orbital_position(&self, galaxy: &Galaxy, days: u64) -> (f32, f32) {
+        if let Some(transfer) = &self.transfer {
+            return transfer.position();
+        }
+        galaxy.systems[self.location].body(self.orbit).map_or((0.0, 0.0), |b| b.orbital_position(days))
+    }
+
+    /// Where the fleet is in the galaxy, somewhere along a lane while it travels
+    pub fn position(&self, galaxy: &Galaxy) -> (f32, f32) {
+        let from = galaxy.positions[self.location];
+        let Some(next) = self.route.first().filter(|_| self.system().is_none()) else {
+            return from;
+        };
+        let to = galaxy.positions[*next];
+        let t = self.progress / galaxy.distance(self.location, *next);
+        (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t)
+    }
+
+    /// Range and strength of the best sensors in the fleet
+    pub fn sensors(&self) -> (f32, f32) {
+        self.ships.iter()
+            .map(|s| s.design.stats())
+            .fold((0.0, 0.0), |(range, strength), s| (range.max(s.sensor_range), strength.max(s.sensor_strength)))
+    }
+
+    /// Sensor strength needed to pick up the fleet, which is as hidden as its least stealthy ship
+    pub fn stealth(&self) -> f32 {
+        self.ships.iter()
+            .map(|s| s.design.stats().stealth)
+            .min_by(f32::total_cmp)
+            .unwrap_or(0.0)
+    }
+
+    /// Days until the fleet reaches a system on its route
+    pub fn eta(&self, galaxy: &Galaxy, system: SystemId) -> Option<u64> {
+        let speed = self.speed();
+        if speed <= 0.0 {
+            return None;
+        }
+        let mut days = 0.0;
+        let mut from = self.location;
+        let mut progress = self.progress;
+        for next in &self.route {
+            days += galaxy.lane_days(from, *next, speed) * (1.0 - progress / galaxy.distance(from, *next));
+            if *next == system {
+                return Some(days.ceil() as u64);
+            }
+            progress = 0.0;
+            from = *next;
+        }
+        None
+    }
+
+    /// Days until the fleet reaches its last waypoint
+    pub fn arrival(&self, galaxy: &Galaxy) -> Option<u64> {
+        self.eta(galaxy, *self.waypoints.last()?)
+    }
+
+    /// Moves the fleet along its route for the given number of days and returns the waypoints it
+    /// reached
+    pub fn advance(&mut self, galaxy: &Galaxy, days: u64) -> Vec<SystemId> {
+        let mut res = Vec::new();
+        if self.is_moving() {
+            // Hyperlanes are entered from the star
+            self.orbit = 0;
+        }
+        let mut days = days as f32;
+        while let Some(&next) = self.route.first() {
+            let lane = galaxy.distance(self.location, next);
+            let speed = self.speed() * galaxy.lane_speed(self.location, next);
+            if self.progress + speed * days < lane {
+                self.progress += speed * days;
+                break;
+            }
+            days -= (lane - self.progress) / speed;
+            self.progress = 0.0;
+            self.location = next;
+            self.orbit = 0;
+            self.route.remove(0);
+            if self.waypoints.first() == Some(&next) {
+                res.push(self.waypoints.remove(0));
+            }
+        }
+        res
+    }
+
+    /// Flies the fleet of an in-system transfer on for the given number of days and returns the body
+    /// it reached, if any
+    pub fn advance_transfer(&mut self, days: u64) -> Option<BodyId> {
+        let speed = self.in_system_speed();
+        let transfer = self.transfer.as_mut()?;
+        transfer.travelled += speed * days as f32;
+        if transfer.travelled < transfer.distance() {
+            return None;
+        }
+        self.orbit = transfer.target;
+        self.transfer = None;
+        Some(self.orbit)
+    }
+}
+
+/// Sends a fleet of the empire to another body of the system it is in. The flight takes the
+/// distance between the current positions of the two bodies over the speed of the fleet
+pub fn plot_transfer(
+    state: &mut GameState,
+    empire: EmpireId,
+    fleet: FleetId,
+    target: BodyId,
+) -> Result<String, String> {
+    let days = state.date.days();
+    let Some(f) = state.fleets.iter_mut().find(|f| f.id == fleet && f.owner == empire) else {
+        return Err("The fleet no longer exists".to_owned());
+    };
+    let system = &state.galaxy.systems[f.location];
+    let Some(body) = system.body(target) else {
+        return Err(format!("There is no such body in {}", system.name));
+    };
+    if f.system().is_none() || f.is_moving() {
+        return Err(format!("{} is travelling between systems", f.name));
+    }
+    if f.in_system_speed() <= 0.0 {
+        return Err(format!("{} can not move", f.name));
+    }
+    if f.transfer.is_none() && f.orbit == target {
+        return Err(format!("{} is already at {}", f.name, body.name));
+    }
+    f.transfer = Some(Transfer {
+        target,
+        from: f.orbital_position(&state.galaxy, days),
+        to: body.orbital_position(days),
+        travelled: 0.0,
+    });
+    let eta = f.transfer_eta().unwrap_or_default();
+    Ok(format!("{} will reach {} in {eta} days", f.name, body.name))
+}
+
+/// Plots a course for a fleet of the empire to the destination. Unless `queue` is set the course
+/// replaces the current one, otherwise the destination is added after the last waypoint. A fleet
+/// that is between systems always finishes the lane it is on first
+pub fn plot_course(
+    state: &mut GameState,
+    empire: EmpireId,
+    fleet: FleetId,
+    destination: SystemId,
+    queue: bool,
+) -> Result<String, String> {
+    let Some(target) = state.galaxy.systems.get(destination) else {
+        return Err("There is no such system".to_owned());
+    };
+    let target = target.name.clone();
+    let Some(f) = state.fleets.iter_mut().find(|f| f.id == fleet && f.owner == empire) else {
+        return Err("The fleet no longer exists".to_owned());
+    };
+    let speed = f.speed();
+    if speed <= 0.0 {
+        return Err(format!("{} can not move", f.name));
+    }
+    if !queue {
+        let keep = if f.system().is_none() { 1 } else { 0 };
+        f.route.truncate(keep);
+        f.waypoints.clear();
+    }
+    let start = f.route.last().copied().unwrap_or(f.location);
+    if start == destination {
+        if !queue && f.is_moving() {
+            f.waypoints.push(destination);
+            return Ok(format!("{} is on its way to {target}", f.name));
+        }
+        return Err(format!("{} is already at {target}", f.name));
+    }
+    let Some(path) = state.galaxy.find_route(start, destination, speed) else {
+        return Err(format!("{} can not reach {target}", f.name));
+    };
+    f.route.extend(path);
+    f.waypoints.push(destination);
+    f.transfer = None;
+    let days = f.eta(&state.galaxy, destination).unwrap_or_default();
+    Ok(format!("{} will reach {target} in {days} days", f.name))
+}
+
+/// Drops every waypoint of a fleet. A fleet that is between systems stops at the next one
+pub fn cancel_course(state: &mut GameState, empire: EmpireId, fleet: FleetId) -> Result<String, String> {
+    let Some(f) = state.fleets.iter_mut().find(|f| f.id == fleet && f.owner == empire) else {
+        return Err("The fleet no longer exists".to_owned());
+    };
+    if !f.is_moving() {
+        return Err(format!("{} has no orders to cancel", f.name));
+    }
+    let keep = if f.system().is_none() { 1 } else { 0 };
+    f.route.truncate(keep);
+    f.waypoints = f.route.clone();
+    Ok(format!("{} was ordered to stop", f.name))
+}
+
+/// Moves every fleet along its route or inside its system for the given number of days
+pub fn movement(state: &mut GameState, days: u64, report: &mut TurnReport) {
+    for fleet in state.fleets.iter_mut() {
+        let reported = fleet.owner == state.player;
+        if let Some(body) = fleet.advance_transfer(days).filter(|_| reported) {
+            let system = &state.galaxy.systems[fleet.location];
+            report.add(Phase::Movement, EventKind::FleetArrived, format!(
+                "{} reached {}",
+                fleet.name,
+                system.body(body).map_or(system.name.as_str(), |b| b.name.as_str())
+            ));
+        }
+        for system in fleet.advance(&state.galaxy, days).into_iter().filter(|_| reported) {
+            report.add(Phase::Movement, EventKind::FleetArrived, format!(
+                "{} arrived at {}",
+                fleet.name,
+                state.galaxy.systems[system].name
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::system::System;
+    use super::*;
+
+    /// A system at least two lanes away from the start
+    fn far_system(galaxy: &Galaxy, start: SystemId) -> SystemId {
+        let near = galaxy.neighbours(start);
+        (0..galaxy.systems.len())
+            .find(|s| *s != start && !near.contains(s))
+            .unwrap()
+    }
+
+    /// Days each system takes to reach at the given speed by Dijkstra, to check A* against
+    fn fastest(galaxy: &Galaxy, from: SystemId, speed: f32) -> Vec<f32> {
+        let mut res = vec![f32::INFINITY; galaxy.systems.len()];
+        let mut done = vec![false; galaxy.systems.len()];
+        res[from] = 0.0;
+        while let Some(current) = (0..res.len())
+            .filter(|s| !done[*s] && res[*s].is_finite())
+            .min_by(|a, b| res[*a].total_cmp(&res[*b]))
+        {
+            done[current] = true;
+            for next in galaxy.neighbours(current) {
+                res[next] = res[next].min(res[current] + galaxy.lane_days(current, next, speed));
+            }
+        }
+        res
+    }
+
+    #[test]
+    fn test_find_route() {
+        let mut state = GameState::new(3);
+        for (id, system) in state.galaxy.systems.iter_mut().enumerate() {
+            system.in_nebula = id % 3 == 1;
+        }
+        let galaxy = &state.galaxy;
+        let days = fastest(galaxy, 0, 0.5);
+        for (to, fastest) in days.iter().enumerate().skip(1) {
+            let route = galaxy.find_route(0, to, 0.5).unwrap();
+            assert_eq!(route.last(), Some(&to));
+            let mut from = 0;
+            let mut time = 0.0;
+            for next in route {
+                assert!(galaxy.neighbours(from).contains(&next));
+                time += galaxy.lane_days(from, next, 0.5);
+                from = next;
+            }
+            assert!((time - fastest).abs() < 1e-2);
+        }
+        assert_eq!(galaxy.find_route(0, 0, 0.5), Some(Vec::new()));
+        assert_eq!(galaxy.find_route(0, 1, 0.0), None);
+    }
+
+    #[test]
+    fn test_route_around_nebula() {
+        // Two ways from 0 to 2, the shorter one through 1
+        let mut galaxy = Galaxy {
+            systems: (0..4).map(System::from_seed).collect(),
+            positions: vec![(0.0, 0.0), (10.0, 0.0), (20.0, 0.0), (10.0, 6.0)],
+            hyperlanes: vec![(0, 1), (1, 2), (0, 3), (3, 2)],
+        };
+        for system in galaxy.systems.iter_mut() {
+            system.in_nebula = false;
+        }
+        assert_eq!(galaxy.find_route(0, 2, 1.0), Some(vec![1, 2]));
+        // The longer way takes less time than crossing the nebula
+        galaxy.systems[1].in_nebula = true;
+        assert_eq!(galaxy.find_route(0, 2, 1.0), Some(vec![3, 2]));
+    }
+
+    #[test]
+    fn test_movement_and_eta() {
+        let mut state = GameState::new(4);
+        let (player, fleet) = (state.player, state.fleets[0].id);
+        let start = state.fleets[0].location;
+        let target = far_system(&state.galaxy, start);
+        assert!(plot_course(&mut state, player, fleet, target, false).is_ok());
+        let eta = state.fleets[0].arrival(&state.galaxy).unwrap();
+        assert!(eta > 0);
+
+        let mut report = TurnReport::default();
+        for day in 1..=eta {
+            movement(&mut state, 1, &mut report);
+            let fleet = &state.fleets[0];
+            if day < eta {
+                assert_eq!(fleet.arrival(&state.galaxy), Some(eta - day));
+            }
+        }
+        assert_eq!(state.fleets[0].system(), Some(target));
+        assert!(!state.fleets[0].is_moving());
+        assert_eq!(report.entries.len(), 1);
+    }
+
+    #[test]
+    fn test_queue_and_cancel() {
+        let mut state = GameState::new(5);
+        let (player, fleet) = (state.player, state.fleets[0].id);
+        let start = state.fleets[0].location;
+        let first = state.galaxy.neighbours(start)[0];
+        let second = far_system(&state.galaxy, start);
+        assert!(plot_course(&mut state, player, fleet, start, false).is_err());
+        assert!(plot_course(&mut state, player, fleet, first, false).is_ok());
+        assert!(plot_course(&mut state, player, fleet, second, true).is_ok());
+        assert_eq!(state.fleets[0].waypoints, vec![first, second]);
+        assert_eq!(state.fleets[0].route.first(), Some(&first));
+        assert!(state.fleets[0].eta(&state.galaxy, first) < state.fleets[0].arrival(&state.galaxy));
+
+        // Half way along the first lane the fleet can only stop at the end of it
+        let lane = state.galaxy.distance(start, first);
+        let half = (lane / 2.0 / state.fleets[0].speed()).floor() as u64;
+        movement(&mut state, half.max(1), &mut TurnReport::default());
+        assert_eq!(state.fleets[0].system(), None);
+        assert!(cancel_course(&mut state, player, fleet).is_ok());
+        assert_eq!(state.fleets[0].route, vec![first]);
+
+        movement(&mut state, 100, &mut TurnReport::default());
+        assert_eq!(state.fleets[0].system(), Some(first));
+        assert!(cancel_course(&mut state, player, fleet).is_err());
+    }
+
+    #[test]
+    fn test_in_system_travel() {
+        let mut state = GameState::new(6);
+        let (player, fleet) = (state.player, state.fleets[0].id);
+        let system = &state.galaxy.systems[state.fleets[0].location];
+        let target = system.bodies.last().unwrap();
+        let (id, distance) = (target.id, target.orbit_radius.unwrap());
+        // The home fleet starts in orbit of the homeworld
+        let home = state.fleets[0].orbit;
+        assert!(plot_transfer(&mut state, player, fleet, home).is_err());
+        assert!(plot_transfer(&mut state, player, fleet, 999).is_err());
+        state.fleets[0].orbit = 0;
+        assert!(plot_transfer(&mut state, player, fleet, 0).is_err());
+        assert!(plot_transfer(&mut state, player, fleet, id).is_ok());
+
+        // From the star the flight is as long as the orbit radius
+        let eta = state.fleets[0].transfer_eta().unwrap();
+        assert_eq!(eta, (distance / state.fleets[0].in_system_speed()).ceil() as u64);
+        let mut report = TurnReport::default();
+        for _ in 0..eta {
+            assert_eq!(state.fleets[0].orbit, 0);
+            movement(&mut state, 1, &mut report);
+        }
+        assert_eq!(state.fleets[0].orbit, id);
+        assert!(state.fleets[0].transfer.is_none());
+        assert_eq!(report.entries.len(), 1);
+
+        // Setting off along a hyperlane leaves the orbit
+        let next = state.galaxy.neighbours(state.fleets[0].location)[0];
+        assert!(plot_course(&mut state, player, fleet, next, false).is_ok());
+        movement(&mut state, 1, &mut report);
+        assert_eq!(state.fleets[0].orbit, 0);
+    }
+}
@@ -0,0 +1,244 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::game::body::{random_name, Body, BodyId};
+use crate::game::system::System;
+
+pub type SystemId = usize;
+
+/// Points at a body anywhere in the galaxy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BodyRef {
+    pub system: SystemId,
+    pub body: BodyId,
+}
+
+/// Radius of the galaxy disc in light years
+const GALAXY_RADIUS: f32 = 100.0;
+
+/// Systems closer to each other than this (in light years) are not generated
+const MIN_SYSTEM_DISTANCE: f32 = 8.0;
+
+/// Besides the lanes keeping the galaxy connected, systems get lanes to neighbours closer than this
+const EXTRA_LANE_LENGTH: f32 = 22.0;
+
+/// Share of their speed fleets keep on a hyperlane leading into or out of a nebula
+pub const NEBULA_SPEED: f32 = 0.5;
+
+#[derive(Clone)]
+pub struct Galaxy {
+    pub systems: Vec<System>,
+    /// Position of every system in light years, indexed the same way as `systems`
+    pub positions: Vec<(f32, f32)>,
+    /// Pairs of systems connected by a hyperlane
+    pub hyperlanes: Vec<(SystemId, SystemId)>,
+}
+
+impl Galaxy {
+    pub fn generate<R: Rng + ?Sized>(system_count: usize, rng: &mut R) -> Self {
+        let mut positions: Vec<(f32, f32)> = Vec::new();
+        let mut attempts = 0;
+        while positions.len() < system_count && attempts < system_count * 100 {
+            attempts += 1;
+            // Uniformly distributed over the disc
+            let r = GALAXY_RADIUS * rng.gen_range(0.0f32..=1.0).sqrt();
+            let phi = rng.gen_range(0.0..std::f32::consts::TAU);
+            let position = (r * phi.cos(), r * phi.sin());
+            if positions.iter().all(|p| distance(*p, position) >= MIN_SYSTEM_DISTANCE) {
+                positions.push(position);
+            }
+        }
+
+        let systems: Vec<System> = positions.iter()
+            .map(|_| {
+                let mut system = System::generate_with_rng(rng);
+                system.name = capitalize(&random_name(rng));
+                system
+            })
+            .collect();
+
+        let hyperlanes = Self::generate_hyperlanes(&positions);
+
+        Galaxy {
+            systems,
+            positions,
+            hyperlanes,
+        }
+    }
+
+    /// Connects the systems with a minimum spanning tree, so that every system is reachable,
+    /// and adds lanes between close neighbours so that there are alternative routes
+    fn generate_hyperlanes(positions: &[(f32, f32)]) -> Vec<(SystemId, SystemId)> {
+        let mut res: Vec<(SystemId, SystemId)> = Vec::new();
+        if positions.is_empty() {
+            return res;
+        }
+
+        // Prim's algorithm
+        let mut in_tree = vec![false; positions.len()];
+        let mut best: Vec<(f32, SystemId)> = vec![(f32::INFINITY, 0); positions.len()];
+        best[0] = (0.0, 0);
+        for _ in 0..positions.len() {
+            let next = (0..positions.len())
+                .filter(|i| !in_tree[*i])
+                .min_by(|a, b| best[*a].0.partial_cmp(&best[*b].0).unwrap())
+                .unwrap();
+            in_tree[next] = true;
+            if next != 0 {
+                res.push((best[next].1, next));
+            }
+            for i in 0..positions.len() {
+                let d = distance(positions[next], positions[i]);
+                if !in_tree[i] && d < best[i].0 {
+                    best[i] = (d, next);
+                }
+            }
+        }
+
+        for a in 0..positions.len() {
+            for b in a + 1..positions.len() {
+                let connected = res.iter().any(|l| *l == (a, b) || *l == (b, a));
+                if !connected && distance(positions[a], positions[b]) <= EXTRA_LANE_LENGTH {
+                    res.push((a, b));
+                }
+            }
+        }
+
+        res
+    }
+
+    pub fn body(&self, body: BodyRef) -> Option<&Body> {
+        self.systems.get(body.system)?.body(body.body)
+    }
+
+    pub fn body_mut(&mut self, body: BodyRef) -> Option<&mut Body> {
+        self.systems.get_mut(body.system)?.body_mut(body.body)
+    }
+
+    /// Every colonised body together with its location
+    pub fn colonies(&self) -> impl Iterator<Item = (BodyRef, &Body)> {
+        self.systems.iter().enumerate().flat_map(|(id, system)| {
+            system.bodies.iter()
+                .filter(|b| b.colony.is_some())
+                .map(move |b| (BodyRef { system: id, body: b.id }, b))
+        })
+    }
+
+    /// Systems connected to the given one by a hyperlane
+    pub fn neighbours(&self, system: SystemId) -> Vec<SystemId> {
+        self.hyperlanes.iter()
+            .filter_map(|(a, b)| {
+                if *a == system {
+                    Some(*b)
+                } else if *b == system {
+                    Some(*a)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Distance between two systems in light years
+    pub fn distance(&self, a: SystemId, b: SystemId) -> f32 {
+        distance(self.positions[a], self.positions[b])
+    }
+
+    /// Speed a fleet makes on the hyperlane between two systems, relative to its full speed
+    pub fn lane_speed(&self, a: SystemId, b: SystemId) -> f32 {
+        if self.systems[a].in_nebula || self.systems[b].in_nebula { NEBULA_SPEED } else { 1.0 }
+    }
+
+    /// Days a fleet of the given speed takes to cross the hyperlane between two systems
+    pub fn lane_days(&self, a: SystemId, b: SystemId, speed: f32) -> f32 {
+        self.distance(a, b) / (speed * self.lane_speed(a, b))
+    }
+
+    /// Fastest way along the hyperlanes for a ship of the given speed, found with A*. Lanes through
+    /// nebulae take longer to cross, so the fastest route is not always the shortest. Returns the
+    /// systems to pass through after `from`, ending with `to`
+    pub fn find_route(&self, from: SystemId, to: SystemId, speed: f32) -> Option<Vec<SystemId>> {
+        if from >= self.systems.len() || to >= self.systems.len() || speed <= 0.0 {
+            return None;
+        }
+        let days = |a: SystemId, b: SystemId| self.lane_days(a, b, speed);
+        // At full speed in a straight line, which no route beats
+        let estimate = |a: SystemId| self.distance(a, to) / speed;
+
+        let mut best = vec![f32::INFINITY; self.systems.len()];
+        let mut came_from: Vec<Option<SystemId>> = vec![None; self.systems.len()];
+        let mut open = BinaryHeap::new();
+        best[from] = 0.0;
+        open.push(RouteStep { estimate: estimate(from), system: from });
+        while let Some(RouteStep { system, .. }) = open.pop() {
+            if system == to {
+                let mut res = vec![to];
+                while let Some(previous) = came_from[*res.last().unwrap()] {
+                    res.push(previous);
+                }
+                res.pop();
+                res.reverse();
+                return Some(res);
+            }
+            for next in self.neighbours(system) {
+                let cost = best[system] + days(system, next);
+                if cost < best[next] {
+                    best[next] = cost;
+                    came_from[next] = Some(system);
+                    open.push(RouteStep { estimate: cost + estimate(next), system: next });
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Entry of the A* open set, the one with the lowest estimate comes out of the heap first
+#[derive(PartialEq)]
+struct RouteStep {
+    estimate: f32,
+    system: SystemId,
+}
+
+impl Eq for RouteStep {}
+
+impl Ord for RouteStep {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate).then_with(|| other.system.cmp(&self.system))
+    }
+}
+
+impl PartialOrd for RouteStep {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => { first.to_uppercase().chain(chars).collect() }
+        None => { String::new() }
+    }
+}
@@ -0,0 +1,212 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use rand::Rng;
+use ratatui::style::Color;
+use crate::game::body::{BodyType, StarType, SOLAR_MASS};
+use crate::game::system::System;
+
+/// Fraction of shield capacity a ship recharges every day it spends out of radiation
+pub const SHIELD_RECHARGE: f32 = 0.1;
+
+/// M dwarfs lighter than this (in solar masses) are considered flare stars
+const FLARE_STAR_MASS_LIMIT: f32 = 0.25;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Hazard {
+    /// Hard radiation from a hot star or a pulsar, slowly wears down shields
+    Radiation,
+    /// Nebula gas or pulsar emissions, shorten the range of sensors
+    SensorInterference,
+    /// The star is prone to flares which hit everything in the system
+    SolarFlares,
+}
+
+impl Hazard {
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Hazard::Radiation => { "Radiation" }
+            Hazard::SensorInterference => { "Sensor interference" }
+            Hazard::SolarFlares => { "Solar flares" }
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Hazard::Radiation => { Color::LightMagenta }
+            Hazard::SensorInterference => { Color::LightCyan }
+            Hazard::SolarFlares => { Color::LightYellow }
+        }
+    }
+}
+
+/// A random event caused by the environment of a system
+#[derive(Clone, Debug, PartialEq)]
+pub enum HazardEvent {
+    /// A solar flare, `severity` is the fraction of shield capacity it strips
+    SolarFlare { severity: f32 },
+}
+
+/// System-wide environmental effects, derived from the star and the surroundings of a system
+#[derive(Clone, Debug, PartialEq)]
+pub struct SystemModifiers {
+    pub hazards: Vec<Hazard>,
+    /// Fraction of shield capacity lost by every ship in the system each day
+    pub shield_damage: f32,
+    /// Multiplier applied to the range of every sensor in the system
+    pub sensor_modifier: f32,
+    /// Chance of a solar flare happening on a given day
+    pub flare_chance: f64,
+}
+
+impl Default for SystemModifiers {
+    fn default() -> Self {
+        Self {
+            hazards: Vec::new(),
+            shield_damage: 0.0,
+            sensor_modifier: 1.0,
+            flare_chance: 0.0,
+        }
+    }
+}
+
+impl SystemModifiers {
+    pub fn for_system(system: &System) -> Self {
+        let mut res = Self::default();
+
+        if let BodyType::Star(class) = &system.star.kind {
+            match class {
+                StarType::O => {
+                    res.shield_damage += 0.02;
+                    res.flare_chance += 0.01;
+                }
+                StarType::B => {
+                    res.shield_damage += 0.01;
+                }
+                StarType::M if system.star.mass / SOLAR_MASS < FLARE_STAR_MASS_LIMIT => {
+                    res.flare_chance += 0.03;
+                }
+                StarType::Pulsar => {
+                    res.shield_damage += 0.05;
+                    res.sensor_modifier *= 0.7;
+                }
+                StarType::RedGiant => {
+                    res.flare_chance += 0.02;
+                }
+                StarType::BlackHole => {
+                    res.shield_damage += 0.03;
+                    res.sensor_modifier *= 0.8;
+                }
+                _ => {}
+            }
+        }
+
+        if system.in_nebula {
+            res.sensor_modifier *= 0.5;
+        }
+
+        if res.shield_damage > 0.0 {
+            res.hazards.push(Hazard::Radiation);
+        }
+        if res.sensor_modifier < 1.0 {
+            res.hazards.push(Hazard::SensorInterference);
+        }
+        if res.flare_chance > 0.0 {
+            res.hazards.push(Hazard::SolarFlares);
+        }
+
+        res
+    }
+
+    /// Rolls for random hazard events over the given number of days
+    pub fn roll_events<R: Rng + ?Sized>(&self, days: u32, rng: &mut R) -> Vec<HazardEvent> {
+        let mut res = Vec::new();
+        if self.flare_chance <= 0.0 {
+            return res;
+        }
+        for _ in 0..days {
+            if rng.gen_bool(self.flare_chance) {
+                res.push(HazardEvent::SolarFlare { severity: rng.gen_range(0.1..=0.6) });
+            }
+        }
+        res
+    }
+
+    pub fn make_info(&self) -> Vec<String> {
+        let mut res: Vec<String> = Vec::new();
+        if self.hazards.is_empty() {
+            res.push("Hazards: none".to_owned());
+            return res;
+        }
+        res.push(format!(
+            "Hazards: {}",
+            self.hazards.iter().map(|h| h.to_str()).collect::<Vec<_>>().join(", ")
+        ));
+        if self.shield_damage > 0.0 {
+            res.push(format!("Shield damage: {:.1}% per day", self.shield_damage * 100.0));
+        }
+        if self.sensor_modifier < 1.0 {
+            res.push(format!("Sensor range: {:.0}%", self.sensor_modifier * 100.0));
+        }
+        if self.flare_chance > 0.0 {
+            res.push(format!("Flare chance: {:.1}% per day", self.flare_chance * 100.0));
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_pulsar_hazards() {
+        let mut system = System::from_seed(0);
+        system.star.kind = BodyType::Star(StarType::Pulsar);
+        system.in_nebula = false;
+        let modifiers = system.modifiers();
+        assert_eq!(modifiers.hazards, vec![Hazard::Radiation, Hazard::SensorInterference]);
+        assert!(modifiers.shield_damage > 0.0);
+    }
+
+    #[test]
+    fn test_nebula_interference() {
+        let mut system = System::from_seed(0);
+        system.star.kind = BodyType::Star(StarType::G);
+        system.in_nebula = true;
+        let modifiers = system.modifiers();
+        assert_eq!(modifiers.hazards, vec![Hazard::SensorInterference]);
+        assert_eq!(modifiers.sensor_modifier, 0.5);
+    }
+
+    #[test]
+    fn test_flare_star() {
+        let mut system = System::from_seed(0);
+        system.star.kind = BodyType::Star(StarType::M);
+        system.star.mass = 0.1 * SOLAR_MASS;
+        system.in_nebula = false;
+        let modifiers = system.modifiers();
+        assert_eq!(modifiers.hazards, vec![Hazard::SolarFlares]);
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let events = modifiers.roll_events(1000, &mut rng);
+        assert!(!events.is_empty());
+        assert!(SystemModifiers::default().roll_events(1000, &mut rng).is_empty());
+    }
+}
@@ -0,0 +1,346 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use strum::Display;
+use crate::game::body::{Body, BodyId};
+use crate::game::empire::EmpireId;
+use crate::game::fleet::FleetId;
+use crate::game::galaxy::{BodyRef, SystemId};
+use crate::game::narrative::{trigger, Trigger};
+use crate::game::ship::ShipRole;
+use crate::game::state::GameState;
+use crate::game::system::System;
+use crate::game::turn::{EventKind, Phase, TurnReport};
+
+/// Days a survey ship with sensors of strength 1 takes to survey a body
+pub const SURVEY_DAYS: f32 = 30.0;
+
+/// How much an empire knows about a system or a body
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Display)]
+pub enum KnowledgeLevel {
+    #[default]
+    Unknown,
+    /// The star has been spotted, nothing more
+    Detected,
+    /// The empire has been there and charted the bodies
+    Explored,
+    /// Survey ships have studied every body
+    Surveyed,
+}
+
+/// What an empire knows about a system
+#[derive(Clone)]
+pub struct SystemKnowledge {
+    pub level: KnowledgeLevel,
+    /// The system as it was when the empire last had eyes on it
+    pub snapshot: System,
+    /// Turn the system was last seen
+    pub last_seen: u32,
+    /// Survey progress of the bodies, from 0 to 1
+    surveys: HashMap<BodyId, f32>,
+}
+
+impl SystemKnowledge {
+    pub fn survey_progress(&self, body: BodyId) -> f32 {
+        self.surveys.get(&body).copied().unwrap_or(0.0)
+    }
+
+    /// How much is known about a body of the system
+    pub fn body_level(&self, body: BodyId) -> KnowledgeLevel {
+        if self.survey_progress(body) >= 1.0 {
+            KnowledgeLevel::Surveyed
+        } else {
+            self.level.min(KnowledgeLevel::Explored)
+        }
+    }
+
+    /// The system as the empire last saw it, without the bodies if it has not been explored
+    pub fn known_system(&self) -> System {
+        let mut res = self.snapshot.clone();
+        if self.level < KnowledgeLevel::Explored {
+            res.bodies.clear();
+        }
+        res
+    }
+
+    /// Description of a body, with the details only a survey reveals left out until it is done
+    pub fn body_info(&self, body: &Body) -> Vec<String> {
+        if self.body_level(body.id) == KnowledgeLevel::Surveyed {
+            return body.make_info();
+        }
+        let mut res = vec![format!("Name: {}", body.name)];
+        if self.level >= KnowledgeLevel::Explored {
+            res.push(format!("Type: {}", body.get_class_as_string()));
+        }
+        res.push(format!("Orbit radius: {:.3e} km", body.orbit_radius.unwrap_or(0.0)));
+        res.push(format!("Not surveyed ({:.0}% done)", self.survey_progress(body.id) * 100.0));
+        res
+    }
+
+    fn all_surveyed(&self) -> bool {
+        std::iter::once(&self.snapshot.star)
+            .chain(self.snapshot.bodies.iter())
+            .all(|b| self.survey_progress(b.id) >= 1.0)
+    }
+}
+
+/// Everything an empire knows about the galaxy
+#[derive(Clone, Default)]
+pub struct Knowledge {
+    systems: HashMap<SystemId, SystemKnowledge>,
+    /// Systems the empire has eyes on right now
+    pub visible: Vec<SystemId>,
+    /// Fleets of other empires its sensors picked up in the last detection pass
+    pub fleets: Vec<FleetId>,
+}
+
+impl Knowledge {
+    pub fn system(&self, id: SystemId) -> Option<&SystemKnowledge> {
+        self.systems.get(&id)
+    }
+
+    /// Every system the empire knows of
+    pub fn systems(&self) -> impl Iterator<Item = (SystemId, &SystemKnowledge)> {
+        self.systems.iter().map(|(id, known)| (*id, known))
+    }
+
+    pub fn level(&self, id: SystemId) -> KnowledgeLevel {
+        self.system(id).map_or(KnowledgeLevel::Unknown, |s| s.level)
+    }
+
+    pub fn is_visible(&self, id: SystemId) -> bool {
+        self.visible.contains(&id)
+    }
+
+    pub fn is_detected(&self, fleet: FleetId) -> bool {
+        self.fleets.contains(&fleet)
+    }
+
+    /// Notes the star of a system without learning anything else about it
+    fn detect(&mut self, id: SystemId, system: &System, turn: u32) {
+        self.systems.entry(id).or_insert_with(|| SystemKnowledge {
+            level: KnowledgeLevel::Detected,
+            snapshot: system.clone(),
+            last_seen: turn,
+            surveys: HashMap::new(),
+        });
+    }
+
+    /// Looks at a system, charting its bodies. Returns whether it was explored for the first time
+    fn see(&mut self, id: SystemId, system: &System, turn: u32) -> bool {
+        self.detect(id, system, turn);
+        let known = self.systems.get_mut(&id).unwrap();
+        let first = known.level < KnowledgeLevel::Explored;
+        known.level = known.level.max(KnowledgeLevel::Explored);
+        known.snapshot = system.clone();
+        known.last_seen = turn;
+        if known.all_surveyed() {
+            known.level = KnowledgeLevel::Surveyed;
+        }
+        first
+    }
+
+    /// Advances the survey of a body. Returns whether the survey was completed just now
+    fn survey(&mut self, id: SystemId, body: BodyId, amount: f32) -> bool {
+        let Some(known) = self.systems.get_mut(&id) else { return false };
+        let progress = known.surveys.entry(body).or_default();
+        if *progress >= 1.0 {
+            return false;
+        }
+        *progress = (*progress + amount).min(1.0);
+        let done = *progress >= 1.0;
+        if known.all_surveyed() {
+            known.level = KnowledgeLevel::Surveyed;
+        }
+        done
+    }
+
+    /// Learns everything about a system at once, as an empire does about its home
+    pub fn chart(&mut self, id: SystemId, system: &System, turn: u32) {
+        self.see(id, system, turn);
+        for body in std::iter::once(&system.star).chain(system.bodies.iter()) {
+            self.survey(id, body.id, 1.0);
+        }
+    }
+}
+
+/// Systems an empire has eyes on, which are the ones with its fleets or colonies
+pub fn observed(state: &GameState, empire: EmpireId) -> Vec<SystemId> {
+    let mut res: Vec<SystemId> = state.fleets.iter()
+        .filter(|f| f.owner == empire)
+        .filter_map(|f| f.system())
+        .chain(state.galaxy.colonies()
+            .filter(|(_, b)| b.colony.as_ref().is_some_and(|c| c.owner == empire))
+            .map(|(r, _)| r.system))
+        .collect();
+    res.sort();
+    res.dedup();
+    res
+}
+
+/// Updates what every empire knows: the systems it has eyes on are explored and their
+/// neighbours detected, and survey ships make progress on the bodies of their systems
+pub fn exploration(state: &mut GameState, days: u64, report: &mut TurnReport) {
+    let turn = state.turn;
+    for empire in 0..state.empires.len() {
+        let visible = observed(state, empire);
+        let knowledge = &mut state.empires[empire].knowledge;
+        for id in &visible {
+            let first = knowledge.see(*id, &state.galaxy.systems[*id], turn);
+            if first && empire == state.player {
+                report.add(Phase::Movement, EventKind::SystemExplored, format!(
+                    "{} was explored",
+                    state.galaxy.systems[*id].name
+                ));
+            }
+            for neighbour in state.galaxy.neighbours(*id) {
+                knowledge.detect(neighbour, &state.galaxy.systems[neighbour], turn);
+            }
+        }
+        knowledge.visible = visible;
+    }
+
+    let mut surveyed = Vec::new();
+    for fleet in &state.fleets {
+        let (Some(system), None) = (fleet.system(), &fleet.transfer) else { continue };
+        let strength = fleet.ships.iter()
+            .filter(|s| s.role == ShipRole::Survey)
+            .map(|s| s.design.stats().sensor_strength)
+            .fold(0.0, f32::max);
+        if strength <= 0.0 {
+            continue;
+        }
+        let knowledge = &mut state.empires[fleet.owner].knowledge;
+        let Some(known) = knowledge.system(system) else { continue };
+        // The body the fleet orbits first, then the others in order
+        let galaxy_system = &state.galaxy.systems[system];
+        let target = std::iter::once(fleet.orbit)
+            .chain(std::iter::once(galaxy_system.star.id))
+            .chain(galaxy_system.bodies.iter().map(|b| b.id))
+            .find(|b| galaxy_system.body(*b).is_some() && known.survey_progress(*b) < 1.0);
+        let Some(target) = target else { continue };
+        if !knowledge.survey(system, target, strength * days as f32 / SURVEY_DAYS) {
+            continue;
+        }
+        surveyed.push((fleet.owner, BodyRef { system, body: target }));
+        if fleet.owner == state.player {
+            report.add(Phase::Movement, EventKind::SurveyCompleted, format!(
+                "{} finished surveying {}",
+                fleet.name,
+                galaxy_system.body(target).unwrap().name
+            ));
+        }
+    }
+    // What the surveys found
+    for (empire, body) in surveyed {
+        trigger(state, empire, Trigger::Survey, body, report);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::fleet::{movement, plot_course};
+
+    /// Moves a fleet to a system, updating what its owner knows day by day
+    fn fly(state: &mut GameState, fleet: usize, to: SystemId, report: &mut TurnReport) {
+        let (owner, id) = (state.fleets[fleet].owner, state.fleets[fleet].id);
+        assert!(plot_course(state, owner, id, to, false).is_ok());
+        while state.fleets[fleet].is_moving() {
+            movement(state, 1, report);
+            exploration(state, 1, report);
+        }
+    }
+
+    #[test]
+    fn test_starting_knowledge() {
+        let state = GameState::new(7);
+        let capital = state.fleets[0].location;
+        let knowledge = &state.player_empire().knowledge;
+        assert_eq!(knowledge.level(capital), KnowledgeLevel::Surveyed);
+        assert!(knowledge.is_visible(capital));
+        let neighbours = state.galaxy.neighbours(capital);
+        for neighbour in &neighbours {
+            assert_eq!(knowledge.level(*neighbour), KnowledgeLevel::Detected);
+            assert!(knowledge.system(*neighbour).unwrap().known_system().bodies.is_empty());
+        }
+        let far = (0..state.galaxy.systems.len())
+            .find(|s| *s != capital && !neighbours.contains(s))
+            .unwrap();
+        assert_eq!(knowledge.level(far), KnowledgeLevel::Unknown);
+    }
+
+    #[test]
+    fn test_exploration() {
+        let mut state = GameState::new(8);
+        let capital = state.fleets[0].location;
+        let target = state.galaxy.neighbours(capital)[0];
+        let mut report = TurnReport::default();
+        fly(&mut state, 0, target, &mut report);
+
+        let knowledge = &state.player_empire().knowledge;
+        assert_eq!(knowledge.level(target), KnowledgeLevel::Explored);
+        assert!(knowledge.is_visible(target));
+        assert_eq!(
+            knowledge.system(target).unwrap().known_system().bodies.len(),
+            state.galaxy.systems[target].bodies.len()
+        );
+        assert!(report.entries.iter().any(|e| matches!(e.kind, EventKind::SystemExplored)));
+        for neighbour in state.galaxy.neighbours(target) {
+            assert!(knowledge.level(neighbour) >= KnowledgeLevel::Detected);
+        }
+
+        // Once the fleet leaves, the snapshot goes stale
+        state.turn = 5;
+        fly(&mut state, 0, capital, &mut report);
+        let knowledge = &state.player_empire().knowledge;
+        assert!(!knowledge.is_visible(target));
+        assert_eq!(knowledge.system(target).unwrap().last_seen, 0);
+        assert_eq!(knowledge.system(capital).unwrap().last_seen, 5);
+    }
+
+    #[test]
+    fn test_survey() {
+        let mut state = GameState::new(9);
+        let capital = state.fleets[1].location;
+        let target = state.galaxy.neighbours(capital)[0];
+        let star = state.galaxy.systems[target].star.id;
+        let mut report = TurnReport::default();
+        fly(&mut state, 1, target, &mut report);
+
+        let known = state.player_empire().knowledge.system(target).unwrap();
+        let mut progress = known.survey_progress(star);
+        assert!(progress > 0.0);
+        assert!(known.body_info(&state.galaxy.systems[target].star).iter().any(|l| l.contains("Not surveyed")));
+        while progress < 1.0 {
+            exploration(&mut state, 1, &mut report);
+            let next = state.player_empire().knowledge.system(target).unwrap().survey_progress(star);
+            assert!(next > progress);
+            progress = next;
+        }
+        let known = state.player_empire().knowledge.system(target).unwrap();
+        assert_eq!(known.body_level(star), KnowledgeLevel::Surveyed);
+        assert!(report.entries.iter().any(|e| matches!(e.kind, EventKind::SurveyCompleted)));
+
+        // Given time the whole system is surveyed
+        let bodies = state.galaxy.systems[target].bodies.len();
+        for _ in 0..=bodies {
+            exploration(&mut state, SURVEY_DAYS as u64 * 10, &mut report);
+        }
+        assert_eq!(state.player_empire().knowledge.level(target), KnowledgeLevel::Surveyed);
+    }
+}
@@ -1,2 +1,30 @@
 pub mod system;
-pub mod body;
\ No newline at end of file
+pub mod body;
+pub mod hazard;
+pub mod evolution;
+pub mod galaxy;
+pub mod empire;
+pub mod fleet;
+pub mod combat;
+pub mod knowledge;
+pub mod sensors;
+pub mod territory;
+pub mod ai;
+pub mod diplomacy;
+pub mod victory;
+pub mod narrative;
+pub mod species;
+pub mod state;
+pub mod order;
+pub mod turn;
+pub mod clock;
+pub mod calendar;
+pub mod resources;
+pub mod economy;
+pub mod colony;
+pub mod building;
+pub mod tech;
+pub mod population;
+pub mod ship;
+pub mod design;
+pub mod shipyard;
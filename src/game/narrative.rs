@@ -0,0 +1,464 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use lazy_static::lazy_static;
+use rand::Rng;
+use serde::Deserialize;
+use strum::Display;
+use crate::game::ai::event_choice;
+use crate::game::body::{BodyType, PlanetType};
+use crate::game::building::{building, slots, used_slots};
+use crate::game::diplomacy::Stance;
+use crate::game::empire::EmpireId;
+use crate::game::galaxy::BodyRef;
+use crate::game::population::capacity;
+use crate::game::resources::Resources;
+use crate::game::state::GameState;
+use crate::game::turn::{EventKind, Phase, ScheduledEvent, TurnReport, DAYS_PER_TURN};
+
+const EVENTS_DATA: &str = include_str!("../../assets/events.json5");
+
+lazy_static! {
+    /// Every narrative event defined in the data files
+    pub static ref EVENTS: Vec<EventDef> =
+        json5::from_str(EVENTS_DATA).expect("event definitions are valid");
+}
+
+/// What sets off a narrative event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Deserialize)]
+pub enum Trigger {
+    /// A survey ship finished surveying a body, which the event is about
+    Survey,
+    /// Colonists founded a colony, which the event is about
+    ColonyFounded,
+    /// Rolled at the end of every turn for a random colony of the empire
+    Random,
+    /// Only happens when a choice of another event leads to it, at the same body
+    Chain,
+}
+
+/// What has to hold for an event to happen
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum Condition {
+    /// The game has reached the turn
+    Turn { min: u32 },
+    /// The empire has at least that many colonies
+    Colonies { min: u32 },
+    /// The empire knows the technology
+    Knows { tech: String },
+    /// The body of the event is a planet of one of the types
+    Planet { types: Vec<PlanetType> },
+    /// The empire is at war with another
+    AtWar,
+}
+
+impl Condition {
+    fn holds(&self, state: &GameState, empire: EmpireId, at: BodyRef) -> bool {
+        match self {
+            Condition::Turn { min } => { state.turn >= *min }
+            Condition::Colonies { min } => {
+                state.galaxy.colonies().filter(|(_, b)| b.colony.as_ref().is_some_and(|c| c.owner == empire)).count()
+                    >= *min as usize
+            }
+            Condition::Knows { tech } => { state.empires[empire].research.knows(tech) }
+            Condition::Planet { types } => {
+                state.galaxy.body(at).is_some_and(|b| matches!(&b.kind, BodyType::Planet(p) if types.contains(p)))
+            }
+            Condition::AtWar => {
+                (0..state.empires.len()).any(|other| other != empire && state.diplomacy.stance(empire, other) == Stance::War)
+            }
+        }
+    }
+}
+
+/// What a choice does
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum Effect {
+    /// Adds to the stockpile of the empire, or takes from it if negative. The stockpile does not
+    /// go below zero
+    Resources { amount: Resources },
+    /// The colony at the body of the event gains or loses population units. It keeps at least
+    /// one and grows no further than the body allows
+    Population { change: i32 },
+    /// Research points go to the research queue of the empire
+    Research { points: f32 },
+    /// A building is completed in the colony at the body of the event for free, if it has a free
+    /// slot for it
+    Building { building: String },
+    /// Another event follows after the given number of turns
+    Event { id: String, turns: u32 },
+}
+
+impl Effect {
+    pub fn describe(&self) -> String {
+        match self {
+            Effect::Resources { amount } => {
+                amount.iter()
+                    .filter(|(_, a)| *a != 0.0)
+                    .map(|(r, a)| format!("{a:+.0} {}", r.to_string().to_lowercase()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }
+            Effect::Population { change } => { format!("{change:+} population") }
+            Effect::Research { points } => { format!("{points:+.0} research") }
+            Effect::Building { building: id } => {
+                format!("A free {}", building(id).map_or(id.as_str(), |b| b.name.as_str()).to_lowercase())
+            }
+            Effect::Event { turns, .. } => { format!("Something may come of it in {turns} turns") }
+        }
+    }
+}
+
+/// One of the answers to an event
+#[derive(Debug, Clone, Deserialize)]
+pub struct Choice {
+    pub label: String,
+    #[serde(default)]
+    pub effects: Vec<Effect>,
+}
+
+impl Choice {
+    pub fn describe(&self) -> String {
+        if self.effects.is_empty() {
+            return "Nothing happens".to_owned();
+        }
+        self.effects.iter().map(|e| e.describe()).collect::<Vec<_>>().join("; ")
+    }
+}
+
+fn certain() -> f32 {
+    1.0
+}
+
+/// A narrative event, as defined in the data files
+#[derive(Debug, Clone, Deserialize)]
+pub struct EventDef {
+    pub id: String,
+    pub title: String,
+    /// `{empire}`, `{system}` and `{body}` stand for the names of the empire and of the place of
+    /// the event
+    pub text: String,
+    pub trigger: Trigger,
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+    /// Chance the event happens when it is triggered
+    #[serde(default = "certain")]
+    pub chance: f32,
+    /// Whether the event happens to an empire only once
+    #[serde(default)]
+    pub unique: bool,
+    pub choices: Vec<Choice>,
+}
+
+pub fn event(id: &str) -> Option<&'static EventDef> {
+    EVENTS.iter().find(|e| e.id == id)
+}
+
+/// An event that happened to an empire and waits for its choice
+#[derive(Debug, Clone, PartialEq)]
+pub struct Incident {
+    pub event: String,
+    pub empire: EmpireId,
+    /// The body the event is about
+    pub at: BodyRef,
+    pub turn: u32,
+}
+
+impl Incident {
+    pub fn def(&self) -> &'static EventDef {
+        event(&self.event).expect("incidents are of defined events")
+    }
+
+    /// Text of the event with the names filled in
+    pub fn text(&self, state: &GameState) -> String {
+        let system = &state.galaxy.systems[self.at.system];
+        let body = system.body(self.at.body).map_or(system.name.as_str(), |b| b.name.as_str());
+        self.def().text
+            .replace("{empire}", &state.empires[self.empire].name)
+            .replace("{system}", &system.name)
+            .replace("{body}", body)
+    }
+}
+
+/// Narrative events that wait for an answer and the ones that happened
+#[derive(Debug, Clone, Default)]
+pub struct Narrative {
+    /// Incidents waiting for the choice of the player, oldest first
+    pub pending: Vec<Incident>,
+    /// Events that happened to every empire, by id
+    happened: Vec<(EmpireId, String)>,
+}
+
+impl Narrative {
+    pub fn has_happened(&self, empire: EmpireId, event: &str) -> bool {
+        self.happened.iter().any(|(e, id)| *e == empire && id == event)
+    }
+
+    /// The oldest incident of the empire waiting for its choice
+    pub fn pending_for(&self, empire: EmpireId) -> Option<&Incident> {
+        self.pending.iter().find(|i| i.empire == empire)
+    }
+}
+
+/// Rolls for an event of the trigger about the body. At most one event happens
+pub fn trigger(state: &mut GameState, empire: EmpireId, trigger: Trigger, at: BodyRef, report: &mut TurnReport) {
+    let candidates: Vec<&'static EventDef> = EVENTS.iter()
+        .filter(|e| e.trigger == trigger)
+        .filter(|e| !e.unique || !state.narrative.has_happened(empire, &e.id))
+        .filter(|e| e.conditions.iter().all(|c| c.holds(state, empire, at)))
+        .collect();
+    for def in candidates {
+        if state.rng().gen_bool(def.chance as f64) {
+            happen(state, empire, def, at, report);
+            return;
+        }
+    }
+}
+
+/// Puts an event to an empire. Computer empires choose at once, the player is asked
+fn happen(state: &mut GameState, empire: EmpireId, def: &'static EventDef, at: BodyRef, report: &mut TurnReport) {
+    state.narrative.happened.push((empire, def.id.clone()));
+    let incident = Incident { event: def.id.clone(), empire, at, turn: state.turn };
+    match &state.empires[empire].ai {
+        Some(ai) => {
+            let choice = event_choice(ai, def);
+            resolve(state, incident, choice);
+        }
+        None => {
+            report.add(Phase::Events, EventKind::Narrative, format!("{}: {}", def.title, incident.text(state)));
+            state.narrative.pending.push(incident);
+        }
+    }
+}
+
+/// Carries out a chained event that came due
+pub fn follow_up(state: &mut GameState, empire: EmpireId, id: &str, at: BodyRef, report: &mut TurnReport) {
+    if let Some(def) = event(id) {
+        if state.empires[empire].eliminated.is_none() {
+            happen(state, empire, def, at, report);
+        }
+    }
+}
+
+/// Random events of every empire at the end of a turn
+pub fn random_events(state: &mut GameState, report: &mut TurnReport) {
+    for empire in 0..state.empires.len() {
+        let colonies: Vec<BodyRef> = state.galaxy.colonies()
+            .filter(|(_, b)| b.colony.as_ref().is_some_and(|c| c.owner == empire))
+            .map(|(r, _)| r)
+            .collect();
+        if colonies.is_empty() {
+            continue;
+        }
+        let at = colonies[state.rng().gen_range(0..colonies.len())];
+        trigger(state, empire, Trigger::Random, at, report);
+    }
+}
+
+/// Answers the oldest pending incident of the event for the empire. Returns what came of the
+/// choice, or why it can not be made
+pub fn choose(state: &mut GameState, empire: EmpireId, id: &str, choice: usize) -> Result<String, String> {
+    let Some(index) = state.narrative.pending.iter().position(|i| i.empire == empire && i.event == id) else {
+        return Err(format!("No event {id} waits for an answer"));
+    };
+    if choice >= state.narrative.pending[index].def().choices.len() {
+        return Err(format!("Event {id} has no choice {}", choice + 1));
+    }
+    let incident = state.narrative.pending.remove(index);
+    Ok(resolve(state, incident, choice))
+}
+
+/// Applies the effects of a choice. Returns a summary of what happened
+fn resolve(state: &mut GameState, incident: Incident, choice: usize) -> String {
+    let def = incident.def();
+    let choice = &def.choices[choice];
+    let mut results = Vec::new();
+    for effect in &choice.effects {
+        results.push(apply(state, &incident, effect));
+    }
+    let results: Vec<String> = results.into_iter().filter(|r| !r.is_empty()).collect();
+    if results.is_empty() {
+        format!("{}: {}", def.title, choice.label)
+    } else {
+        format!("{}: {} ({})", def.title, choice.label, results.join(", "))
+    }
+}
+
+/// Applies an effect of a choice. Returns what came of it, if anything
+fn apply(state: &mut GameState, incident: &Incident, effect: &Effect) -> String {
+    let empire = incident.empire;
+    match effect {
+        Effect::Resources { amount } => {
+            let stockpile = &mut state.empires[empire].stockpile;
+            for (resource, amount) in amount.iter() {
+                stockpile.set(resource, (stockpile.get(resource) + amount).max(0.0));
+            }
+            effect.describe()
+        }
+        Effect::Population { change } => {
+            let Some(body) = state.galaxy.body_mut(incident.at) else { return String::new() };
+            let room = capacity(body, &state.empires[empire].species);
+            let Some(colony) = body.colony.as_mut().filter(|c| c.owner == empire) else { return String::new() };
+            let before = colony.population;
+            for _ in 0..change.unsigned_abs() {
+                if *change > 0 && colony.population < room {
+                    colony.add_pop();
+                } else if *change < 0 && colony.population > 1 {
+                    colony.remove_pop();
+                }
+            }
+            let change = colony.population as i32 - before as i32;
+            if change == 0 { String::new() } else { format!("{change:+} population on {}", body.name) }
+        }
+        Effect::Research { points } => {
+            let research = &mut state.empires[empire].research;
+            let done: Vec<String> = research.advance(*points).iter().map(|t| t.name.clone()).collect();
+            if done.is_empty() {
+                effect.describe()
+            } else {
+                format!("{}, completing {}", effect.describe(), done.join(" and "))
+            }
+        }
+        Effect::Building { building: id } => {
+            let (Some(def), Some(body)) = (building(id), state.galaxy.body_mut(incident.at)) else { return String::new() };
+            let allowed = match &body.kind {
+                BodyType::Planet(planet) => { def.allowed_on(planet) }
+                _ => { false }
+            };
+            if !allowed || used_slots(body, def.kind) >= slots(body, def.kind) {
+                return format!("no room for a {} on {}", def.name.to_lowercase(), body.name);
+            }
+            let name = body.name.clone();
+            let Some(colony) = body.colony.as_mut().filter(|c| c.owner == empire) else { return String::new() };
+            colony.buildings.push(def.id.clone());
+            colony.fill_jobs();
+            format!("a {} stands on {name}", def.name.to_lowercase())
+        }
+        Effect::Event { id, turns } => {
+            let follow = ScheduledEvent::FollowUp { empire, event: id.clone(), at: incident.at };
+            state.schedule.after(state.date, (*turns as u64 * DAYS_PER_TURN).max(1), follow);
+            String::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::resources::Resource;
+    use crate::game::tech::tech;
+    use crate::game::turn::end_turn;
+
+    use super::*;
+
+    fn homeworld(state: &GameState) -> BodyRef {
+        state.galaxy.colonies().find(|(_, b)| b.colony.as_ref().unwrap().owner == state.player).unwrap().0
+    }
+
+    #[test]
+    fn test_definitions() {
+        assert!(!EVENTS.is_empty());
+        for def in EVENTS.iter() {
+            assert!(!def.choices.is_empty(), "{} offers no choice", def.id);
+            assert!((0.0..=1.0).contains(&def.chance), "{} has chance {}", def.id, def.chance);
+            assert!(EVENTS.iter().filter(|e| e.id == def.id).count() == 1, "{} is defined twice", def.id);
+            for condition in &def.conditions {
+                if let Condition::Knows { tech: id } = condition {
+                    assert!(tech(id).is_some(), "{} needs unknown {id}", def.id);
+                }
+            }
+            for effect in def.choices.iter().flat_map(|c| c.effects.iter()) {
+                match effect {
+                    Effect::Building { building: id } => {
+                        assert!(building(id).is_some(), "{} builds unknown {id}", def.id);
+                    }
+                    Effect::Event { id, .. } => {
+                        assert!(event(id).is_some_and(|e| e.trigger == Trigger::Chain), "{} leads to {id}", def.id);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_player_choice() {
+        let mut state = GameState::new(1);
+        let at = homeworld(&state);
+        let mut report = TurnReport::default();
+        happen(&mut state, 0, event("derelict_probe").unwrap(), at, &mut report);
+        assert_eq!(report.entries[0].kind, EventKind::Narrative);
+        assert!(state.narrative.has_happened(state.player, "derelict_probe"));
+        let incident = state.narrative.pending_for(state.player).unwrap().clone();
+        assert!(incident.text(&state).contains(&state.galaxy.body(at).unwrap().name));
+
+        // Unique events do not happen again
+        let before = state.narrative.pending.len();
+        trigger(&mut state, 0, Trigger::Survey, at, &mut report);
+        assert!(state.narrative.pending.iter().skip(before).all(|i| i.event != "derelict_probe"));
+
+        assert!(choose(&mut state, 0, "derelict_probe", 5).is_err());
+        let alloys = state.empires[0].stockpile.get(Resource::Alloys);
+        assert!(choose(&mut state, 0, "derelict_probe", 1).is_ok());
+        assert_eq!(state.empires[0].stockpile.get(Resource::Alloys), alloys + 40.0);
+        assert!(state.narrative.pending_for(state.player).is_none());
+        assert!(choose(&mut state, 0, "derelict_probe", 1).is_err());
+    }
+
+    #[test]
+    fn test_chain() {
+        let mut state = GameState::new(1);
+        let at = homeworld(&state);
+        happen(&mut state, 0, event("derelict_probe").unwrap(), at, &mut TurnReport::default());
+        choose(&mut state, 0, "derelict_probe", 2).unwrap();
+        for _ in 0..3 {
+            end_turn(&mut state);
+            assert!(state.narrative.pending_for(state.player).is_none_or(|i| i.event != "probe_origin"));
+        }
+        end_turn(&mut state);
+        let incident = state.narrative.pending.iter().find(|i| i.event == "probe_origin").unwrap();
+        assert_eq!(incident.at, at);
+    }
+
+    #[test]
+    fn test_effects() {
+        let mut state = GameState::new(1);
+        let at = homeworld(&state);
+        let incident = Incident { event: "baby_boom".to_owned(), empire: state.player, at, turn: 0 };
+        let population = state.galaxy.body(at).unwrap().colony.as_ref().unwrap().population;
+        apply(&mut state, &incident, &Effect::Population { change: -100 });
+        assert_eq!(state.galaxy.body(at).unwrap().colony.as_ref().unwrap().population, 1);
+        apply(&mut state, &incident, &Effect::Population { change: population as i32 - 1 });
+        assert_eq!(state.galaxy.body(at).unwrap().colony.as_ref().unwrap().population, population);
+
+        state.empires[0].stockpile.set(Resource::Energy, 10.0);
+        apply(&mut state, &incident, &Effect::Resources { amount: Resources::of(Resource::Energy, -50.0) });
+        assert_eq!(state.empires[0].stockpile.get(Resource::Energy), 0.0);
+    }
+
+    #[test]
+    fn test_computer_empires_choose() {
+        let mut state = GameState::with_setup(1, crate::game::clock::TimeMode::Turns, &crate::game::state::Setup {
+            opponents: 1,
+            ..Default::default()
+        });
+        let at = state.galaxy.colonies().find(|(_, b)| b.colony.as_ref().unwrap().owner == 1).unwrap().0;
+        let mut report = TurnReport::default();
+        happen(&mut state, 1, event("founders_day").unwrap(), at, &mut report);
+        assert!(report.entries.is_empty());
+        assert!(state.narrative.pending.is_empty());
+        assert!(state.narrative.has_happened(1, "founders_day"));
+    }
+}
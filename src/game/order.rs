@@ -0,0 +1,83 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+use crate::game::body::BodyId;
+use crate::game::design::ShipDesign;
+use crate::game::diplomacy::Proposal;
+use crate::game::empire::EmpireId;
+use crate::game::fleet::FleetId;
+use crate::game::galaxy::{BodyRef, SystemId};
+use crate::game::population::Workforce;
+
+/// An instruction given to the simulation by the player. Orders are queued and carried out at
+/// the start of the next turn
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Order {
+    /// Send a fleet to a system along the fastest route, dropping its other waypoints
+    MoveFleet { fleet: FleetId, destination: SystemId },
+    /// Add a system to the end of a fleet's course
+    QueueMove { fleet: FleetId, destination: SystemId },
+    /// Drop every waypoint of a fleet
+    CancelMove { fleet: FleetId },
+    /// Fly a fleet to another body of the system it is in
+    MoveInSystem { fleet: FleetId, body: BodyId },
+    /// Use a colony ship of a fleet to settle a body in the system the fleet is in
+    Colonize { fleet: FleetId, target: BodyRef },
+    /// Claim the system a fleet is in with an outpost
+    BuildOutpost { fleet: FleetId },
+    /// Move population units from one colony of the empire to another
+    Migrate { from: BodyRef, to: BodyRef, population: u32 },
+    /// Set how many workers of a colony do every job
+    AssignJobs { colony: BodyRef, workforce: Workforce },
+    /// Add a building or district to the construction queue of a colony
+    Build { colony: BodyRef, building: String },
+    /// Remove an entry from the construction queue of a colony
+    CancelConstruction { colony: BodyRef, index: usize },
+    /// Lay down a ship of one of the empire's designs in the shipyard of a colony
+    BuildShip { colony: BodyRef, design: String },
+    /// Remove an entry from the shipyard queue of a colony
+    CancelShip { colony: BodyRef, index: usize },
+    /// Refit the outdated ships of a fleet in the shipyard of a colony in the same system
+    RefitFleet { fleet: FleetId, colony: BodyRef },
+    /// Add a technology and its missing prerequisites to the research queue
+    QueueResearch { tech: String },
+    /// Remove a technology and everything that needs it from the research queue
+    DequeueResearch { tech: String },
+    /// Save a ship design, replacing the design of the same name with a new revision
+    SaveDesign { design: ShipDesign },
+    DeleteDesign { name: String },
+    /// Put a proposal to another empire, or declare war on it or end a treaty with it
+    Propose { to: EmpireId, proposal: Proposal },
+    /// Accept or reject the oldest open proposal of another empire
+    AnswerProposal { from: EmpireId, accept: bool },
+    /// Make a choice in the oldest narrative event of the kind waiting for an answer
+    ResolveEvent { event: String, choice: usize },
+}
+
+impl Order {
+    /// Whether the order only concerns the empire's own paperwork, plotting a course or answering
+    /// a narrative event, so that it is carried out as soon as it is issued instead of waiting for
+    /// the end of the turn
+    pub fn is_immediate(&self) -> bool {
+        matches!(
+            self,
+            Order::SaveDesign { .. } | Order::DeleteDesign { .. }
+                | Order::MoveFleet { .. } | Order::QueueMove { .. } | Order::CancelMove { .. }
+                | Order::MoveInSystem { .. } | Order::ResolveEvent { .. }
+        )
+    }
+}
@@ -0,0 +1,465 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+use crate::game::body::{Body, BodyType, PlanetType};
+use crate::game::empire::EmpireId;
+use crate::game::galaxy::BodyRef;
+use crate::game::resources::{Resource, Resources};
+use crate::game::shipyard::return_refits;
+use crate::game::state::GameState;
+use crate::game::species::Species;
+use crate::game::tech::{growth_bonus, job_bonus, Modifier};
+use crate::game::turn::{EventKind, Phase, TurnReport};
+
+/// Share of the free room that a colony's population grows by every turn
+const GROWTH_RATE: f32 = 0.1;
+
+/// Growth progress lost every turn by the colonies of an empire without food
+const STARVATION_PENALTY: f32 = 0.5;
+
+/// Population a planet the size of Earth with perfect habitability can hold
+const BASE_CAPACITY: f32 = 20.0;
+
+/// Food eaten by a population unit every turn
+pub const FOOD_PER_POP: f32 = 1.0;
+
+/// Work a population unit of a colony can be assigned to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString, Serialize, Deserialize)]
+pub enum Job {
+    Farmer,
+    Miner,
+    Technician,
+    Researcher,
+}
+
+impl Job {
+    /// Jobs in the order new population is assigned to them
+    pub const ALL: [Job; 4] = [
+        Job::Farmer,
+        Job::Miner,
+        Job::Technician,
+        Job::Researcher,
+    ];
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+
+    /// Resources a single worker produces every turn
+    pub fn output(&self) -> Resources {
+        match self {
+            Job::Farmer => { Resources::of(Resource::Food, 3.0) }
+            Job::Miner => { Resources::of(Resource::Minerals, 3.0) }
+            Job::Technician => { Resources::of(Resource::Energy, 3.0) }
+            Job::Researcher => { Resources::of(Resource::Research, 3.0) }
+        }
+    }
+
+    /// How well the job can be done on a planet of the given type
+    pub fn modifier(&self, planet: &PlanetType) -> f32 {
+        #[rustfmt::skip]
+        let res = match (self, planet) {
+            (Job::Farmer,     PlanetType::Earthlike)    => { 1.5  }
+            (Job::Farmer,     PlanetType::Desert)       => { 0.75 }
+            (Job::Farmer,     PlanetType::Rock)         => { 0.75 }
+            (Job::Farmer,     PlanetType::Ice)          => { 0.5  }
+            (Job::Farmer,     PlanetType::AsteroidRing) => { 0.25 }
+            (Job::Miner,      PlanetType::AsteroidRing) => { 1.5  }
+            (Job::Miner,      PlanetType::Rock)         => { 1.25 }
+            (Job::Technician, PlanetType::Desert)       => { 1.25 }
+            (Job::Researcher, PlanetType::Ice)          => { 1.5  }
+            _ => { 1.0 }
+        };
+        res
+    }
+}
+
+/// Number of workers in every job of a colony
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Workforce {
+    workers: [u32; 4],
+}
+
+impl Workforce {
+    pub fn get(&self, job: Job) -> u32 {
+        self.workers[job.index()]
+    }
+
+    pub fn set(&mut self, job: Job, workers: u32) {
+        self.workers[job.index()] = workers;
+    }
+
+    pub fn total(&self) -> u32 {
+        self.workers.iter().sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Job, u32)> + '_ {
+        Job::ALL.iter().map(|j| (*j, self.get(*j)))
+    }
+
+    /// Puts a worker into the job with the fewest workers that still has free slots. Returns
+    /// whether a job was found
+    pub fn hire(&mut self, slots: &Workforce) -> bool {
+        let Some(job) = Job::ALL.iter()
+            .filter(|j| self.get(**j) < slots.get(**j))
+            .min_by_key(|j| self.get(**j)) else {
+            return false;
+        };
+        self.workers[job.index()] += 1;
+        true
+    }
+
+    /// Whether every job has no more workers than slots
+    pub fn fits(&self, slots: &Workforce) -> bool {
+        self.iter().all(|(job, workers)| workers <= slots.get(job))
+    }
+
+    /// Lays off a worker of the job with the most workers
+    pub fn fire(&mut self) {
+        let job = Job::ALL.iter().max_by_key(|j| self.get(**j)).unwrap();
+        let workers = &mut self.workers[job.index()];
+        *workers = workers.saturating_sub(1);
+    }
+
+    /// Resources produced by the workers on a planet of the given type
+    pub fn production(&self, planet: &PlanetType, modifiers: &[Modifier]) -> Resources {
+        self.iter()
+            .map(|(job, workers)| {
+                job.output() * (job.modifier(planet) * (1.0 + job_bonus(modifiers, job)) * workers as f32)
+            })
+            .fold(Resources::default(), |a, b| a + b)
+    }
+}
+
+/// Population of the species a body can hold. Every habitable body holds at least one
+/// population unit
+pub fn capacity(body: &Body, species: &Species) -> u32 {
+    let habitability = species.habitability(body);
+    if habitability <= 0.0 {
+        return 0;
+    }
+    let size = match body.kind {
+        BodyType::Planet(PlanetType::AsteroidRing) => { 0.25 }
+        _ => { body.relative_size() }
+    };
+    ((BASE_CAPACITY * size * habitability).floor() as u32).max(1)
+}
+
+/// Change of population over a turn, following the logistic curve. It is negative when the
+/// population is over the capacity
+pub fn logistic_growth(population: u32, capacity: u32) -> f32 {
+    if capacity == 0 {
+        return -(population as f32);
+    }
+    let population = population as f32;
+    GROWTH_RATE * population * (1.0 - population / capacity as f32)
+}
+
+/// Whether the empire has run out of food and eats more than it produces
+pub fn is_starving(state: &GameState, empire: EmpireId) -> bool {
+    let empire = &state.empires[empire];
+    empire.stockpile.get(Resource::Food) <= 0.0 && empire.ledger.net().get(Resource::Food) < 0.0
+}
+
+/// Grows or shrinks the population of every colony, moves population out of overcrowded colonies
+/// and abandons colonies with no population left
+pub fn growth(state: &mut GameState, report: &mut TurnReport) {
+    let starving: Vec<bool> = (0..state.empires.len()).map(|e| is_starving(state, e)).collect();
+    for (empire, starving) in starving.iter().enumerate() {
+        if *starving && empire == state.player {
+            report.add(Phase::Growth, EventKind::Starvation, format!(
+                "The colonies of {} are starving",
+                state.empires[empire].name
+            ));
+        }
+    }
+
+    let colonies: Vec<BodyRef> = state.galaxy.colonies().map(|(r, _)| r).collect();
+    for target in colonies.iter().copied() {
+        let body = state.galaxy.body_mut(target).unwrap();
+        let owner = &state.empires[body.colony.as_ref().unwrap().owner];
+        let capacity = capacity(body, &owner.species);
+        let growth = logistic_growth(body.colony.as_ref().unwrap().population, capacity);
+        // Faster breeders also shrink faster when over capacity, starvation hits every species alike
+        let growth = growth * (1.0 + growth_bonus(&owner.modifiers())).max(0.0);
+        let name = body.name.clone();
+        let colony = body.colony.as_mut().unwrap();
+        // A starving colony does not grow, only shrinks
+        if starving[colony.owner] {
+            colony.growth = colony.growth.min(0.0) - STARVATION_PENALTY;
+        } else {
+            colony.growth += growth;
+        }
+
+        while colony.growth >= 1.0 && colony.population < capacity {
+            colony.growth -= 1.0;
+            colony.add_pop();
+        }
+        while colony.growth <= -1.0 && colony.population > 0 {
+            colony.growth += 1.0;
+            colony.remove_pop();
+        }
+        // Progress is not stored beyond the next population unit
+        colony.growth = colony.growth.clamp(-1.0, 1.0);
+
+        if colony.population == 0 {
+            let owner = colony.owner;
+            let queue = body.colony.take().unwrap().shipyard_queue;
+            if owner == state.player {
+                report.add(Phase::Growth, EventKind::ColonyLost, format!(
+                    "The colony of {} on {name} has died out",
+                    state.empires[owner].name
+                ));
+            }
+            return_refits(state, owner, target, queue);
+        }
+    }
+
+    for from in colonies {
+        let owner = state.galaxy.body(from).and_then(|b| b.colony.as_ref()).map(|c| c.owner);
+        if let Some(text) = overcrowding_migration(state, from).filter(|_| owner == Some(state.player)) {
+            report.add(Phase::Growth, EventKind::Migration, text);
+        }
+    }
+}
+
+/// Moves a population unit from an overcrowded colony to the emptiest other colony of its owner
+fn overcrowding_migration(state: &mut GameState, from: BodyRef) -> Option<String> {
+    let body = state.galaxy.body(from)?;
+    let colony = body.colony.as_ref()?;
+    let owner = colony.owner;
+    let species = &state.empires[owner].species;
+    if colony.population <= capacity(body, species) {
+        return None;
+    }
+    let to = state.galaxy.colonies()
+        .filter(|(r, b)| *r != from && b.colony.as_ref().unwrap().owner == owner)
+        .map(|(r, b)| (r, capacity(b, species) as i64 - b.colony.as_ref().unwrap().population as i64))
+        .filter(|(_, room)| *room > 0)
+        .max_by_key(|(_, room)| *room)?
+        .0;
+    migrate(state, owner, from, to, 1).ok()
+}
+
+/// Moves population between two colonies of an empire. Returns a message for the report, or why
+/// the population could not move
+pub fn migrate(
+    state: &mut GameState,
+    empire: EmpireId,
+    from: BodyRef,
+    to: BodyRef,
+    population: u32,
+) -> Result<String, String> {
+    let (Some(source), Some(destination)) = (state.galaxy.body(from), state.galaxy.body(to)) else {
+        return Err("The colony no longer exists".to_owned());
+    };
+    let (Some(source_colony), Some(destination_colony)) = (&source.colony, &destination.colony) else {
+        return Err("Population can only migrate between colonies".to_owned());
+    };
+    if from == to || population == 0 {
+        return Err("Nobody has to migrate".to_owned());
+    }
+    if source_colony.owner != empire || destination_colony.owner != empire {
+        return Err("Population can only migrate between colonies of the same empire".to_owned());
+    }
+    // A colony can not be left empty
+    if source_colony.population <= population {
+        return Err(format!("{} does not have enough population to send", source.name));
+    }
+    if destination_colony.population + population > capacity(destination, &state.empires[empire].species) {
+        return Err(format!("{} does not have room for {population} more population", destination.name));
+    }
+    let text = format!("{population} population migrated from {} to {}", source.name, destination.name);
+
+    let source_colony = state.galaxy.body_mut(from).unwrap().colony.as_mut().unwrap();
+    for _ in 0..population {
+        source_colony.remove_pop();
+    }
+    let destination_colony = state.galaxy.body_mut(to).unwrap().colony.as_mut().unwrap();
+    for _ in 0..population {
+        destination_colony.add_pop();
+    }
+    Ok(text)
+}
+
+/// Reassigns the workers of a colony. Workers can not outnumber the population
+pub fn assign_jobs(
+    state: &mut GameState,
+    empire: EmpireId,
+    target: BodyRef,
+    workforce: Workforce,
+) -> Result<String, String> {
+    let Some(body) = state.galaxy.body_mut(target) else {
+        return Err("The colony no longer exists".to_owned());
+    };
+    let name = body.name.clone();
+    let Some(colony) = body.colony.as_mut().filter(|c| c.owner == empire) else {
+        return Err(format!("{name} is not a colony of the empire"));
+    };
+    if workforce.total() > colony.population {
+        return Err(format!("{name} does not have enough population for these jobs"));
+    }
+    if !workforce.fits(&colony.job_slots()) {
+        return Err(format!("{name} does not have enough job slots"));
+    }
+    colony.workforce = workforce;
+    Ok(format!("Jobs on {name} were reassigned"))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use crate::game::body::{PlanetZone, EARTH_RADIUS};
+    use crate::game::colony::Colony;
+    use crate::game::turn::end_turn;
+
+    use super::*;
+
+    fn planet(planet_type: PlanetType, radius: f32) -> Body {
+        let mut body = Body::generate_planet(&PlanetZone::HabitableZone, &mut StdRng::seed_from_u64(0));
+        body.kind = BodyType::Planet(planet_type);
+        body.radius = radius;
+        body
+    }
+
+    /// The player's homeworld and another colony of the player
+    fn two_colonies(seed: u64) -> (GameState, BodyRef, BodyRef) {
+        let mut state = GameState::new(seed);
+        let home = state.galaxy.colonies().next().unwrap().0;
+        let other = state.galaxy.systems.iter().enumerate()
+            .find_map(|(system, s)| {
+                s.bodies.iter()
+                    .find(|b| capacity(b, &state.empires[0].species) > 1 && b.colony.is_none())
+                    .map(|b| BodyRef { system, body: b.id })
+            })
+            .unwrap();
+        state.galaxy.body_mut(other).unwrap().colony = Some(Colony::new(state.player, state.date));
+        (state, home, other)
+    }
+
+    #[test]
+    fn test_logistic_growth() {
+        assert!(logistic_growth(1, 10) > 0.0);
+        assert!(logistic_growth(5, 10) > logistic_growth(1, 10));
+        assert!(logistic_growth(5, 10) > logistic_growth(9, 10));
+        assert_eq!(logistic_growth(10, 10), 0.0);
+        assert!(logistic_growth(12, 10) < 0.0);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let humans = Species::default();
+        let earth = planet(PlanetType::Earthlike, EARTH_RADIUS);
+        assert_eq!(capacity(&earth, &humans), BASE_CAPACITY as u32);
+        assert!(capacity(&planet(PlanetType::Earthlike, EARTH_RADIUS * 1.5), &humans) > capacity(&earth, &humans));
+        assert!(capacity(&planet(PlanetType::Desert, EARTH_RADIUS), &humans) < capacity(&earth, &humans));
+        assert_eq!(capacity(&planet(PlanetType::GasGiant, EARTH_RADIUS * 10.0), &humans), 0);
+        assert_eq!(capacity(&planet(PlanetType::AsteroidRing, 0.0), &humans), 1);
+
+        // A species of frozen worlds finds room for more on them
+        let frozen = Species { climate: PlanetType::Ice, ..Species::default() };
+        let ice = planet(PlanetType::Ice, EARTH_RADIUS);
+        assert!(capacity(&ice, &frozen) > capacity(&ice, &humans));
+        assert!(capacity(&earth, &frozen) < capacity(&earth, &humans));
+    }
+
+    #[test]
+    fn test_workforce() {
+        let mut slots = Workforce::default();
+        for job in Job::ALL {
+            slots.set(job, 2);
+        }
+        slots.set(Job::Researcher, 1);
+        let mut workforce = Workforce::default();
+        for _ in 0..7 {
+            assert!(workforce.hire(&slots));
+        }
+        assert!(!workforce.hire(&slots));
+        assert_eq!(workforce.get(Job::Farmer), 2);
+        assert_eq!(workforce.get(Job::Researcher), 1);
+        workforce.fire();
+        assert_eq!(workforce.total(), 6);
+        assert_eq!(workforce.get(Job::Technician), 1);
+
+        let production = workforce.production(&PlanetType::Earthlike, &[]);
+        assert_eq!(production.get(Resource::Food), 2.0 * 3.0 * 1.5);
+        assert_eq!(production.get(Resource::Minerals), 2.0 * 3.0);
+    }
+
+    #[test]
+    fn test_growth_up_to_capacity() {
+        let (mut state, _, other) = two_colonies(11);
+        let target = capacity(state.galaxy.body(other).unwrap(), &state.empires[0].species);
+        let mut last = 1;
+        for _ in 0..200 {
+            state.empires[0].stockpile.set(Resource::Food, 1000.0);
+            end_turn(&mut state);
+            let population = state.galaxy.body(other).unwrap().colony.as_ref().unwrap().population;
+            assert!(population >= last && population <= target);
+            last = population;
+        }
+        assert_eq!(last, target);
+    }
+
+    #[test]
+    fn test_starvation() {
+        let (mut state, home, _) = two_colonies(12);
+        let population = state.galaxy.body(home).unwrap().colony.as_ref().unwrap().population;
+        state.galaxy.body_mut(home).unwrap().colony.as_mut().unwrap().workforce = Workforce::default();
+        state.empires[0].stockpile.set(Resource::Food, 0.0);
+        let report = end_turn(&mut state);
+        end_turn(&mut state);
+
+        assert!(report.entries.iter().any(|e| e.kind == EventKind::Starvation));
+        assert!(state.galaxy.body(home).unwrap().colony.as_ref().unwrap().population < population);
+    }
+
+    #[test]
+    fn test_migration() {
+        let (mut state, home, other) = two_colonies(13);
+        let player = state.player;
+        let home_colony = state.galaxy.body_mut(home).unwrap().colony.as_mut().unwrap();
+        while home_colony.population < 5 {
+            home_colony.add_pop();
+        }
+        let before = home_colony.population;
+
+        assert!(migrate(&mut state, player, home, other, 1).is_ok());
+        assert_eq!(state.galaxy.body(home).unwrap().colony.as_ref().unwrap().population, before - 1);
+        assert_eq!(state.galaxy.body(other).unwrap().colony.as_ref().unwrap().population, 2);
+
+        assert!(migrate(&mut state, player, other, home, 2).is_err());
+        assert!(migrate(&mut state, player + 1, home, other, 1).is_err());
+    }
+
+    #[test]
+    fn test_assign_jobs() {
+        let (mut state, home, _) = two_colonies(14);
+        let player = state.player;
+        let mut workforce = Workforce::default();
+        workforce.set(Job::Researcher, 3);
+        assert!(assign_jobs(&mut state, player, home, workforce).is_ok());
+        let colony = state.galaxy.body(home).unwrap().colony.as_ref().unwrap();
+        assert_eq!(colony.workforce, workforce);
+        assert_eq!(colony.unemployed(), colony.population - 3);
+
+        workforce.set(Job::Miner, 1000);
+        assert!(assign_jobs(&mut state, player, home, workforce).is_err());
+    }
+}
@@ -0,0 +1,259 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::ops::{Add, AddAssign, Mul, Sub, SubAssign};
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString, Serialize, Deserialize)]
+pub enum Resource {
+    Energy,
+    Minerals,
+    Alloys,
+    Food,
+    Research,
+    Influence,
+}
+
+impl Resource {
+    pub const ALL: [Resource; 6] = [
+        Resource::Energy,
+        Resource::Minerals,
+        Resource::Alloys,
+        Resource::Food,
+        Resource::Research,
+        Resource::Influence,
+    ];
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// An amount of every kind of resource. In data files it is written as a map from resources to
+/// amounts, leaving out the zero ones
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(try_from = "HashMap<String, f32>")]
+pub struct Resources {
+    amounts: [f32; 6],
+}
+
+impl Resources {
+    pub fn of(resource: Resource, amount: f32) -> Self {
+        let mut res = Self::default();
+        res.set(resource, amount);
+        res
+    }
+
+    /// Builds an amount from `(resource, amount)` pairs, as they are written in data files
+    pub fn from_pairs(pairs: &[(Resource, f32)]) -> Self {
+        let mut res = Self::default();
+        for (resource, amount) in pairs {
+            res.add_amount(*resource, *amount);
+        }
+        res
+    }
+
+    pub fn get(&self, resource: Resource) -> f32 {
+        self.amounts[resource.index()]
+    }
+
+    pub fn set(&mut self, resource: Resource, amount: f32) {
+        self.amounts[resource.index()] = amount;
+    }
+
+    pub fn add_amount(&mut self, resource: Resource, amount: f32) {
+        self.amounts[resource.index()] += amount;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Resource, f32)> + '_ {
+        Resource::ALL.iter().map(|r| (*r, self.get(*r)))
+    }
+
+    /// Whether there is at least `cost` of every resource
+    pub fn covers(&self, cost: &Resources) -> bool {
+        self.iter().all(|(r, amount)| amount >= cost.get(r))
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.amounts.iter().all(|a| *a == 0.0)
+    }
+}
+
+impl TryFrom<HashMap<String, f32>> for Resources {
+    type Error = strum::ParseError;
+
+    fn try_from(value: HashMap<String, f32>) -> Result<Self, Self::Error> {
+        let pairs = value.into_iter()
+            .map(|(r, amount)| Ok((r.parse()?, amount)))
+            .collect::<Result<Vec<(Resource, f32)>, Self::Error>>()?;
+        Ok(Self::from_pairs(&pairs))
+    }
+}
+
+impl Add for Resources {
+    type Output = Resources;
+
+    fn add(mut self, rhs: Self) -> Self::Output {
+        self += rhs;
+        self
+    }
+}
+
+impl AddAssign for Resources {
+    fn add_assign(&mut self, rhs: Self) {
+        for (a, b) in self.amounts.iter_mut().zip(rhs.amounts) {
+            *a += b;
+        }
+    }
+}
+
+impl Sub for Resources {
+    type Output = Resources;
+
+    fn sub(mut self, rhs: Self) -> Self::Output {
+        self -= rhs;
+        self
+    }
+}
+
+impl SubAssign for Resources {
+    fn sub_assign(&mut self, rhs: Self) {
+        for (a, b) in self.amounts.iter_mut().zip(rhs.amounts) {
+            *a -= b;
+        }
+    }
+}
+
+impl Mul<f32> for Resources {
+    type Output = Resources;
+
+    fn mul(mut self, rhs: f32) -> Self::Output {
+        for a in self.amounts.iter_mut() {
+            *a *= rhs;
+        }
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LedgerEntry {
+    /// What the income or expense comes from, e.g. "Capital" or "Fleet upkeep"
+    pub source: String,
+    pub resource: Resource,
+    /// Positive for income, negative for expenses
+    pub amount: f32,
+}
+
+/// Income and expenses of an empire over one turn
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Ledger {
+    pub entries: Vec<LedgerEntry>,
+}
+
+impl Ledger {
+    pub fn record(&mut self, source: &str, resource: Resource, amount: f32) {
+        if amount != 0.0 {
+            self.entries.push(LedgerEntry { source: source.to_owned(), resource, amount });
+        }
+    }
+
+    /// Records every resource of `amounts` as income from `source`
+    pub fn record_income(&mut self, source: &str, amounts: &Resources) {
+        for (resource, amount) in amounts.iter() {
+            self.record(source, resource, amount);
+        }
+    }
+
+    /// Records every resource of `amounts` as an expense of `source`
+    pub fn record_expense(&mut self, source: &str, amounts: &Resources) {
+        for (resource, amount) in amounts.iter() {
+            self.record(source, resource, -amount);
+        }
+    }
+
+    pub fn income(&self) -> Resources {
+        let mut res = Resources::default();
+        for entry in self.entries.iter().filter(|e| e.amount > 0.0) {
+            res.add_amount(entry.resource, entry.amount);
+        }
+        res
+    }
+
+    /// Income from every source but the given one
+    pub fn income_except(&self, source: &str) -> Resources {
+        let mut res = Resources::default();
+        for entry in self.entries.iter().filter(|e| e.amount > 0.0 && e.source != source) {
+            res.add_amount(entry.resource, entry.amount);
+        }
+        res
+    }
+
+    pub fn expenses(&self) -> Resources {
+        let mut res = Resources::default();
+        for entry in self.entries.iter().filter(|e| e.amount < 0.0) {
+            res.add_amount(entry.resource, -entry.amount);
+        }
+        res
+    }
+
+    pub fn net(&self) -> Resources {
+        self.income() - self.expenses()
+    }
+
+    /// Net amount of every resource per source, in the order the sources first appeared
+    pub fn by_source(&self) -> Vec<(String, Resources)> {
+        let mut res: Vec<(String, Resources)> = Vec::new();
+        for entry in &self.entries {
+            match res.iter_mut().find(|(s, _)| *s == entry.source) {
+                Some((_, amounts)) => { amounts.add_amount(entry.resource, entry.amount) }
+                None => { res.push((entry.source.clone(), Resources::of(entry.resource, entry.amount))) }
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ledger_totals() {
+        let mut ledger = Ledger::default();
+        ledger.record("Capital", Resource::Energy, 10.0);
+        ledger.record("Capital", Resource::Minerals, 5.0);
+        ledger.record("Fleet upkeep", Resource::Energy, -3.0);
+
+        assert_eq!(ledger.income().get(Resource::Energy), 10.0);
+        assert_eq!(ledger.expenses().get(Resource::Energy), 3.0);
+        assert_eq!(ledger.net().get(Resource::Energy), 7.0);
+        assert_eq!(ledger.net().get(Resource::Minerals), 5.0);
+
+        let sources = ledger.by_source();
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources[1].0, "Fleet upkeep");
+        assert_eq!(sources[1].1.get(Resource::Energy), -3.0);
+    }
+
+    #[test]
+    fn test_covers() {
+        let stockpile = Resources::from_pairs(&[(Resource::Minerals, 100.0), (Resource::Energy, 10.0)]);
+        assert!(stockpile.covers(&Resources::of(Resource::Minerals, 100.0)));
+        assert!(!stockpile.covers(&Resources::of(Resource::Energy, 11.0)));
+    }
+}
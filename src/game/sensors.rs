@@ -0,0 +1,219 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::game::empire::EmpireId;
+use crate::game::fleet::Fleet;
+use crate::game::galaxy::SystemId;
+use crate::game::state::GameState;
+use crate::game::turn::{EventKind, Phase, TurnReport};
+
+/// Light years the sensors of an outpost reach
+const OUTPOST_SENSOR_RANGE: f32 = 5.0;
+
+const OUTPOST_SENSOR_STRENGTH: f32 = 1.0;
+
+/// Something an empire sees the galaxy through: a fleet, a colony or an outpost
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sensor {
+    /// Light years, in galaxy coordinates
+    pub position: (f32, f32),
+    /// Light years
+    pub range: f32,
+    pub strength: f32,
+    /// Multiplier of the range in the system the sensor is in
+    pub interference: f32,
+}
+
+impl Sensor {
+    /// Whether the sensor picks up a fleet at the position. Interference shortens the range once,
+    /// by the stronger of that around the sensor and that in the system the fleet is in. Without
+    /// any strength a sensor picks up nothing
+    pub fn detects(&self, position: (f32, f32), stealth: f32, interference: f32) -> bool {
+        let distance = ((position.0 - self.position.0).powi(2) + (position.1 - self.position.1).powi(2)).sqrt();
+        let range = self.range * self.interference.min(interference);
+        self.strength > 0.0 && self.strength >= stealth && distance <= range
+    }
+}
+
+/// Multiplier of sensor ranges in a system, 1 between systems
+fn interference(state: &GameState, system: Option<SystemId>) -> f32 {
+    system.map_or(1.0, |s| state.galaxy.systems[s].modifiers().sensor_modifier)
+}
+
+/// Every sensor of an empire, on its fleets, in its colonies and at its outposts
+pub fn sensors(state: &GameState, empire: EmpireId) -> Vec<Sensor> {
+    let fleets = state.fleets.iter()
+        .filter(|f| f.owner == empire)
+        .map(|f| {
+            let (range, strength) = f.sensors();
+            Sensor {
+                position: f.position(&state.galaxy),
+                range,
+                strength,
+                interference: interference(state, f.system()),
+            }
+        });
+    let colonies = state.galaxy.colonies()
+        .filter_map(|(r, b)| b.colony.as_ref().filter(|c| c.owner == empire).map(|c| (r, c)))
+        .map(|(r, c)| {
+            let (range, strength) = c.sensors();
+            Sensor {
+                position: state.galaxy.positions[r.system],
+                range,
+                strength,
+                interference: interference(state, Some(r.system)),
+            }
+        });
+    let outposts = state.galaxy.systems.iter().enumerate()
+        .filter(|(_, s)| s.outpost.as_ref().is_some_and(|o| o.owner == empire))
+        .map(|(id, _)| Sensor {
+            position: state.galaxy.positions[id],
+            range: OUTPOST_SENSOR_RANGE,
+            strength: OUTPOST_SENSOR_STRENGTH,
+            interference: interference(state, Some(id)),
+        });
+    fleets.chain(colonies).chain(outposts).collect()
+}
+
+/// Whether any of the sensors picks up the fleet
+pub fn is_detected(state: &GameState, sensors: &[Sensor], fleet: &Fleet) -> bool {
+    let (position, stealth) = (fleet.position(&state.galaxy), fleet.stealth());
+    let interference = interference(state, fleet.system());
+    sensors.iter().any(|s| s.detects(position, stealth, interference))
+}
+
+/// Works out which fleets of other empires every empire sees this turn. Fleets the player picks
+/// up that it did not see before go into the report
+pub fn detection(state: &mut GameState, report: &mut TurnReport) {
+    for empire in 0..state.empires.len() {
+        // Allies see what the other sees
+        let sensors: Vec<Sensor> = (0..state.empires.len())
+            .filter(|e| *e == empire || state.diplomacy.shares_sensors(empire, *e))
+            .flat_map(|e| sensors(state, e))
+            .collect();
+        let detected: Vec<&Fleet> = state.fleets.iter()
+            .filter(|f| f.owner != empire && is_detected(state, &sensors, f))
+            .collect();
+        if empire == state.player {
+            let known = &state.empires[empire].knowledge;
+            for fleet in detected.iter().filter(|f| !known.is_detected(f.id)) {
+                report.add(Phase::Movement, EventKind::FleetDetected, format!(
+                    "Sensors picked up {} of {} near {}",
+                    fleet.name,
+                    state.empires[fleet.owner].name,
+                    state.galaxy.systems[fleet.location].name
+                ));
+            }
+        }
+        let detected = detected.iter().map(|f| f.id).collect();
+        state.empires[empire].knowledge.fleets = detected;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::style::Color;
+    use crate::game::design::ShipDesign;
+    use crate::game::empire::Empire;
+    use crate::game::fleet::FleetId;
+    use crate::game::ship::Ship;
+    use super::*;
+
+    /// Adds a rival empire with a fleet of a ship of the given components in the system
+    fn rival_fleet(state: &mut GameState, system: SystemId, components: &[&str]) -> FleetId {
+        let design = ShipDesign {
+            name: "Raider".to_owned(),
+            hull: "corvette".to_owned(),
+            components: components.iter().map(|c| c.to_string()).collect(),
+            revision: 0,
+        };
+        state.empires.push(Empire::new(1, "Rival", Color::Red, system));
+        let id = state.next_fleet_id();
+        let mut fleet = Fleet::new(id, "Raiders", 1, system);
+        fleet.ships = vec![Ship::new("Raider 1", &design)];
+        state.fleets.push(fleet);
+        id
+    }
+
+    #[test]
+    fn test_sensor_range() {
+        let sensor = Sensor { position: (0.0, 0.0), range: 10.0, strength: 1.0, interference: 1.0 };
+        assert!(sensor.detects((6.0, 8.0), 0.0, 1.0));
+        assert!(sensor.detects((6.0, 8.0), 1.0, 1.0));
+        assert!(!sensor.detects((6.0, 8.1), 0.0, 1.0));
+        // Stealthier ships than the sensors are strong stay hidden, however close
+        assert!(!sensor.detects((0.0, 0.0), 2.0, 1.0));
+        // Interference around the target shortens the range
+        assert!(!sensor.detects((6.0, 0.0), 0.0, 0.5));
+        assert!(sensor.detects((4.0, 0.0), 0.0, 0.5));
+        // Interference counts once, even with both ends in the same nebula
+        let sensor = Sensor { interference: 0.5, ..sensor };
+        assert!(sensor.detects((4.0, 0.0), 0.0, 0.5));
+        assert!(!sensor.detects((6.0, 0.0), 0.0, 1.0));
+        // A sensor without any strength sees nothing, not even what is right next to it
+        let sensor = Sensor { strength: 0.0, ..sensor };
+        assert!(!sensor.detects((0.0, 0.0), 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_detection() {
+        let mut state = GameState::new(10);
+        let capital = state.player_empire().capital;
+        let player_sensors = sensors(&state, state.player);
+        let far = (0..state.galaxy.systems.len())
+            .find(|s| player_sensors.iter().all(|p| !p.detects(state.galaxy.positions[*s], 0.0, 1.0)))
+            .unwrap();
+        let fleet = rival_fleet(&mut state, far, &["fission_reactor", "chemical_thruster", "mass_driver"]);
+        let mut report = TurnReport::default();
+        detection(&mut state, &mut report);
+        assert!(!state.player_empire().knowledge.is_detected(fleet));
+        assert!(report.entries.is_empty());
+
+        let index = state.fleets.len() - 1;
+        state.fleets[index].location = capital;
+        detection(&mut state, &mut report);
+        assert!(state.player_empire().knowledge.is_detected(fleet));
+        assert_eq!(report.entries.len(), 1);
+        assert!(matches!(report.entries[0].kind, EventKind::FleetDetected));
+        // A fleet already seen is not reported again
+        detection(&mut state, &mut report);
+        assert_eq!(report.entries.len(), 1);
+    }
+
+    #[test]
+    fn test_no_sensors() {
+        // An unarmed fleet without sensors sees nothing, even in a system full of ships
+        let mut state = GameState::new(12);
+        let capital = state.player_empire().capital;
+        let fleet = rival_fleet(&mut state, capital, &["fission_reactor", "chemical_thruster"]);
+        assert_eq!(state.fleets.last().unwrap().sensors().1, 0.0);
+        detection(&mut state, &mut TurnReport::default());
+        assert!(state.empires[1].knowledge.fleets.is_empty());
+        assert!(state.player_empire().knowledge.is_detected(fleet));
+    }
+
+    #[test]
+    fn test_stealth() {
+        let mut state = GameState::new(11);
+        let capital = state.player_empire().capital;
+        let fleet = rival_fleet(&mut state, capital, &["fission_reactor", "chemical_thruster", "signature_dampener"]);
+        assert_eq!(state.fleets.last().unwrap().stealth(), 2.0);
+        detection(&mut state, &mut TurnReport::default());
+        assert!(!state.player_empire().knowledge.is_detected(fleet));
+        assert!(!state.sees_fleet(state.player, state.fleets.last().unwrap()));
+        assert!(state.sees_fleet(1, state.fleets.last().unwrap()));
+    }
+}
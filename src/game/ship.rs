@@ -0,0 +1,89 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::game::design::ShipDesign;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ShipRole {
+    Warship,
+    /// Carries colonists, spent when founding a colony
+    ColonyShip,
+    /// Unarmed ships with sensors, which survey the bodies of the systems they visit
+    Survey,
+    /// Anything else that is unarmed, such as freighters
+    Auxiliary,
+}
+
+impl ShipRole {
+    pub fn of(design: &ShipDesign) -> Self {
+        let stats = design.stats();
+        if stats.colonists > 0 {
+            ShipRole::ColonyShip
+        } else if stats.damage > 0.0 {
+            ShipRole::Warship
+        } else if stats.sensor_strength > 0.0 {
+            ShipRole::Survey
+        } else {
+            ShipRole::Auxiliary
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ship {
+    pub name: String,
+    pub role: ShipRole,
+    /// The design the ship was built or last refitted to
+    pub design: ShipDesign,
+    /// Armour and structure lost in battle and not repaired yet
+    pub damage: f32,
+    /// Shield capacity worn down by radiation and solar flares and not recharged yet
+    pub shield_loss: f32,
+}
+
+impl Ship {
+    pub fn new(name: &str, design: &ShipDesign) -> Self {
+        Self {
+            name: name.to_owned(),
+            role: ShipRole::of(design),
+            design: design.clone(),
+            damage: 0.0,
+            shield_loss: 0.0,
+        }
+    }
+
+    /// Shield the ship would go into battle with
+    pub fn shield(&self) -> f32 {
+        (self.design.stats().shield - self.shield_loss).max(0.0)
+    }
+
+    /// Strips the given fraction of shield capacity
+    pub fn drain_shield(&mut self, fraction: f32) {
+        let capacity = self.design.stats().shield;
+        self.shield_loss = (self.shield_loss + capacity * fraction).min(capacity);
+    }
+
+    /// Recharges the given fraction of shield capacity
+    pub fn recharge_shield(&mut self, fraction: f32) {
+        let capacity = self.design.stats().shield;
+        self.shield_loss = (self.shield_loss - capacity * fraction).max(0.0);
+    }
+
+    /// Whether the empire has saved a newer revision of the ship's design
+    pub fn is_outdated(&self, designs: &[ShipDesign]) -> bool {
+        designs.iter().any(|d| d.name == self.design.name && d.revision > self.design.revision)
+    }
+}
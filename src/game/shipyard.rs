@@ -0,0 +1,399 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::game::design::ShipDesign;
+use crate::game::empire::EmpireId;
+use crate::game::fleet::{Fleet, FleetId};
+use crate::game::galaxy::BodyRef;
+use crate::game::resources::{Ledger, Resources};
+use crate::game::ship::Ship;
+use crate::game::state::GameState;
+use crate::game::turn::{EventKind, Phase, TurnReport};
+
+/// Building a colony needs to construct ships
+pub const SHIPYARD: &str = "shipyard";
+
+/// Tonnes of ship a yard puts together in a turn
+const TONNES_PER_TURN: f32 = 50.0;
+
+/// Shortest time a ship takes to build
+const MIN_BUILD_TIME: u32 = 2;
+
+/// A ship being built or refitted in the shipyard of a colony. The cost is paid in equal
+/// instalments over the build time, and the work stalls on turns the empire can not pay
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShipConstruction {
+    /// The design as it was when the ship was laid down
+    pub design: ShipDesign,
+    pub ship_name: String,
+    /// The ship as it was before a refit, which returns to service if the refit is cancelled
+    pub refitting: Option<Ship>,
+    /// Turns of work done so far
+    pub progress: u32,
+    /// What has been paid so far, refunded if the work is cancelled
+    pub paid: Resources,
+}
+
+impl ShipConstruction {
+    pub fn new(design: &ShipDesign, ship_name: &str) -> Self {
+        Self {
+            design: design.clone(),
+            ship_name: ship_name.to_owned(),
+            refitting: None,
+            progress: 0,
+            paid: Resources::default(),
+        }
+    }
+
+    /// Refit of an existing ship to a newer revision of its design
+    pub fn refit(ship: Ship, design: &ShipDesign) -> Self {
+        Self {
+            refitting: Some(ship.clone()),
+            ..Self::new(design, &ship.name)
+        }
+    }
+
+    pub fn is_refit(&self) -> bool {
+        self.refitting.is_some()
+    }
+
+    /// Turns of work the ship needs, refits take half the time
+    pub fn build_time(&self) -> u32 {
+        let turns = (self.design.stats().mass / TONNES_PER_TURN).ceil() as u32;
+        let turns = turns.max(MIN_BUILD_TIME);
+        if self.is_refit() { turns.div_ceil(2) } else { turns }
+    }
+
+    /// Full price of the work, refits cost half as much as a new ship
+    pub fn cost(&self) -> Resources {
+        let cost = self.design.stats().cost;
+        if self.is_refit() { cost * 0.5 } else { cost }
+    }
+
+    /// Payment due for the next turn of work
+    pub fn instalment(&self) -> Resources {
+        self.cost() * (1.0 / self.build_time() as f32)
+    }
+
+    pub fn describe(&self) -> String {
+        let kind = if self.is_refit() { "refit" } else { "build" };
+        format!(
+            "{} ({} r{}, {kind}) {}/{}",
+            self.ship_name,
+            self.design.name,
+            self.design.revision,
+            self.progress,
+            self.build_time(),
+        )
+    }
+}
+
+/// Checks that the body is a colony of the empire with a shipyard and returns its name
+fn check_yard(state: &GameState, empire: EmpireId, yard: BodyRef) -> Result<String, String> {
+    let Some(body) = state.galaxy.body(yard) else {
+        return Err("The colony no longer exists".to_owned());
+    };
+    let Some(colony) = body.colony.as_ref().filter(|c| c.owner == empire) else {
+        return Err(format!("{} is not a colony of the empire", body.name));
+    };
+    if !colony.has_shipyard() {
+        return Err(format!("{} has no shipyard", body.name));
+    }
+    Ok(body.name.clone())
+}
+
+/// Lays down a new ship of one of the empire's designs in the shipyard of a colony
+pub fn queue_ship(
+    state: &mut GameState,
+    empire: EmpireId,
+    yard: BodyRef,
+    design: &str,
+) -> Result<String, String> {
+    let name = check_yard(state, empire, yard)?;
+    let owner = &mut state.empires[empire];
+    let Some(design) = owner.design(design).cloned() else {
+        return Err(format!("{} has no design called {design}", owner.name));
+    };
+    if let Some(problem) = design.validate(&owner.research).first() {
+        return Err(format!("{} can not be built: {problem}", design.name));
+    }
+    owner.ships_built += 1;
+    let ship_name = format!("{} {}", design.name, owner.ships_built);
+    let colony = state.galaxy.body_mut(yard).unwrap().colony.as_mut().unwrap();
+    colony.shipyard_queue.push(ShipConstruction::new(&design, &ship_name));
+    Ok(format!("{ship_name} was laid down at {name}"))
+}
+
+/// Removes an entry from the shipyard queue of a colony and refunds what was paid for it. A ship
+/// that was being refitted goes back into service as it was
+pub fn cancel_ship(
+    state: &mut GameState,
+    empire: EmpireId,
+    yard: BodyRef,
+    index: usize,
+) -> Result<String, String> {
+    let name = check_yard(state, empire, yard)?;
+    let colony = state.galaxy.body_mut(yard).unwrap().colony.as_mut().unwrap();
+    if index >= colony.shipyard_queue.len() {
+        return Err(format!("Nothing to cancel at {name}"));
+    }
+    let entry = colony.shipyard_queue.remove(index);
+    state.empires[empire].stockpile += entry.paid;
+    if let Some(ship) = entry.refitting {
+        launch(state, empire, yard, ship);
+    }
+    Ok(format!("Work on {} at {name} was cancelled", entry.ship_name))
+}
+
+/// Takes the ships of a fleet whose designs have newer revisions into the shipyard of a colony in
+/// the same system, to be refitted to the latest revision
+pub fn refit_fleet(
+    state: &mut GameState,
+    empire: EmpireId,
+    fleet: FleetId,
+    yard: BodyRef,
+) -> Result<String, String> {
+    let name = check_yard(state, empire, yard)?;
+    let designs = state.empires[empire].designs.clone();
+    let Some(f) = state.fleets.iter_mut().find(|f| f.id == fleet && f.owner == empire) else {
+        return Err("The fleet no longer exists".to_owned());
+    };
+    if f.system() != Some(yard.system) || f.is_moving() {
+        return Err(format!("{} is not at {name}", f.name));
+    }
+    let (outdated, current): (Vec<Ship>, Vec<Ship>) = f.ships.drain(..)
+        .partition(|s| s.is_outdated(&designs));
+    f.ships = current;
+    if outdated.is_empty() {
+        return Err(format!("Every ship of {} is up to date", f.name));
+    }
+    let fleet_name = f.name.clone();
+    let count = outdated.len();
+    state.remove_empty_fleets();
+    let colony = state.galaxy.body_mut(yard).unwrap().colony.as_mut().unwrap();
+    for ship in outdated {
+        let latest = designs.iter().find(|d| d.name == ship.design.name).unwrap();
+        colony.shipyard_queue.push(ShipConstruction::refit(ship, latest));
+    }
+    Ok(format!("{count} ships of {fleet_name} went into the yards of {name} for a refit"))
+}
+
+/// Puts the ships that were in for a refit back into service as they were, after the colony whose
+/// yards they were in was lost
+pub fn return_refits(state: &mut GameState, empire: EmpireId, yard: BodyRef, queue: Vec<ShipConstruction>) {
+    for ship in queue.into_iter().filter_map(|e| e.refitting) {
+        launch(state, empire, yard, ship);
+    }
+}
+
+/// Puts a ship into service in the system of the yard, joining a fleet of the empire that is
+/// waiting there or forming a new one
+fn launch(state: &mut GameState, empire: EmpireId, yard: BodyRef, ship: Ship) {
+    let existing = state.fleets.iter_mut()
+        .find(|f| f.owner == empire && f.location == yard.system && !f.is_moving());
+    match existing {
+        Some(fleet) => { fleet.ships.push(ship) }
+        None => {
+            // The yard may be gone, taking its name with it
+            let name = state.galaxy.body(yard)
+                .map_or(&state.galaxy.systems[yard.system].name, |b| &b.name);
+            let name = format!("{name} Squadron");
+            let mut fleet = Fleet::new(state.next_fleet_id(), &name, empire, yard.system);
+            fleet.ships.push(ship);
+            state.fleets.push(fleet);
+        }
+    }
+}
+
+/// Advances the first entry of every shipyard queue of the empire by a turn, paying the
+/// instalments out of the turn's ledger
+pub fn shipyards(state: &mut GameState, empire: EmpireId, ledger: &mut Ledger, report: &mut TurnReport) {
+    let yards: Vec<BodyRef> = state.galaxy.colonies()
+        .filter(|(_, b)| b.colony.as_ref().is_some_and(|c| c.owner == empire))
+        .map(|(r, _)| r)
+        .collect();
+    for yard in yards {
+        let stockpile = state.empires[empire].stockpile;
+        let body = state.galaxy.body_mut(yard).unwrap();
+        let name = body.name.clone();
+        let colony = body.colony.as_mut().unwrap();
+        let Some(current) = colony.shipyard_queue.first_mut() else { continue };
+        let instalment = current.instalment();
+        if !(stockpile + ledger.net()).covers(&instalment) {
+            continue;
+        }
+        ledger.record_expense("Ship construction", &instalment);
+        current.paid += instalment;
+        current.progress += 1;
+        if current.progress < current.build_time() {
+            continue;
+        }
+        let entry = colony.shipyard_queue.remove(0);
+        let (kind, text) = if entry.is_refit() {
+            (EventKind::RefitCompleted, format!(
+                "{} was refitted to revision {} of the {} design at {name}",
+                entry.ship_name,
+                entry.design.revision,
+                entry.design.name,
+            ))
+        } else {
+            (EventKind::ShipCompleted, format!("{} was launched at {name}", entry.ship_name))
+        };
+        if empire == state.player {
+            report.add(Phase::Production, kind, text);
+        }
+        launch(state, empire, yard, Ship::new(&entry.ship_name, &entry.design));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::design::save_design;
+    use crate::game::resources::Resource;
+    use crate::game::turn::end_turn;
+    use super::*;
+
+    /// Game with a shipyard on the homeworld and plenty of resources
+    fn game(seed: u64) -> (GameState, BodyRef) {
+        let mut state = GameState::new(seed);
+        let home = state.galaxy.colonies().next().unwrap().0;
+        let colony = state.galaxy.body_mut(home).unwrap().colony.as_mut().unwrap();
+        colony.buildings.push(SHIPYARD.to_owned());
+        for resource in Resource::ALL {
+            state.empires[0].stockpile.set(resource, 1000.0);
+        }
+        (state, home)
+    }
+
+    fn ships(state: &GameState) -> usize {
+        state.fleets.iter().map(|f| f.ships.len()).sum()
+    }
+
+    #[test]
+    fn test_build_ship() {
+        let (mut state, home) = game(1);
+        let player = state.player;
+        let design = state.player_empire().designs[0].clone();
+        let before = ships(&state);
+        assert!(queue_ship(&mut state, player, home, &design.name).is_ok());
+
+        let entry = ShipConstruction::new(&design, "");
+        for turn in 1..=entry.build_time() {
+            let report = end_turn(&mut state);
+            let launched = report.entries.iter().any(|e| e.kind == EventKind::ShipCompleted);
+            assert_eq!(launched, turn == entry.build_time());
+            let paid: Resources = state.player_empire().ledger.by_source().into_iter()
+                .filter(|(s, _)| s == "Ship construction")
+                .map(|(_, r)| r)
+                .fold(Resources::default(), |a, b| a + b);
+            assert!(!paid.is_zero());
+        }
+        assert_eq!(ships(&state), before + 1);
+        let colony = state.galaxy.body(home).unwrap().colony.as_ref().unwrap();
+        assert!(colony.shipyard_queue.is_empty());
+        assert!(state.fleets.iter().any(|f| f.location == home.system
+            && f.ships.iter().any(|s| s.design == design)));
+    }
+
+    #[test]
+    fn test_needs_shipyard_and_resources() {
+        let (mut state, home) = game(2);
+        let player = state.player;
+        let design = state.player_empire().designs[0].name.clone();
+        assert!(queue_ship(&mut state, player, home, "No such design").is_err());
+
+        state.galaxy.body_mut(home).unwrap().colony.as_mut().unwrap().buildings.clear();
+        assert!(queue_ship(&mut state, player, home, &design).is_err());
+
+        // Without resources the work stalls
+        let (mut state, home) = game(2);
+        assert!(queue_ship(&mut state, player, home, &design).is_ok());
+        state.empires[0].stockpile = Resources::default();
+        let mut ledger = Ledger::default();
+        shipyards(&mut state, player, &mut ledger, &mut TurnReport::default());
+        assert!(ledger.expenses().is_zero());
+        let colony = state.galaxy.body(home).unwrap().colony.as_ref().unwrap();
+        assert_eq!(colony.shipyard_queue[0].progress, 0);
+
+        // Cancelling refunds what was paid
+        let (mut state, home) = game(3);
+        let before = state.player_empire().stockpile;
+        assert!(queue_ship(&mut state, player, home, &design).is_ok());
+        assert!(cancel_ship(&mut state, player, home, 0).is_ok());
+        assert_eq!(state.player_empire().stockpile, before);
+    }
+
+    #[test]
+    fn test_refit() {
+        let (mut state, home) = game(4);
+        let player = state.player;
+        let fleet = state.fleets.iter().find(|f| f.owner == player).unwrap().id;
+        let mut design = state.player_empire().designs[0].clone();
+        let weapon = design.components.iter().position(|c| c == "mass_driver").unwrap();
+        design.components.remove(weapon);
+        save_design(&mut state, player, design.clone()).unwrap();
+        let latest = state.player_empire().design(&design.name).unwrap().clone();
+        assert_eq!(latest.revision, design.revision + 1);
+
+        let outdated = state.fleets[fleet].ships.iter()
+            .filter(|s| s.is_outdated(&state.empires[player].designs))
+            .count();
+        assert!(outdated > 0);
+        assert!(refit_fleet(&mut state, player, fleet, home).is_ok());
+        assert!(refit_fleet(&mut state, player, fleet, home).is_err());
+        for _ in 0..10 {
+            end_turn(&mut state);
+        }
+        let refitted = state.fleets.iter()
+            .flat_map(|f| f.ships.iter())
+            .filter(|s| s.design == latest)
+            .count();
+        assert_eq!(refitted, outdated);
+    }
+
+    #[test]
+    fn test_refit_whole_fleet() {
+        let (mut state, home) = game(5);
+        let player = state.player;
+        let mut design = state.player_empire().designs[0].clone();
+        let fleet = state.next_fleet_id();
+        let mut squadron = Fleet::new(fleet, "Squadron", player, home.system);
+        squadron.ships = vec![Ship::new("Old", &design)];
+        state.fleets.push(squadron);
+        design.components.pop();
+        save_design(&mut state, player, design).unwrap();
+
+        // The emptied fleet is disbanded rather than left behind
+        assert!(refit_fleet(&mut state, player, fleet, home).is_ok());
+        assert!(state.fleets.iter().all(|f| !f.ships.is_empty()));
+        assert!(!state.fleets.iter().any(|f| f.id == fleet));
+
+        // A ship in the yards of a colony that dies out goes back into service as it was
+        let before = ships(&state);
+        let colony = state.galaxy.body_mut(home).unwrap().colony.as_mut().unwrap();
+        while colony.population > 1 {
+            colony.remove_pop();
+        }
+        colony.growth = -0.5;
+        // Starving
+        state.empires[player].stockpile.set(Resource::Food, 0.0);
+        state.empires[player].ledger.record("Population upkeep", Resource::Food, -10.0);
+        crate::game::population::growth(&mut state, &mut TurnReport::default());
+        assert!(state.galaxy.body(home).unwrap().colony.is_none());
+        assert_eq!(ships(&state), before + 1);
+        assert!(state.fleets.iter().any(|f| f.ships.iter().any(|s| s.name == "Old")));
+    }
+}
@@ -0,0 +1,332 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use lazy_static::lazy_static;
+use rand::Rng;
+use serde::Deserialize;
+use strum::Display;
+use crate::game::body::{Body, BodyType, PlanetType};
+use crate::game::tech::{habitability_bonus, Modifier};
+
+const TRAITS_DATA: &str = include_str!("../../assets/traits.json5");
+
+lazy_static! {
+    /// Every trait defined in the data files
+    pub static ref TRAITS: Vec<TraitDef> =
+        json5::from_str(TRAITS_DATA).expect("trait definitions are valid");
+}
+
+/// Planet types a species can come from, the climates it prefers
+pub const CLIMATES: [PlanetType; 4] = [
+    PlanetType::Earthlike,
+    PlanetType::Desert,
+    PlanetType::Ice,
+    PlanetType::Rock,
+];
+
+/// Habitability of planets of other climates than the preferred one, as a share of what the
+/// planet type offers
+const FOREIGN_CLIMATE: f32 = 0.8;
+
+/// A habitable planet stays at least this habitable, whatever the traits
+const MIN_HABITABILITY: f32 = 0.05;
+
+/// Points a species spends on traits, besides what its drawbacks give back
+const TRAIT_POINTS: i32 = 2;
+
+/// Chance a species has a drawback, which pays for another strength
+const DRAWBACK_CHANCE: f64 = 0.5;
+
+const NAME_ONSETS: [&str; 18] = [
+    "Zor", "Vel", "Kha", "Ith", "Mor", "Sha", "Tal", "Qua", "Ryn",
+    "Ux", "Bel", "Dra", "Ner", "Oss", "Py", "Gal", "Xi", "Lum",
+];
+const NAME_MIDDLES: [&str; 11] = ["a", "e", "i", "o", "u", "ae", "y", "ou", "ar", "el", ""];
+const NAME_ENDINGS: [&str; 12] = ["thi", "ni", "rans", "ari", "oth", "ex", "ul", "ites", "on", "ids", "ar", "eem"];
+
+/// Parts of the portraits, every line nine columns wide
+const PORTRAIT_CROWNS: [&str; 6] = ["  \\   /  ", "   | |   ", "  ^   ^  ", "  ~~~~~  ", "   ***   ", "         "];
+/// Top, left side, right side and bottom of a head
+const PORTRAIT_HEADS: [(&str, char, char, &str); 4] = [
+    (" .-----. ", '|', '|', " '-----' "),
+    (" /-----\\ ", '|', '|', " \\-----/ "),
+    (" (=====) ", '(', ')', " (=====) "),
+    (" .-^-^-. ", '<', '>', "  \\___/  "),
+];
+const PORTRAIT_EYES: [&str; 8] = [" o o ", " @ @ ", " 0 0 ", " - - ", " ooo ", "o   o", " ^ ^ ", " * * "];
+const PORTRAIT_MOUTHS: [&str; 7] = ["  v  ", " --- ", "  w  ", " <=> ", " \\_/ ", "  o  ", " ~~~ "];
+
+/// A trait of a species, as defined in the data files
+#[derive(Debug, Clone, Deserialize)]
+pub struct TraitDef {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    /// Points the trait costs, negative for a drawback
+    pub cost: i32,
+    #[serde(default)]
+    pub modifiers: Vec<Modifier>,
+    /// Traits a species with this one can not have
+    #[serde(default)]
+    pub excludes: Vec<String>,
+}
+
+impl TraitDef {
+    /// Whether a species with the given traits can also have this one
+    fn fits(&self, traits: &[String]) -> bool {
+        !traits.contains(&self.id)
+            && !traits.iter().any(|t| self.excludes.contains(t))
+            && !traits.iter().filter_map(|t| species_trait(t)).any(|t| t.excludes.contains(&self.id))
+    }
+}
+
+pub fn species_trait(id: &str) -> Option<&'static TraitDef> {
+    TRAITS.iter().find(|t| t.id == id)
+}
+
+/// Outlook of a species on the galaxy, which shapes what it thinks of other species
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum Ethic {
+    Militarist,
+    Pacifist,
+    Xenophile,
+    Xenophobe,
+    Materialist,
+    Spiritualist,
+    Egalitarian,
+    Authoritarian,
+}
+
+impl Ethic {
+    pub const ALL: [Ethic; 8] = [
+        Ethic::Militarist,
+        Ethic::Pacifist,
+        Ethic::Xenophile,
+        Ethic::Xenophobe,
+        Ethic::Materialist,
+        Ethic::Spiritualist,
+        Ethic::Egalitarian,
+        Ethic::Authoritarian,
+    ];
+
+    /// The ethic a species holding this one can not hold as well
+    pub fn opposite(&self) -> Ethic {
+        match self {
+            Ethic::Militarist => { Ethic::Pacifist }
+            Ethic::Pacifist => { Ethic::Militarist }
+            Ethic::Xenophile => { Ethic::Xenophobe }
+            Ethic::Xenophobe => { Ethic::Xenophile }
+            Ethic::Materialist => { Ethic::Spiritualist }
+            Ethic::Spiritualist => { Ethic::Materialist }
+            Ethic::Egalitarian => { Ethic::Authoritarian }
+            Ethic::Authoritarian => { Ethic::Egalitarian }
+        }
+    }
+}
+
+/// The people of an empire
+#[derive(Debug, Clone, PartialEq)]
+pub struct Species {
+    pub name: String,
+    /// Planets of this type suit the species best
+    pub climate: PlanetType,
+    /// Ids of the traits
+    pub traits: Vec<String>,
+    pub ethics: Vec<Ethic>,
+    /// Lines of a small picture of the species
+    pub portrait: Vec<String>,
+}
+
+impl Default for Species {
+    /// Humans of an Earthlike world, with neither strengths nor drawbacks
+    fn default() -> Self {
+        Self {
+            name: "Human".to_owned(),
+            climate: PlanetType::Earthlike,
+            traits: Vec::new(),
+            ethics: Vec::new(),
+            portrait: portrait(5, 0, 0, 0),
+        }
+    }
+}
+
+impl Species {
+    pub fn generate<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let name = format!(
+            "{}{}{}",
+            NAME_ONSETS[rng.gen_range(0..NAME_ONSETS.len())],
+            NAME_MIDDLES[rng.gen_range(0..NAME_MIDDLES.len())],
+            NAME_ENDINGS[rng.gen_range(0..NAME_ENDINGS.len())],
+        );
+        let climate = CLIMATES[rng.gen_range(0..CLIMATES.len())].clone();
+
+        let mut traits: Vec<String> = Vec::new();
+        let mut points = TRAIT_POINTS;
+        if rng.gen_bool(DRAWBACK_CHANCE) {
+            let drawbacks: Vec<&TraitDef> = TRAITS.iter().filter(|t| t.cost < 0).collect();
+            let drawback = drawbacks[rng.gen_range(0..drawbacks.len())];
+            traits.push(drawback.id.clone());
+            points -= drawback.cost;
+        }
+        loop {
+            let strengths: Vec<&TraitDef> = TRAITS.iter()
+                .filter(|t| t.cost > 0 && t.cost <= points && t.fits(&traits))
+                .collect();
+            if strengths.is_empty() {
+                break;
+            }
+            let strength = strengths[rng.gen_range(0..strengths.len())];
+            traits.push(strength.id.clone());
+            points -= strength.cost;
+        }
+
+        let first = Ethic::ALL[rng.gen_range(0..Ethic::ALL.len())];
+        let others: Vec<Ethic> = Ethic::ALL.into_iter().filter(|e| *e != first && *e != first.opposite()).collect();
+        let ethics = vec![first, others[rng.gen_range(0..others.len())]];
+
+        let portrait = portrait(
+            rng.gen_range(0..PORTRAIT_CROWNS.len()),
+            rng.gen_range(0..PORTRAIT_HEADS.len()),
+            rng.gen_range(0..PORTRAIT_EYES.len()),
+            rng.gen_range(0..PORTRAIT_MOUTHS.len()),
+        );
+        Self { name, climate, traits, ethics, portrait }
+    }
+
+    pub fn traits(&self) -> impl Iterator<Item = &'static TraitDef> + '_ {
+        self.traits.iter().filter_map(|t| species_trait(t))
+    }
+
+    /// Lasting bonuses of the traits
+    pub fn modifiers(&self) -> Vec<Modifier> {
+        self.traits().flat_map(|t| t.modifiers.iter().copied()).collect()
+    }
+
+    /// How well the body suits a colony of the species, from 0 (uninhabitable) up. Planets of
+    /// the preferred climate are perfect, the others are less habitable than for an average
+    /// species, and traits may change both
+    pub fn habitability(&self, body: &Body) -> f32 {
+        let BodyType::Planet(planet) = &body.kind else { return 0.0 };
+        let base = planet.habitability();
+        if base <= 0.0 {
+            return 0.0;
+        }
+        let climate = if *planet == self.climate { 1.0 } else { base * FOREIGN_CLIMATE };
+        (climate + habitability_bonus(&self.modifiers())).max(MIN_HABITABILITY)
+    }
+
+    /// Ethics both species hold, and pairs of ethics of the two that oppose each other
+    pub fn ethics_with(&self, other: &Species) -> (usize, usize) {
+        let shared = self.ethics.iter().filter(|e| other.ethics.contains(e)).count();
+        let opposed = self.ethics.iter().filter(|e| other.ethics.contains(&e.opposite())).count();
+        (shared, opposed)
+    }
+
+    pub fn make_info(&self) -> Vec<String> {
+        let climate = self.climate.to_str();
+        let mut res = vec![
+            format!("Species: {}", self.name),
+            format!("Prefers: {climate}s"),
+            format!("Ethics: {}", self.ethics.iter().map(|e| e.to_string()).collect::<Vec<_>>().join(", ")),
+        ];
+        for def in self.traits() {
+            let modifiers: Vec<String> = def.modifiers.iter().map(|m| m.describe()).collect();
+            res.push(format!("{}: {}", def.name, modifiers.join(", ")));
+        }
+        res
+    }
+}
+
+/// Draws a portrait from its parts
+fn portrait(crown: usize, head: usize, eyes: usize, mouth: usize) -> Vec<String> {
+    let (top, left, right, bottom) = PORTRAIT_HEADS[head];
+    vec![
+        PORTRAIT_CROWNS[crown].to_owned(),
+        top.to_owned(),
+        format!(" {left}{}{right} ", PORTRAIT_EYES[eyes]),
+        format!(" {left}{}{right} ", PORTRAIT_MOUTHS[mouth]),
+        bottom.to_owned(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use crate::game::body::{PlanetZone, EARTH_RADIUS};
+
+    use super::*;
+
+    fn planet(kind: PlanetType) -> Body {
+        let mut res = Body::generate_planet(&PlanetZone::HabitableZone, &mut StdRng::seed_from_u64(0));
+        res.kind = BodyType::Planet(kind);
+        res.radius = EARTH_RADIUS;
+        res
+    }
+
+    #[test]
+    fn test_definitions() {
+        assert!(TRAITS.iter().any(|t| t.cost > 0));
+        assert!(TRAITS.iter().any(|t| t.cost < 0));
+        for def in TRAITS.iter() {
+            assert_ne!(def.cost, 0, "{} is free", def.id);
+            for other in &def.excludes {
+                assert!(species_trait(other).is_some(), "{} excludes unknown {other}", def.id);
+            }
+        }
+    }
+
+    #[test]
+    fn test_generation() {
+        for seed in 0..200 {
+            let species = Species::generate(&mut StdRng::seed_from_u64(seed));
+            assert!(!species.name.is_empty());
+            assert!(CLIMATES.contains(&species.climate));
+            let cost: i32 = species.traits().map(|t| t.cost).sum();
+            assert!(cost <= TRAIT_POINTS, "seed {seed}: traits cost {cost}");
+            assert_eq!(species.traits().count(), species.traits.len());
+            for (i, t) in species.traits().enumerate() {
+                let others: Vec<String> = species.traits.iter().enumerate().filter(|(j, _)| *j != i).map(|(_, t)| t.clone()).collect();
+                assert!(t.fits(&others), "seed {seed}: {} does not go with {others:?}", t.id);
+            }
+            assert_eq!(species.ethics.len(), 2);
+            assert_ne!(species.ethics[0], species.ethics[1].opposite());
+            assert_eq!(species.portrait.len(), 5);
+            assert!(species.portrait.iter().all(|l| l.chars().count() == 9), "seed {seed}: {:?}", species.portrait);
+        }
+    }
+
+    #[test]
+    fn test_habitability() {
+        let mut species = Species { climate: PlanetType::Ice, ..Species::default() };
+        assert_eq!(species.habitability(&planet(PlanetType::Ice)), 1.0);
+        assert!(species.habitability(&planet(PlanetType::Earthlike)) < 1.0);
+        assert_eq!(species.habitability(&planet(PlanetType::GasGiant)), 0.0);
+
+        let desert = species.habitability(&planet(PlanetType::Desert));
+        species.traits.push("adaptive".to_owned());
+        assert!(species.habitability(&planet(PlanetType::Desert)) > desert);
+        assert_eq!(species.habitability(&planet(PlanetType::GasGiant)), 0.0);
+    }
+
+    #[test]
+    fn test_ethics() {
+        let a = Species { ethics: vec![Ethic::Militarist, Ethic::Xenophobe], ..Species::default() };
+        let b = Species { ethics: vec![Ethic::Militarist, Ethic::Xenophile], ..Species::default() };
+        assert_eq!(a.ethics_with(&b), (1, 1));
+        assert_eq!(a.ethics_with(&a), (2, 0));
+    }
+}
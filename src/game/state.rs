@@ -0,0 +1,308 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt::{Debug, Formatter};
+use std::ops::Deref;
+use std::sync::Arc;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use ratatui::style::Color;
+use crate::game::ai::{personality, Ai, Difficulty, EMPIRES, PERSONALITIES};
+use crate::game::calendar::{Date, Schedule};
+use crate::game::clock::{Clock, TimeMode};
+use crate::game::diplomacy::{Diplomacy, Stance};
+use crate::game::colony::{homeworld_candidate, stable_star, suits_homeworld, Colony, HOMEWORLD_BUILDINGS, HOMEWORLD_POPULATION};
+use crate::game::empire::{Empire, EmpireId};
+use crate::game::combat::Battle;
+use crate::game::knowledge::exploration;
+use crate::game::narrative::Narrative;
+use crate::game::sensors::detection;
+use crate::game::fleet::{Fleet, FleetId};
+use crate::game::galaxy::{Galaxy, SystemId};
+use crate::game::ship::{Ship, ShipRole};
+use crate::game::species::Species;
+use crate::game::order::Order;
+use crate::game::population::capacity;
+use crate::game::turn::{execute_order, ScheduledEvent, TurnReport};
+use crate::game::victory::{Outcome, VictoryConditions};
+
+/// Number of systems in a newly generated galaxy
+pub const GALAXY_SIZE: usize = 30;
+
+/// The whole simulation: everything that exists in a game, independent of how it is displayed
+#[derive(Clone)]
+pub struct GameState {
+    pub galaxy: Galaxy,
+    pub empires: Vec<Empire>,
+    pub fleets: Vec<Fleet>,
+    pub diplomacy: Diplomacy,
+    pub narrative: Narrative,
+    pub date: Date,
+    pub schedule: Schedule<ScheduledEvent>,
+    pub turn: u32,
+    /// Orders waiting to be carried out at the end of the turn
+    pub orders: Vec<(EmpireId, Order)>,
+    pub last_report: Option<TurnReport>,
+    /// Entries collected since the last turn was processed
+    pub pending_report: TurnReport,
+    /// The latest battles, oldest first
+    pub battles: Vec<Battle>,
+    pub clock: Clock,
+    /// Empire controlled by the player
+    pub player: EmpireId,
+    /// Seed the game was generated from
+    pub seed: u64,
+    pub victory: VictoryConditions,
+    /// How the game ended, `None` while it goes on
+    pub outcome: Option<Outcome>,
+    rng: StdRng,
+}
+
+impl GameState {
+    pub fn new(seed: u64) -> Self {
+        Self::with_time_mode(seed, TimeMode::Turns)
+    }
+
+    pub fn with_time_mode(seed: u64, time_mode: TimeMode) -> Self {
+        Self::with_setup(seed, time_mode, &Setup::default())
+    }
+
+    pub fn with_setup(seed: u64, time_mode: TimeMode, setup: &Setup) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut galaxy = Galaxy::generate(GALAXY_SIZE, &mut rng);
+
+        let species = Species::generate(&mut rng);
+        let capital = galaxy.systems.iter()
+            .position(|s| suits_homeworld(s, &species))
+            .or_else(|| galaxy.systems.iter().position(|s| homeworld_candidate(s, &species).is_some()))
+            .unwrap_or(0);
+        let mut empires = vec![Empire::new(0, "Terran Federation", Color::LightBlue, capital)];
+        empires[0].species = species;
+        for (name, color) in EMPIRES.iter().take(setup.opponents) {
+            let taken: Vec<SystemId> = empires.iter().map(|e| e.capital).collect();
+            let species = Species::generate(&mut rng);
+            let Some(capital) = remote_system(&galaxy, &taken, &species) else { break };
+            let personality = setup.personality.as_deref()
+                .and_then(personality)
+                .unwrap_or_else(|| &PERSONALITIES[rng.gen_range(0..PERSONALITIES.len())]);
+            let mut empire = Empire::new(empires.len(), name, *color, capital);
+            empire.species = species;
+            empire.ai = Some(Ai::new(personality, setup.difficulty));
+            empires.push(empire);
+        }
+        let mut fleets = Vec::new();
+        for empire in empires.iter_mut() {
+            let founded = found_empire(&mut galaxy, empire, fleets.len());
+            fleets.extend(founded);
+        }
+
+        let mut res = Self {
+            galaxy,
+            empires,
+            fleets,
+            diplomacy: Diplomacy::default(),
+            narrative: Narrative::default(),
+            date: Date::default(),
+            schedule: Schedule::default(),
+            turn: 0,
+            orders: Vec::new(),
+            last_report: None,
+            pending_report: TurnReport::default(),
+            clock: Clock::new(time_mode),
+            player: 0,
+            seed,
+            victory: setup.victory.clone(),
+            outcome: None,
+            battles: Vec::new(),
+            rng,
+        };
+        // What the empires see from where they start
+        exploration(&mut res, 0, &mut TurnReport::default());
+        detection(&mut res, &mut TurnReport::default());
+        res
+    }
+
+    /// Random number generator of the game. All randomness of the simulation has to come from
+    /// here, so that a game is reproducible from its seed
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    pub fn issue_order(&mut self, empire: EmpireId, order: Order) {
+        if order.is_immediate() && empire != self.player {
+            execute_order(self, empire, order, &mut TurnReport::default());
+        } else if order.is_immediate() {
+            let mut report = std::mem::take(&mut self.pending_report);
+            execute_order(self, empire, order, &mut report);
+            self.pending_report = report;
+        } else {
+            self.orders.push((empire, order));
+        }
+    }
+
+    /// Whether fleets of the two empires fight when they meet
+    pub fn hostile(&self, a: EmpireId, b: EmpireId) -> bool {
+        a != b && self.diplomacy.stance(a, b) == Stance::War
+    }
+
+    /// Whether the empire knows where the fleet is: one of its own, or one its sensors picked up
+    pub fn sees_fleet(&self, empire: EmpireId, fleet: &Fleet) -> bool {
+        fleet.owner == empire || self.empires[empire].knowledge.is_detected(fleet.id)
+    }
+
+    /// Disbands the fleets that have no ships left
+    pub fn remove_empty_fleets(&mut self) {
+        self.fleets.retain(|f| !f.ships.is_empty());
+    }
+
+    /// Id for a new fleet
+    pub fn next_fleet_id(&self) -> FleetId {
+        self.fleets.iter().map(|f| f.id + 1).max().unwrap_or(0)
+    }
+
+    pub fn player_empire(&self) -> &Empire {
+        &self.empires[self.player]
+    }
+
+    /// Takes a read-only snapshot of the current state
+    pub fn view(&self) -> GameView {
+        GameView(Arc::new(self.clone()))
+    }
+}
+
+/// How a new game is set up
+#[derive(Debug, Clone, Default)]
+pub struct Setup {
+    /// Number of empires the computer plays against the player
+    pub opponents: usize,
+    pub difficulty: Difficulty,
+    /// Id of the personality of every computer empire, a random one for each if `None`
+    pub personality: Option<String>,
+    pub victory: VictoryConditions,
+}
+
+/// Settles the homeworld of a new empire and gives it its starting fleets, which get ids from
+/// `first_fleet` on
+fn found_empire(galaxy: &mut Galaxy, empire: &mut Empire, first_fleet: FleetId) -> Vec<Fleet> {
+    let capital = empire.capital;
+    let homeworld = homeworld_candidate(&galaxy.systems[capital], &empire.species);
+    if let Some(body) = homeworld {
+        let body = galaxy.systems[capital].body_mut(body).unwrap();
+        let mut colony = Colony::new(empire.id, Date::default());
+        colony.buildings = HOMEWORLD_BUILDINGS.map(String::from).to_vec();
+        while colony.population < HOMEWORLD_POPULATION.min(capacity(body, &empire.species)) {
+            colony.add_pop();
+        }
+        body.colony = Some(colony);
+    }
+    let mut home_fleet = Fleet::new(first_fleet, "Home Fleet", empire.id, capital);
+    home_fleet.orbit = homeworld.unwrap_or(0);
+    let warship = empire.design_for(ShipRole::Warship).expect("there is a starting warship design");
+    let colony_ship = empire.design_for(ShipRole::ColonyShip).expect("there is a starting colony ship design");
+    home_fleet.ships = vec![
+        Ship::new("Vigilant", warship),
+        Ship::new("Resolute", warship),
+        Ship::new("Mayflower", colony_ship),
+    ];
+    let scout = empire.design_for(ShipRole::Survey).expect("there is a starting survey ship design");
+    let mut survey_fleet = Fleet::new(first_fleet + 1, "Survey Corps", empire.id, capital);
+    survey_fleet.orbit = home_fleet.orbit;
+    survey_fleet.ships = vec![Ship::new("Wayfarer", scout)];
+    empire.ships_built = (home_fleet.ships.len() + survey_fleet.ships.len()) as u32;
+    empire.knowledge.chart(capital, &galaxy.systems[capital], 0);
+    vec![home_fleet, survey_fleet]
+}
+
+/// The free system fit for a homeworld of the species that lies furthest from the taken ones
+fn remote_system(galaxy: &Galaxy, taken: &[SystemId], species: &Species) -> Option<SystemId> {
+    let free = |s: &SystemId| !taken.contains(s) && stable_star(&galaxy.systems[*s]);
+    let mut candidates: Vec<SystemId> = (0..galaxy.systems.len())
+        .filter(|s| free(s) && suits_homeworld(&galaxy.systems[*s], species))
+        .collect();
+    if candidates.is_empty() {
+        candidates = (0..galaxy.systems.len())
+            .filter(|s| free(s) && homeworld_candidate(&galaxy.systems[*s], species).is_some())
+            .collect();
+    }
+    let nearest = |s: SystemId| taken.iter().map(|t| galaxy.distance(s, *t)).fold(f32::INFINITY, f32::min);
+    candidates.into_iter().max_by(|a, b| nearest(*a).total_cmp(&nearest(*b)))
+}
+
+/// Read-only snapshot of a [`GameState`], which is handed out to the UI through the action channel
+#[derive(Clone)]
+pub struct GameView(Arc<GameState>);
+
+impl Deref for GameView {
+    type Target = GameState;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl PartialEq for GameView {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for GameView {}
+
+impl Debug for GameView {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GameView").field("date", &self.date).finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_galaxy() {
+        let a = GameState::new(11);
+        let b = GameState::new(11);
+        let names = |s: &GameState| s.galaxy.systems.iter().map(|s| s.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&a), names(&b));
+        assert_eq!(a.galaxy.hyperlanes, b.galaxy.hyperlanes);
+    }
+
+    #[test]
+    fn test_galaxy_connected() {
+        let state = GameState::new(5);
+        let mut reached = vec![0];
+        let mut i = 0;
+        while i < reached.len() {
+            for n in state.galaxy.neighbours(reached[i]) {
+                if !reached.contains(&n) {
+                    reached.push(n);
+                }
+            }
+            i += 1;
+        }
+        assert_eq!(reached.len(), state.galaxy.systems.len());
+    }
+
+    #[test]
+    fn test_view_is_a_snapshot() {
+        let mut state = GameState::new(0);
+        let view = state.view();
+        state.date.advance(30);
+        assert_eq!(view.date, Date::default());
+        assert_eq!(view, view.clone());
+        assert_ne!(view, state.view());
+    }
+}
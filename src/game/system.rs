@@ -14,21 +14,41 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::game::body::{Body, StarType};
-use rand::Rng;
+use std::ops::RangeInclusive;
+use crate::game::body::{Body, BodyId, BodyType, PlanetZone, StarType};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use crate::game::body::PlanetZone::{HabitableZone, InnerRing, OuterRing};
+use crate::game::evolution::generate_age;
+use crate::game::empire::EmpireId;
+use crate::game::hazard::SystemModifiers;
+use crate::game::territory::Outpost;
 
 #[derive(Clone)]
 pub struct System {
     pub bodies: Vec<Body>,
     pub star: Body,
     pub name: String,
+    pub in_nebula: bool,
+    /// Age of the star in years
+    pub star_age: f64,
+    /// Whether a warning about the upcoming change of the star's stage was already given
+    pub evolution_warned: bool,
+    pub outpost: Option<Outpost>,
 }
 
 impl System {
     pub fn generate() -> Self {
-        let mut rng = rand::thread_rng();
-        let mut star = Body::generate_star();
+        Self::generate_with_rng(&mut rand::thread_rng())
+    }
+
+    /// Generates a system deterministically, so the same seed always yields the same system
+    pub fn from_seed(seed: u64) -> Self {
+        Self::generate_with_rng(&mut StdRng::seed_from_u64(seed))
+    }
+
+    pub fn generate_with_rng<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let star = Body::generate_star(rng);
 
         let star_roche_limit = star.radius * 1.6;
 
@@ -38,23 +58,23 @@ impl System {
 
         match body_count {
             1..=3 => {
-                planets.append(&mut Body::generate_planet_with_count(&HabitableZone, 1));
-                planets.append(&mut Body::generate_planet_with_count(&OuterRing, body_count - 1));
+                planets.append(&mut Body::generate_planet_with_count(&HabitableZone, 1, rng));
+                planets.append(&mut Body::generate_planet_with_count(&OuterRing, body_count - 1, rng));
             }
             4..=5 => {
-                planets.append(&mut Body::generate_planet_with_count(&InnerRing, 1));
-                planets.append(&mut Body::generate_planet_with_count(&HabitableZone, 1));
-                planets.append(&mut Body::generate_planet_with_count(&OuterRing, body_count - 2));
+                planets.append(&mut Body::generate_planet_with_count(&InnerRing, 1, rng));
+                planets.append(&mut Body::generate_planet_with_count(&HabitableZone, 1, rng));
+                planets.append(&mut Body::generate_planet_with_count(&OuterRing, body_count - 2, rng));
             }
             6..=7 => {
-                planets.append(&mut Body::generate_planet_with_count(&InnerRing, 1));
-                planets.append(&mut Body::generate_planet_with_count(&HabitableZone, 2));
-                planets.append(&mut Body::generate_planet_with_count(&OuterRing, body_count - 3));
+                planets.append(&mut Body::generate_planet_with_count(&InnerRing, 1, rng));
+                planets.append(&mut Body::generate_planet_with_count(&HabitableZone, 2, rng));
+                planets.append(&mut Body::generate_planet_with_count(&OuterRing, body_count - 3, rng));
             }
             8..=10 => {
-                planets.append(&mut Body::generate_planet_with_count(&InnerRing, 2));
-                planets.append(&mut Body::generate_planet_with_count(&HabitableZone, 2));
-                planets.append(&mut Body::generate_planet_with_count(&OuterRing, body_count - 4));
+                planets.append(&mut Body::generate_planet_with_count(&InnerRing, 2, rng));
+                planets.append(&mut Body::generate_planet_with_count(&HabitableZone, 2, rng));
+                planets.append(&mut Body::generate_planet_with_count(&OuterRing, body_count - 4, rng));
             }
 
             _ => unreachable!()
@@ -63,15 +83,163 @@ impl System {
         let mut bodies = Vec::new();
         
         for mut planet in planets {
-            planet.orbit_radius = Some(rng.gen_range(2.9..=20.0) * 10.0f32.powi(10) + star_roche_limit);
-            planet.orbit_period = Some(f32::sqrt(planet.orbit_radius.unwrap().powi(2) / 7.5));
+            let zone = planet.zone.clone().unwrap_or(OuterRing);
+            planet.orbit_radius = Some(rng.gen_range(zone.orbit_bounds()) + star_roche_limit);
             bodies.push(planet);
         }
 
+        bodies.sort_by(|a, b| a.orbit_radius.partial_cmp(&b.orbit_radius).unwrap());
+        // The star is always body 0
+        for (i, body) in bodies.iter_mut().enumerate() {
+            body.id = i + 1;
+        }
+
+        let in_nebula = rng.gen_ratio(1, 8);
+
+        let star_age = match &star.kind {
+            BodyType::Star(class) => { generate_age(class, star.mass, rng) }
+            _ => { 0.0 }
+        };
+
         System {
-            bodies: bodies.clone(),
+            bodies,
             star,
             name: "Not solar system".to_string(),
+            in_nebula,
+            star_age,
+            evolution_warned: false,
+            outpost: None,
         }
     }
-}
\ No newline at end of file
+
+    /// Range of orbit radii belonging to a zone. Zones move outwards as the star swells into a
+    /// red giant, and collapse together with it
+    pub fn zone_bounds(&self, zone: &PlanetZone) -> RangeInclusive<f32> {
+        let scale = match &self.star.kind {
+            BodyType::Star(StarType::RedGiant) => { 3.0 }
+            BodyType::Star(StarType::WhiteDwarf) => { 0.1 }
+            BodyType::Star(StarType::Pulsar | StarType::BlackHole) => { 0.0 }
+            _ => { 1.0 }
+        };
+        let roche_limit = self.star.radius * 1.6;
+        let bounds = zone.orbit_bounds();
+        (bounds.start() * scale + roche_limit)..=(bounds.end() * scale + roche_limit)
+    }
+
+    /// Zone a body orbiting at the given radius currently belongs to
+    pub fn zone_at(&self, orbit_radius: f32) -> PlanetZone {
+        for zone in [InnerRing, HabitableZone] {
+            if orbit_radius <= *self.zone_bounds(&zone).end() {
+                return zone;
+            }
+        }
+        OuterRing
+    }
+
+    pub fn body(&self, id: BodyId) -> Option<&Body> {
+        std::iter::once(&self.star).chain(self.bodies.iter()).find(|b| b.id == id)
+    }
+
+    pub fn body_mut(&mut self, id: BodyId) -> Option<&mut Body> {
+        std::iter::once(&mut self.star).chain(self.bodies.iter_mut()).find(|b| b.id == id)
+    }
+
+    /// The empire that claims the system, with a colony or an outpost
+    pub fn owner(&self) -> Option<EmpireId> {
+        self.bodies.iter()
+            .find_map(|b| b.colony.as_ref().map(|c| c.owner))
+            .or(self.outpost.as_ref().map(|o| o.owner))
+    }
+
+    /// Whether the empire has a colony or an outpost in the system
+    pub fn is_held_by(&self, empire: EmpireId) -> bool {
+        self.outpost.as_ref().is_some_and(|o| o.owner == empire)
+            || self.bodies.iter().any(|b| b.colony.as_ref().is_some_and(|c| c.owner == empire))
+    }
+
+    /// Environmental effects of this system, derived from its star and surroundings
+    pub fn modifiers(&self) -> SystemModifiers {
+        SystemModifiers::for_system(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const SEEDS: u64 = 500;
+
+    /// Golden file for [`test_snapshot_seed_42`]. Regenerate it with
+    /// `UPDATE_SNAPSHOTS=1 cargo test` after an intentional change to the generators
+    const SNAPSHOT_SEED_42: &str = include_str!("snapshots/system_seed_42.txt");
+
+    fn describe(system: &System) -> String {
+        let mut res = vec![format!("System: {}", system.name)];
+        res.push(format!("In nebula: {}", system.in_nebula));
+        res.extend(system.evolution_info());
+        res.extend(system.modifiers().make_info());
+        for body in std::iter::once(&system.star).chain(system.bodies.iter()) {
+            res.push(String::new());
+            res.extend(body.make_info());
+            res.push(format!("Orbit period: {:.1} days", body.orbit_days().unwrap_or(0.0)));
+        }
+        res.join("\n") + "\n"
+    }
+
+    #[test]
+    fn test_orbits_within_zone_bounds() {
+        for seed in 0..SEEDS {
+            let system = System::from_seed(seed);
+            let roche_limit = system.star.radius * 1.6;
+            for body in &system.bodies {
+                let zone = body.zone.clone().expect("planets always have a zone");
+                let bounds = zone.orbit_bounds();
+                let orbit = body.orbit_radius.unwrap() - roche_limit;
+                // Allow for the precision lost when adding the Roche limit back
+                let epsilon = orbit.abs() * 1e-5;
+                assert!(
+                    orbit >= bounds.start() - epsilon && orbit <= bounds.end() + epsilon,
+                    "seed {seed}: {} orbits at {orbit:e}, outside of {zone:?}", body.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_periods_monotonic_with_radius() {
+        for seed in 0..SEEDS {
+            let system = System::from_seed(seed);
+            for pair in system.bodies.windows(2) {
+                assert!(pair[0].orbit_radius <= pair[1].orbit_radius, "seed {seed}: bodies not sorted");
+                assert!(pair[0].orbit_days() <= pair[1].orbit_days(), "seed {seed}: periods not monotonic");
+            }
+        }
+    }
+
+    #[test]
+    fn test_body_count() {
+        for seed in 0..SEEDS {
+            let system = System::from_seed(seed);
+            assert!((1..=10).contains(&system.bodies.len()), "seed {seed}");
+            assert!(system.star.orbit_radius.is_none());
+        }
+    }
+
+    #[test]
+    fn test_same_seed_same_system() {
+        assert_eq!(describe(&System::from_seed(7)), describe(&System::from_seed(7)));
+    }
+
+    #[test]
+    fn test_snapshot_seed_42() {
+        let actual = describe(&System::from_seed(42));
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            let path = concat!(env!("CARGO_MANIFEST_DIR"), "/src/game/snapshots/system_seed_42.txt");
+            std::fs::write(path, &actual).unwrap();
+            return;
+        }
+        assert_eq!(SNAPSHOT_SEED_42, actual);
+    }
+}
@@ -0,0 +1,369 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use strum::Display;
+use crate::game::empire::EmpireId;
+use crate::game::population::Job;
+use crate::game::resources::Resource;
+use crate::game::state::GameState;
+use crate::game::turn::{EventKind, Phase, TurnReport};
+
+const TECHNOLOGIES_DATA: &str = include_str!("../../assets/technologies.json5");
+
+lazy_static! {
+    /// Every technology defined in the data files
+    pub static ref TECHNOLOGIES: Vec<TechDef> =
+        json5::from_str(TECHNOLOGIES_DATA).expect("technology definitions are valid");
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Deserialize)]
+pub enum Category {
+    Physics,
+    Society,
+    Engineering,
+}
+
+/// A lasting bonus granted by a technology or a trait of a species
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum Modifier {
+    /// Workers of the job produce a share more
+    JobOutput { job: Job, bonus: f32 },
+    /// Upkeep of buildings changes by a share
+    BuildingUpkeep { bonus: f32 },
+    /// Population grows faster by a share
+    Growth { bonus: f32 },
+    /// Habitability of every habitable planet changes by the amount
+    Habitability { bonus: f32 },
+}
+
+impl Modifier {
+    pub fn describe(&self) -> String {
+        match self {
+            Modifier::JobOutput { job, bonus } => { format!("{job} output {:+.0}%", bonus * 100.0) }
+            Modifier::BuildingUpkeep { bonus } => { format!("Building upkeep {:+.0}%", bonus * 100.0) }
+            Modifier::Growth { bonus } => { format!("Population growth {:+.0}%", bonus * 100.0) }
+            Modifier::Habitability { bonus } => { format!("Habitability {:+.0}%", bonus * 100.0) }
+        }
+    }
+}
+
+/// Total bonus to the output of a job
+pub fn job_bonus(modifiers: &[Modifier], job: Job) -> f32 {
+    modifiers.iter()
+        .map(|m| match m {
+            Modifier::JobOutput { job: j, bonus } if *j == job => { *bonus }
+            _ => { 0.0 }
+        })
+        .sum()
+}
+
+/// Total change of building upkeep
+pub fn upkeep_bonus(modifiers: &[Modifier]) -> f32 {
+    modifiers.iter()
+        .map(|m| match m {
+            Modifier::BuildingUpkeep { bonus } => { *bonus }
+            _ => { 0.0 }
+        })
+        .sum()
+}
+
+/// Total change of population growth
+pub fn growth_bonus(modifiers: &[Modifier]) -> f32 {
+    modifiers.iter()
+        .map(|m| match m {
+            Modifier::Growth { bonus } => { *bonus }
+            _ => { 0.0 }
+        })
+        .sum()
+}
+
+/// Total change of the habitability of planets
+pub fn habitability_bonus(modifiers: &[Modifier]) -> f32 {
+    modifiers.iter()
+        .map(|m| match m {
+            Modifier::Habitability { bonus } => { *bonus }
+            _ => { 0.0 }
+        })
+        .sum()
+}
+
+/// A technology, as defined in the data files
+#[derive(Debug, Clone, Deserialize)]
+pub struct TechDef {
+    pub id: String,
+    pub name: String,
+    pub category: Category,
+    /// Research points needed
+    pub cost: f32,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub prerequisites: Vec<String>,
+    /// Ids of the buildings it unlocks
+    #[serde(default)]
+    pub buildings: Vec<String>,
+    /// Ids of the ship components it unlocks
+    #[serde(default)]
+    pub components: Vec<String>,
+    #[serde(default)]
+    pub modifiers: Vec<Modifier>,
+}
+
+impl TechDef {
+    /// Length of the longest chain of prerequisites leading to the technology
+    pub fn tier(&self) -> usize {
+        self.prerequisites.iter()
+            .filter_map(|p| tech(p))
+            .map(|p| p.tier() + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Technologies that have this one as a prerequisite
+    pub fn leads_to(&self) -> Vec<&'static TechDef> {
+        TECHNOLOGIES.iter().filter(|t| t.prerequisites.contains(&self.id)).collect()
+    }
+
+    /// Everything the technology unlocks, in words
+    pub fn describe_unlocks(&self) -> Vec<String> {
+        let mut res: Vec<String> = Vec::new();
+        res.extend(self.buildings.iter().map(|b| format!("Building: {b}")));
+        res.extend(self.components.iter().map(|c| format!("Component: {c}")));
+        res.extend(self.modifiers.iter().map(|m| m.describe()));
+        res
+    }
+}
+
+pub fn tech(id: &str) -> Option<&'static TechDef> {
+    TECHNOLOGIES.iter().find(|t| t.id == id)
+}
+
+/// Whether something unlocked by technologies still needs one the empire does not know
+fn locked_by<F: Fn(&TechDef) -> bool>(research: &Research, unlocks: F) -> bool {
+    TECHNOLOGIES.iter().any(|t| unlocks(t) && !research.knows(&t.id))
+}
+
+/// Research progress of an empire
+#[derive(Debug, Clone, Default)]
+pub struct Research {
+    /// Ids of the researched technologies, in the order they were researched
+    pub known: Vec<String>,
+    /// Ids of the technologies to research, the first one is being researched
+    pub queue: Vec<String>,
+    /// Research points put into the first technology of the queue
+    pub progress: f32,
+}
+
+impl Research {
+    pub fn knows(&self, id: &str) -> bool {
+        self.known.iter().any(|k| k == id)
+    }
+
+    pub fn is_queued(&self, id: &str) -> bool {
+        self.queue.iter().any(|q| q == id)
+    }
+
+    /// Whether every prerequisite of the technology is researched
+    pub fn can_research(&self, def: &TechDef) -> bool {
+        !self.knows(&def.id) && def.prerequisites.iter().all(|p| self.knows(p))
+    }
+
+    pub fn building_unlocked(&self, building: &str) -> bool {
+        !locked_by(self, |t| t.buildings.iter().any(|b| b == building))
+    }
+
+    pub fn component_unlocked(&self, component: &str) -> bool {
+        !locked_by(self, |t| t.components.iter().any(|c| c == component))
+    }
+
+    /// Modifiers of every researched technology
+    pub fn modifiers(&self) -> Vec<Modifier> {
+        self.known.iter()
+            .filter_map(|id| tech(id))
+            .flat_map(|t| t.modifiers.iter().copied())
+            .collect()
+    }
+
+    /// Adds the technology to the end of the queue, together with any prerequisites that are
+    /// neither researched nor queued yet
+    pub fn enqueue(&mut self, id: &str) -> Result<(), String> {
+        let Some(def) = tech(id) else {
+            return Err(format!("There is no such technology as {id}"));
+        };
+        if self.knows(id) {
+            return Err(format!("{} is already researched", def.name));
+        }
+        if self.is_queued(id) {
+            return Ok(());
+        }
+        for prerequisite in &def.prerequisites {
+            if !self.knows(prerequisite) {
+                self.enqueue(prerequisite)?;
+            }
+        }
+        self.queue.push(def.id.clone());
+        Ok(())
+    }
+
+    /// Removes the technology from the queue, along with everything queued that needs it
+    pub fn dequeue(&mut self, id: &str) {
+        let Some(index) = self.queue.iter().position(|q| q == id) else { return };
+        if index == 0 {
+            self.progress = 0.0;
+        }
+        self.queue.remove(index);
+        let dependants: Vec<String> = self.queue.iter()
+            .filter(|q| tech(q).is_some_and(|t| t.prerequisites.iter().any(|p| p == id)))
+            .cloned()
+            .collect();
+        for dependant in dependants {
+            self.dequeue(&dependant);
+        }
+    }
+
+    /// Puts research points into the queue. Returns the technologies that were completed
+    pub fn advance(&mut self, points: f32) -> Vec<&'static TechDef> {
+        let mut res = Vec::new();
+        self.progress += points;
+        while let Some(def) = self.queue.first().and_then(|id| tech(id)) {
+            if self.progress < def.cost {
+                break;
+            }
+            self.progress -= def.cost;
+            self.queue.remove(0);
+            self.known.push(def.id.clone());
+            res.push(def);
+        }
+        if self.queue.is_empty() {
+            self.progress = 0.0;
+        }
+        res
+    }
+
+    /// Turns until the queued technology is researched at the given research income
+    pub fn turns_until(&self, id: &str, income: f32) -> Option<u32> {
+        let index = self.queue.iter().position(|q| q == id)?;
+        if income <= 0.0 {
+            return None;
+        }
+        let needed: f32 = self.queue[..=index].iter().filter_map(|q| tech(q)).map(|t| t.cost).sum();
+        Some(((needed - self.progress) / income).ceil().max(1.0) as u32)
+    }
+}
+
+/// Puts the research points every empire gathered into its research queue. Points of empires with
+/// nothing queued stay in the stockpile
+pub fn research(state: &mut GameState, report: &mut TurnReport) {
+    let player = state.player;
+    for empire in state.empires.iter_mut() {
+        if empire.research.queue.is_empty() {
+            continue;
+        }
+        let points = empire.stockpile.get(Resource::Research);
+        empire.stockpile.set(Resource::Research, 0.0);
+        for def in empire.research.advance(points).into_iter().filter(|_| empire.id == player) {
+            report.add(Phase::Research, EventKind::ResearchCompleted, format!(
+                "{} researched {}",
+                empire.name,
+                def.name
+            ));
+        }
+    }
+}
+
+/// Adds a technology to the research queue of an empire
+pub fn queue_research(state: &mut GameState, empire: EmpireId, id: &str) -> Result<(), String> {
+    state.empires[empire].research.enqueue(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::building::BUILDINGS;
+    use crate::game::turn::end_turn;
+
+    use super::*;
+
+    #[test]
+    fn test_definitions() {
+        assert!(!TECHNOLOGIES.is_empty());
+        for def in TECHNOLOGIES.iter() {
+            assert!(def.cost > 0.0, "{} is free", def.id);
+            for prerequisite in &def.prerequisites {
+                assert!(tech(prerequisite).is_some(), "{} needs unknown {prerequisite}", def.id);
+            }
+            for building in &def.buildings {
+                assert!(BUILDINGS.iter().any(|b| b.id == *building), "{} unlocks unknown {building}", def.id);
+            }
+            // Also guards against cycles, which would never end
+            assert!(def.tier() < TECHNOLOGIES.len());
+        }
+    }
+
+    #[test]
+    fn test_enqueue_adds_prerequisites() {
+        let mut research = Research::default();
+        research.enqueue("cognitive_networks").unwrap();
+        let position = |id: &str| research.queue.iter().position(|q| q == id).unwrap();
+        assert!(position("applied_research") < position("efficient_bureaucracy"));
+        assert!(position("advanced_sensors") < position("cognitive_networks"));
+        assert_eq!(research.queue.last().unwrap(), "cognitive_networks");
+        assert!(research.enqueue("no_such_tech").is_err());
+
+        research.dequeue("applied_research");
+        assert!(!research.is_queued("cognitive_networks"));
+    }
+
+    #[test]
+    fn test_advance_carries_over() {
+        let mut research = Research::default();
+        research.enqueue("hydroponics").unwrap();
+        research.enqueue("metallurgy").unwrap();
+        assert!(research.advance(30.0).is_empty());
+        let done = research.advance(50.0);
+        assert_eq!(done.len(), 1);
+        assert!(research.knows("hydroponics"));
+        assert_eq!(research.progress, 20.0);
+        assert_eq!(research.turns_until("metallurgy", 10.0), Some(6));
+    }
+
+    #[test]
+    fn test_unlocks() {
+        let mut research = Research::default();
+        assert!(research.building_unlocked("farm"));
+        assert!(!research.building_unlocked("shipyard"));
+        research.known.push("orbital_construction".to_owned());
+        assert!(research.building_unlocked("shipyard"));
+
+        research.known.push("hydroponics".to_owned());
+        assert_eq!(job_bonus(&research.modifiers(), Job::Farmer), 0.25);
+        assert_eq!(job_bonus(&research.modifiers(), Job::Miner), 0.0);
+    }
+
+    #[test]
+    fn test_research_each_turn() {
+        let mut state = GameState::new(8);
+        let player = state.player;
+        queue_research(&mut state, player, "hydroponics").unwrap();
+        let mut completed = false;
+        for _ in 0..30 {
+            completed |= end_turn(&mut state).entries.iter().any(|e| e.kind == EventKind::ResearchCompleted);
+        }
+        assert!(completed);
+        assert!(state.player_empire().research.knows("hydroponics"));
+    }
+}
@@ -0,0 +1,311 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::game::calendar::Date;
+use crate::game::empire::EmpireId;
+use crate::game::fleet::FleetId;
+use crate::game::galaxy::SystemId;
+use crate::game::knowledge::KnowledgeLevel;
+use crate::game::resources::{Resource, Resources};
+use crate::game::state::GameState;
+use crate::game::system::System;
+use crate::game::turn::{EventKind, Phase, TurnReport};
+
+/// Id of the building that turns a colony into a starbase
+pub const STARBASE: &str = "starbase";
+
+/// Influence the first claim of an empire costs
+pub const CLAIM_COST: f32 = 10.0;
+
+/// Influence every system an empire already owns adds to the cost of a claim
+pub const CLAIM_COST_PER_SYSTEM: f32 = 2.0;
+
+/// Light years beyond its system an outpost pushes the borders of its empire
+const OUTPOST_RANGE: f32 = 6.0;
+
+/// Light years a colony claims around its system, before its population is counted
+const COLONY_RANGE: f32 = 8.0;
+
+const RANGE_PER_POP: f32 = 0.5;
+
+/// Light years a starbase adds to the claim of its colony
+const STARBASE_RANGE: f32 = 8.0;
+
+/// A station of an empire in a system it has no colony in, which claims the system
+#[derive(Clone, Debug, PartialEq)]
+pub struct Outpost {
+    pub owner: EmpireId,
+    pub built: Date,
+}
+
+/// The area around an owned system its empire lays claim to
+#[derive(Clone, Debug, PartialEq)]
+pub struct Claim {
+    pub owner: EmpireId,
+    pub system: SystemId,
+    pub position: (f32, f32),
+    /// Light years
+    pub range: f32,
+}
+
+impl Claim {
+    /// Light years the claim reaches past the position, negative when it falls short
+    pub fn strength_at(&self, position: (f32, f32)) -> f32 {
+        let distance = ((position.0 - self.position.0).powi(2) + (position.1 - self.position.1).powi(2)).sqrt();
+        self.range - distance
+    }
+
+    /// Whether the areas of the two claims touch
+    pub fn touches(&self, other: &Claim) -> bool {
+        self.strength_at(other.position) + other.range >= 0.0
+    }
+}
+
+/// Light years the owner of a system claims around it: the most of what its outpost and its
+/// colonies claim
+pub fn claim_range(system: &System) -> f32 {
+    let outpost = if system.outpost.is_some() { OUTPOST_RANGE } else { 0.0 };
+    system.bodies.iter()
+        .filter_map(|b| b.colony.as_ref())
+        .map(|c| {
+            let starbase = if c.buildings.iter().any(|b| b == STARBASE) { STARBASE_RANGE } else { 0.0 };
+            COLONY_RANGE + c.population as f32 * RANGE_PER_POP + starbase
+        })
+        .fold(outpost, f32::max)
+}
+
+/// Claims of the owned systems among the given ones
+pub fn claims<'a>(systems: impl Iterator<Item = (SystemId, &'a System)>, positions: &[(f32, f32)]) -> Vec<Claim> {
+    systems
+        .filter_map(|(id, system)| system.owner().map(|owner| Claim {
+            owner,
+            system: id,
+            position: positions[id],
+            range: claim_range(system),
+        }))
+        .collect()
+}
+
+/// The empire whose borders the position lies within. Where claims overlap the one reaching
+/// furthest past the position wins
+pub fn owner_at(claims: &[Claim], position: (f32, f32)) -> Option<EmpireId> {
+    claims.iter()
+        .map(|c| (c, c.strength_at(position)))
+        .filter(|(_, strength)| *strength >= 0.0)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(c, _)| c.owner)
+}
+
+/// The empire whose borders every system of the galaxy lies within
+pub fn territory(state: &GameState) -> Vec<Option<EmpireId>> {
+    let claims = claims(state.galaxy.systems.iter().enumerate(), &state.galaxy.positions);
+    state.galaxy.positions.iter().map(|p| owner_at(&claims, *p)).collect()
+}
+
+/// Claims of the empires as far as the given one knows them, from the systems as it last saw them
+pub fn known_claims(state: &GameState, empire: EmpireId) -> Vec<Claim> {
+    let mut systems: Vec<(SystemId, System)> = state.empires[empire].knowledge.systems()
+        .map(|(id, known)| (id, known.known_system()))
+        .collect();
+    systems.sort_by_key(|(id, _)| *id);
+    claims(systems.iter().map(|(id, s)| (*id, s)), &state.galaxy.positions)
+}
+
+/// The empire whose borders every system of the galaxy lies within, as far as the given empire
+/// knows
+pub fn known_territory(state: &GameState, empire: EmpireId) -> Vec<Option<EmpireId>> {
+    let claims = known_claims(state, empire);
+    state.galaxy.positions.iter().map(|p| owner_at(&claims, *p)).collect()
+}
+
+/// Works out anew which systems every empire holds, dropping those whose colonies died out or
+/// were destroyed
+pub fn update_systems(state: &mut GameState) {
+    for empire in state.empires.iter_mut() {
+        empire.systems = state.galaxy.systems.iter().enumerate()
+            .filter(|(_, s)| s.is_held_by(empire.id))
+            .map(|(id, _)| id)
+            .collect();
+    }
+}
+
+/// Influence the next claim of an empire costs
+pub fn claim_cost(state: &GameState, empire: EmpireId) -> f32 {
+    CLAIM_COST + CLAIM_COST_PER_SYSTEM * state.empires[empire].systems.len() as f32
+}
+
+/// Builds an outpost with a fleet of the empire in the system it is in, claiming the system for
+/// influence. Returns a message for the report, or why the system can not be claimed
+pub fn build_outpost(state: &mut GameState, empire: EmpireId, fleet: FleetId) -> Result<String, String> {
+    let Some(fleet) = state.fleets.iter().find(|f| f.id == fleet && f.owner == empire) else {
+        return Err("The fleet no longer exists".to_owned());
+    };
+    let Some(id) = fleet.system() else {
+        return Err(format!("{} can only build an outpost in a system", fleet.name));
+    };
+    let system = &state.galaxy.systems[id];
+    if state.empires[empire].knowledge.level(id) < KnowledgeLevel::Explored {
+        return Err(format!("{} has to be explored before it is claimed", system.name));
+    }
+    if let Some(owner) = system.owner() {
+        return Err(format!("{} is already claimed by {}", system.name, state.empires[owner].name));
+    }
+    if let Some(owner) = territory(state)[id].filter(|o| *o != empire) {
+        return Err(format!("{} lies within the borders of {}", system.name, state.empires[owner].name));
+    }
+    let cost = claim_cost(state, empire);
+    let owner = &mut state.empires[empire];
+    if !owner.stockpile.covers(&Resources::from_pairs(&[(Resource::Influence, cost)])) {
+        return Err(format!("Claiming {} takes {cost} influence", system.name));
+    }
+    owner.stockpile.add_amount(Resource::Influence, -cost);
+    if !owner.systems.contains(&id) {
+        owner.systems.push(id);
+    }
+
+    let name = system.name.clone();
+    state.galaxy.systems[id].outpost = Some(Outpost { owner: empire, built: state.date });
+    Ok(format!("{} built an outpost in {name}", state.empires[empire].name))
+}
+
+/// Finds empires whose borders touched for the first time. Both learn of each other, and the
+/// player hears of its first contacts
+pub fn borders(state: &mut GameState, report: &mut TurnReport) {
+    let claims = claims(state.galaxy.systems.iter().enumerate(), &state.galaxy.positions);
+    for (i, a) in claims.iter().enumerate() {
+        for b in &claims[i + 1..] {
+            if a.owner == b.owner || !a.touches(b) || state.empires[a.owner].contacts.contains(&b.owner) {
+                continue;
+            }
+            state.empires[a.owner].contacts.push(b.owner);
+            state.empires[b.owner].contacts.push(a.owner);
+            let text = format!(
+                "{} and {} met at {}",
+                state.empires[a.owner].name,
+                state.empires[b.owner].name,
+                state.galaxy.systems[b.system].name
+            );
+            state.diplomacy.record(state.turn, a.owner, b.owner, text);
+            for (us, them) in [(a, b), (b, a)] {
+                if us.owner == state.player {
+                    report.add(Phase::Growth, EventKind::FirstContact, format!(
+                        "First contact: our borders met those of {} at {}",
+                        state.empires[them.owner].name,
+                        state.galaxy.systems[them.system].name
+                    ));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::style::Color;
+    use crate::game::colony::Colony;
+    use crate::game::empire::Empire;
+    use crate::game::knowledge::exploration;
+    use super::*;
+
+    /// The system nearest to the capital of the player
+    fn nearest(state: &GameState) -> SystemId {
+        let capital = state.player_empire().capital;
+        *state.galaxy.neighbours(capital).iter()
+            .min_by(|a, b| state.galaxy.distance(capital, **a).total_cmp(&state.galaxy.distance(capital, **b)))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_owner_at() {
+        let claim = |owner, position| Claim { owner, system: owner, position, range: 10.0 };
+        let claims = [claim(0, (0.0, 0.0)), claim(1, (15.0, 0.0))];
+        assert_eq!(owner_at(&claims, (4.0, 0.0)), Some(0));
+        assert_eq!(owner_at(&claims, (11.0, 0.0)), Some(1));
+        assert_eq!(owner_at(&claims, (30.0, 0.0)), None);
+        assert!(claims[0].touches(&claims[1]));
+        assert!(!claims[0].touches(&claim(1, (25.0, 0.0))));
+    }
+
+    #[test]
+    fn test_build_outpost() {
+        let mut state = GameState::new(12);
+        let (player, fleet) = (state.player, state.fleets[0].id);
+        let capital = state.player_empire().capital;
+        assert!(build_outpost(&mut state, player, fleet).is_err());
+
+        let target = nearest(&state);
+        state.fleets[0].location = target;
+        assert!(build_outpost(&mut state, player, fleet).is_err());
+        exploration(&mut state, 0, &mut TurnReport::default());
+        let influence = state.player_empire().stockpile.get(Resource::Influence);
+        let cost = claim_cost(&state, player);
+        assert_eq!(cost, CLAIM_COST + CLAIM_COST_PER_SYSTEM);
+        assert!(build_outpost(&mut state, player, fleet).is_ok());
+        assert_eq!(state.player_empire().stockpile.get(Resource::Influence), influence - cost);
+        assert_eq!(state.player_empire().systems, vec![capital, target]);
+        assert_eq!(state.galaxy.systems[target].owner(), Some(player));
+        assert_eq!(territory(&state)[target], Some(player));
+        assert!(build_outpost(&mut state, player, fleet).is_err());
+
+        // Claims have to be paid for
+        let next = *state.galaxy.neighbours(target).iter().find(|s| state.galaxy.systems[**s].owner().is_none()).unwrap();
+        state.fleets[0].location = next;
+        exploration(&mut state, 0, &mut TurnReport::default());
+        state.empires[player].stockpile.add_amount(Resource::Influence, -influence);
+        assert!(build_outpost(&mut state, player, fleet).is_err());
+        assert_eq!(state.galaxy.systems[next].owner(), None);
+    }
+
+    #[test]
+    fn test_known_territory() {
+        let mut state = GameState::new(14);
+        let player = state.player;
+        let target = nearest(&state);
+        state.empires.push(Empire::new(1, "Rival", Color::Red, target));
+        state.galaxy.systems[target].outpost = Some(Outpost { owner: 1, built: state.date });
+        assert_eq!(territory(&state)[target], Some(1));
+        // The outpost was built after the player last looked
+        assert_ne!(known_territory(&state, player)[target], Some(1));
+
+        state.fleets[0].location = target;
+        exploration(&mut state, 0, &mut TurnReport::default());
+        assert_eq!(known_territory(&state, player)[target], Some(1));
+    }
+
+    #[test]
+    fn test_first_contact() {
+        let mut state = GameState::new(13);
+        let target = nearest(&state);
+        state.empires.push(Empire::new(1, "Rival", Color::Red, target));
+        state.galaxy.systems[target].outpost = Some(Outpost { owner: 1, built: state.date });
+        // A settled system claims more than an outpost
+        let before = claim_range(&state.galaxy.systems[target]);
+        let mut colony = Colony::new(1, state.date);
+        colony.population = 10;
+        state.galaxy.systems[target].bodies[0].colony = Some(colony);
+        assert!(claim_range(&state.galaxy.systems[target]) > before);
+
+        let mut report = TurnReport::default();
+        borders(&mut state, &mut report);
+        assert_eq!(state.empires[0].contacts, vec![1]);
+        assert_eq!(state.empires[1].contacts, vec![0]);
+        assert_eq!(report.entries.len(), 1);
+        assert!(matches!(report.entries[0].kind, EventKind::FirstContact));
+        // Contact is only made once
+        borders(&mut state, &mut report);
+        assert_eq!(report.entries.len(), 1);
+    }
+}
@@ -0,0 +1,648 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use crate::game::building::{cancel_construction, construction, queue_building};
+use crate::game::body::Body;
+use crate::game::calendar::{DAYS_PER_MONTH, DAYS_PER_YEAR};
+use crate::game::colony::{found_colony, start_colonization};
+use crate::game::combat::combat;
+use crate::game::design::{delete_design, save_design};
+use crate::game::diplomacy::{answer_proposal, propose, relations};
+use crate::game::economy::compute_ledger;
+use crate::game::empire::EmpireId;
+use crate::game::evolution::StellarEvent;
+use crate::game::fleet::{cancel_course, movement, plot_course, plot_transfer};
+use crate::game::galaxy::{BodyRef, SystemId};
+use crate::game::hazard::{HazardEvent, SHIELD_RECHARGE};
+use crate::game::knowledge::{exploration, KnowledgeLevel};
+use crate::game::narrative::{choose, follow_up, random_events, trigger, Trigger};
+use crate::game::ai::ai_turn;
+use crate::game::sensors::detection;
+use crate::game::territory::{borders, build_outpost};
+use crate::game::order::Order;
+use crate::game::population::{assign_jobs, growth, migrate};
+use crate::game::shipyard::{cancel_ship, queue_ship, refit_fleet, return_refits, shipyards};
+use crate::game::state::GameState;
+use crate::game::tech::{queue_research, research};
+use crate::game::victory::standings;
+
+/// In-game days that pass with every turn
+pub const DAYS_PER_TURN: u64 = DAYS_PER_MONTH;
+
+/// Steps of a turn, in the order they are processed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum Phase {
+    Orders,
+    Movement,
+    Combat,
+    Production,
+    Growth,
+    Research,
+    Diplomacy,
+    Events,
+    /// Statistics are recorded and the game is checked for victory and defeat
+    Standings,
+    Ai,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 10] = [
+        Phase::Orders,
+        Phase::Movement,
+        Phase::Combat,
+        Phase::Production,
+        Phase::Growth,
+        Phase::Research,
+        Phase::Diplomacy,
+        Phase::Events,
+        Phase::Standings,
+        Phase::Ai,
+    ];
+}
+
+/// What a report entry is about, used to decide which of them pause a real-time game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Serialize, Deserialize)]
+pub enum EventKind {
+    FleetArrived,
+    SolarFlare,
+    /// A star is about to leave its current stage
+    StellarWarning,
+    /// A star became a red giant or a remnant
+    StellarChange,
+    /// A scheduled message came due
+    Notice,
+    /// An empire ran out of a resource
+    ResourceDeficit,
+    /// An order could not be carried out
+    OrderRejected,
+    ColonizationStarted,
+    ColonyFounded,
+    /// Colonists did not manage to found their colony, or a colony died out
+    ColonyLost,
+    /// An empire has no food left for its population
+    Starvation,
+    Migration,
+    ConstructionStarted,
+    BuildingCompleted,
+    ResearchCompleted,
+    ShipCompleted,
+    RefitCompleted,
+    Battle,
+    FleetDestroyed,
+    SystemExplored,
+    SurveyCompleted,
+    /// Sensors picked up a fleet of another empire
+    FleetDetected,
+    OutpostBuilt,
+    /// The borders of two empires met for the first time
+    FirstContact,
+    WarDeclared,
+    /// Another empire put a proposal to the player
+    ProposalReceived,
+    ProposalRejected,
+    /// Two empires agreed on a treaty or a new stance
+    TreatySigned,
+    TreatyCancelled,
+    /// An empire lost its last colony
+    EmpireEliminated,
+    /// The game was won or lost
+    GameOver,
+    /// A narrative event waits for the choice of the player
+    Narrative,
+    /// The player made a choice in a narrative event
+    ChoiceMade,
+}
+
+impl EventKind {
+    /// Whether the entry is about the dealings of two empires
+    pub fn is_diplomatic(&self) -> bool {
+        matches!(
+            self,
+            EventKind::WarDeclared | EventKind::ProposalReceived | EventKind::ProposalRejected
+                | EventKind::TreatySigned | EventKind::TreatyCancelled
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportEntry {
+    pub phase: Phase,
+    pub kind: EventKind,
+    pub text: String,
+}
+
+/// Everything that changed during a turn
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TurnReport {
+    pub turn: u32,
+    pub entries: Vec<ReportEntry>,
+}
+
+/// Something scheduled to happen on a later date
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScheduledEvent {
+    /// A message for the player, such as a reminder
+    Notice { text: String },
+    /// Colonists finished founding their colony
+    FoundColony { empire: EmpireId, target: BodyRef },
+    /// A narrative event follows up on a choice made in another
+    FollowUp { empire: EmpireId, event: String, at: BodyRef },
+}
+
+impl TurnReport {
+    pub fn add(&mut self, phase: Phase, kind: EventKind, text: String) {
+        self.entries.push(ReportEntry { phase, kind, text });
+    }
+}
+
+/// Advances the game by one turn, running every phase over the state in a fixed order. Given the
+/// same state and orders the outcome is always the same
+pub fn end_turn(state: &mut GameState) -> TurnReport {
+    // Outside of real time the date always sits at the start of a turn
+    let days = DAYS_PER_TURN - state.date.days() % DAYS_PER_TURN;
+    advance_days(state, days).pop().expect("a turn boundary is always reached")
+}
+
+/// Moves the date forward day by day, processing a turn every time a turn's worth of days has
+/// passed. Both turn-based and real-time games advance through here
+pub fn advance_days(state: &mut GameState, days: u64) -> Vec<TurnReport> {
+    let mut res = Vec::new();
+    for _ in 0..days {
+        state.date.advance(1);
+        run_day(state);
+        if state.date.days().is_multiple_of(DAYS_PER_TURN) {
+            res.push(process_turn(state));
+        }
+    }
+    res
+}
+
+/// Moves the fleets and carries out everything scheduled for the current date
+fn run_day(state: &mut GameState) {
+    let mut report = std::mem::take(&mut state.pending_report);
+    movement(state, 1, &mut report);
+    exploration(state, 1, &mut report);
+    state.pending_report = report;
+    for event in state.schedule.take_due(state.date) {
+        match event {
+            ScheduledEvent::Notice { text } => {
+                state.pending_report.add(Phase::Events, EventKind::Notice, text);
+            }
+            ScheduledEvent::FoundColony { empire, target } => {
+                let (kind, text) = match found_colony(state, empire, target) {
+                    Ok(text) => { (EventKind::ColonyFounded, text) }
+                    Err(text) => { (EventKind::ColonyLost, text) }
+                };
+                if empire == state.player {
+                    state.pending_report.add(Phase::Growth, kind, text);
+                }
+                if kind == EventKind::ColonyFounded {
+                    let mut report = std::mem::take(&mut state.pending_report);
+                    trigger(state, empire, Trigger::ColonyFounded, target, &mut report);
+                    state.pending_report = report;
+                }
+            }
+            ScheduledEvent::FollowUp { empire, event, at } => {
+                let mut report = std::mem::take(&mut state.pending_report);
+                follow_up(state, empire, &event, at, &mut report);
+                state.pending_report = report;
+            }
+        }
+    }
+}
+
+/// Runs every phase of a turn, without moving the date
+fn process_turn(state: &mut GameState) -> TurnReport {
+    state.turn += 1;
+
+    let mut report = std::mem::take(&mut state.pending_report);
+    report.turn = state.turn;
+    for phase in Phase::ALL {
+        run_phase(state, phase, &mut report);
+    }
+
+    state.last_report = Some(report.clone());
+    report
+}
+
+pub fn run_phase(state: &mut GameState, phase: Phase, report: &mut TurnReport) {
+    match phase {
+        Phase::Orders => { orders(state, report) }
+        // Fleets move day by day, see `run_day`, sensors sweep for them once a turn
+        Phase::Movement => { detection(state, report) }
+        Phase::Combat => { combat(state, report) }
+        Phase::Production => { production(state, report) }
+        Phase::Growth => {
+            growth(state, report);
+            borders(state, report);
+        }
+        Phase::Research => { research(state, report) }
+        Phase::Diplomacy => { relations(state, report) }
+        Phase::Events => {
+            events(state, report, DAYS_PER_TURN);
+            random_events(state, report);
+        }
+        Phase::Standings => { standings(state, report) }
+        Phase::Ai => { ai_turn(state) }
+    }
+}
+
+fn orders(state: &mut GameState, report: &mut TurnReport) {
+    let orders: Vec<(EmpireId, Order)> = state.orders.drain(..).collect();
+    for (empire, order) in orders {
+        // Other empires do not report to the player how their orders went, only their dealings
+        // with the player
+        if empire == state.player {
+            execute_order(state, empire, order, report);
+        } else {
+            let mut scratch = TurnReport::default();
+            execute_order(state, empire, order, &mut scratch);
+            report.entries.extend(scratch.entries.into_iter().filter(|e| e.kind.is_diplomatic()));
+        }
+    }
+}
+
+/// Carries out an order of an empire
+pub fn execute_order(state: &mut GameState, empire: EmpireId, order: Order, report: &mut TurnReport) {
+    match order {
+        Order::MoveFleet { fleet, destination } => {
+            if let Err(text) = plot_course(state, empire, fleet, destination, false) {
+                report.add(Phase::Orders, EventKind::OrderRejected, text);
+            }
+        }
+        Order::QueueMove { fleet, destination } => {
+            if let Err(text) = plot_course(state, empire, fleet, destination, true) {
+                report.add(Phase::Orders, EventKind::OrderRejected, text);
+            }
+        }
+        Order::CancelMove { fleet } => {
+            if let Err(text) = cancel_course(state, empire, fleet) {
+                report.add(Phase::Orders, EventKind::OrderRejected, text);
+            }
+        }
+        Order::MoveInSystem { fleet, body } => {
+            if let Err(text) = plot_transfer(state, empire, fleet, body) {
+                report.add(Phase::Orders, EventKind::OrderRejected, text);
+            }
+        }
+        Order::Colonize { fleet, target } => {
+            let (kind, text) = match start_colonization(state, empire, fleet, target) {
+                Ok(text) => { (EventKind::ColonizationStarted, text) }
+                Err(text) => { (EventKind::OrderRejected, text) }
+            };
+            report.add(Phase::Orders, kind, text);
+        }
+        Order::BuildOutpost { fleet } => {
+            let (kind, text) = match build_outpost(state, empire, fleet) {
+                Ok(text) => { (EventKind::OutpostBuilt, text) }
+                Err(text) => { (EventKind::OrderRejected, text) }
+            };
+            report.add(Phase::Orders, kind, text);
+        }
+        Order::Migrate { from, to, population } => {
+            let (kind, text) = match migrate(state, empire, from, to, population) {
+                Ok(text) => { (EventKind::Migration, text) }
+                Err(text) => { (EventKind::OrderRejected, text) }
+            };
+            report.add(Phase::Orders, kind, text);
+        }
+        Order::Build { colony, building } => {
+            let (kind, text) = match queue_building(state, empire, colony, &building) {
+                Ok(text) => { (EventKind::ConstructionStarted, text) }
+                Err(text) => { (EventKind::OrderRejected, text) }
+            };
+            report.add(Phase::Orders, kind, text);
+        }
+        Order::CancelConstruction { colony, index } => {
+            let (kind, text) = match cancel_construction(state, empire, colony, index) {
+                Ok(text) => { (EventKind::Notice, text) }
+                Err(text) => { (EventKind::OrderRejected, text) }
+            };
+            report.add(Phase::Orders, kind, text);
+        }
+        Order::BuildShip { colony, design } => {
+            let (kind, text) = match queue_ship(state, empire, colony, &design) {
+                Ok(text) => { (EventKind::ConstructionStarted, text) }
+                Err(text) => { (EventKind::OrderRejected, text) }
+            };
+            report.add(Phase::Orders, kind, text);
+        }
+        Order::CancelShip { colony, index } => {
+            let (kind, text) = match cancel_ship(state, empire, colony, index) {
+                Ok(text) => { (EventKind::Notice, text) }
+                Err(text) => { (EventKind::OrderRejected, text) }
+            };
+            report.add(Phase::Orders, kind, text);
+        }
+        Order::RefitFleet { fleet, colony } => {
+            let (kind, text) = match refit_fleet(state, empire, fleet, colony) {
+                Ok(text) => { (EventKind::ConstructionStarted, text) }
+                Err(text) => { (EventKind::OrderRejected, text) }
+            };
+            report.add(Phase::Orders, kind, text);
+        }
+        Order::QueueResearch { tech } => {
+            if let Err(text) = queue_research(state, empire, &tech) {
+                report.add(Phase::Orders, EventKind::OrderRejected, text);
+            }
+        }
+        Order::DequeueResearch { tech } => {
+            state.empires[empire].research.dequeue(&tech);
+        }
+        Order::SaveDesign { design } => {
+            let (kind, text) = match save_design(state, empire, design) {
+                Ok(text) => { (EventKind::Notice, text) }
+                Err(text) => { (EventKind::OrderRejected, text) }
+            };
+            report.add(Phase::Orders, kind, text);
+        }
+        Order::DeleteDesign { name } => {
+            if let Err(text) = delete_design(state, empire, &name) {
+                report.add(Phase::Orders, EventKind::OrderRejected, text);
+            }
+        }
+        Order::AssignJobs { colony, workforce } => {
+            if let Err(text) = assign_jobs(state, empire, colony, workforce) {
+                report.add(Phase::Orders, EventKind::OrderRejected, text);
+            }
+        }
+        Order::Propose { to, proposal } => {
+            match propose(state, empire, to, proposal) {
+                Ok((kind, text)) if empire == state.player || to == state.player => {
+                    report.add(Phase::Orders, kind, text);
+                }
+                Ok(_) => {}
+                Err(text) => { report.add(Phase::Orders, EventKind::OrderRejected, text) }
+            }
+        }
+        Order::AnswerProposal { from, accept } => {
+            let (kind, text) = match answer_proposal(state, empire, from, accept) {
+                Ok(result) => { result }
+                Err(text) => { (EventKind::OrderRejected, text) }
+            };
+            report.add(Phase::Orders, kind, text);
+        }
+        Order::ResolveEvent { event, choice } => {
+            let (kind, text) = match choose(state, empire, &event, choice) {
+                Ok(text) => { (EventKind::ChoiceMade, text) }
+                Err(text) => { (EventKind::OrderRejected, text) }
+            };
+            report.add(Phase::Orders, kind, text);
+        }
+    }
+}
+
+fn production(state: &mut GameState, report: &mut TurnReport) {
+    construction(state, report);
+    for id in 0..state.empires.len() {
+        let mut ledger = compute_ledger(state, id);
+        shipyards(state, id, &mut ledger, report);
+        let empire = &mut state.empires[id];
+        let deficits = empire.apply_ledger(ledger);
+        for resource in deficits.into_iter().filter(|_| id == state.player) {
+            report.add(Phase::Production, EventKind::ResourceDeficit, format!(
+                "{} has run out of {}",
+                empire.name,
+                resource.to_string().to_lowercase()
+            ));
+        }
+    }
+}
+
+/// Environmental events of every system over the given number of days. Only those in systems the
+/// player knows of are reported
+pub fn events(state: &mut GameState, report: &mut TurnReport, days: u64) {
+    radiation(state, days);
+    let years = days as f64 / DAYS_PER_YEAR as f64;
+    for id in 0..state.galaxy.systems.len() {
+        let known = state.player_empire().knowledge.level(id) >= KnowledgeLevel::Detected;
+        let modifiers = state.galaxy.systems[id].modifiers();
+        let flares = modifiers.roll_events(days as u32, state.rng());
+        let system = &mut state.galaxy.systems[id];
+        for event in flares {
+            match event {
+                HazardEvent::SolarFlare { severity } => {
+                    let ships = state.fleets.iter_mut()
+                        .filter(|f| f.system() == Some(id))
+                        .flat_map(|f| f.ships.iter_mut());
+                    for ship in ships {
+                        ship.drain_shield(severity);
+                    }
+                    if known {
+                        report.add(Phase::Events, EventKind::SolarFlare, format!(
+                            "Solar flare in {} ({:.0}% shield loss)",
+                            system.name,
+                            severity * 100.0
+                        ));
+                    }
+                }
+            }
+        }
+
+        let (stellar, lost) = system.advance_age(years);
+        for event in stellar.into_iter().filter(|_| known) {
+            let kind = match event {
+                StellarEvent::Warning { .. } => { EventKind::StellarWarning }
+                _ => { EventKind::StellarChange }
+            };
+            report.add(Phase::Events, kind, format!("{}: {}", system.name, event.describe()));
+        }
+        destroyed(state, id, lost, report);
+    }
+}
+
+/// Settles what was on the bodies a star destroyed: their colonies are lost, ships in refit at
+/// their yards are handed back and fleets in orbit of them are left circling the star
+fn destroyed(state: &mut GameState, system: SystemId, bodies: Vec<Body>, report: &mut TurnReport) {
+    for body in bodies {
+        for fleet in state.fleets.iter_mut().filter(|f| f.location == system) {
+            if fleet.orbit == body.id {
+                fleet.orbit = 0;
+            }
+            if fleet.transfer.as_ref().is_some_and(|t| t.target == body.id) {
+                fleet.transfer = None;
+            }
+        }
+        let Some(colony) = body.colony else { continue };
+        if colony.owner == state.player {
+            report.add(Phase::Events, EventKind::ColonyLost, format!(
+                "The colony of {} on {} was destroyed by its star",
+                state.empires[colony.owner].name,
+                body.name
+            ));
+        }
+        return_refits(state, colony.owner, BodyRef { system, body: body.id }, colony.shipyard_queue);
+    }
+}
+
+/// Radiation wears down the shields of ships in irradiated systems, everywhere else they recharge
+fn radiation(state: &mut GameState, days: u64) {
+    let damage: Vec<f32> = state.galaxy.systems.iter().map(|s| s.modifiers().shield_damage).collect();
+    for fleet in state.fleets.iter_mut() {
+        let damage = fleet.system().map_or(0.0, |s| damage[s]);
+        for ship in fleet.ships.iter_mut() {
+            if damage > 0.0 {
+                ship.drain_shield(damage * days as f32);
+            } else {
+                ship.recharge_shield(SHIELD_RECHARGE * days as f32);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::evolution::STELLAR_TIME_SCALE;
+    use crate::game::knowledge::Knowledge;
+    use crate::game::resources::Resource;
+    use crate::game::ship::Ship;
+    use crate::game::shipyard::ShipConstruction;
+
+    use super::*;
+
+    #[test]
+    fn test_end_turn_advances_time() {
+        let mut state = GameState::new(0);
+        let report = end_turn(&mut state);
+        assert_eq!(report.turn, 1);
+        assert_eq!(state.turn, 1);
+        assert_eq!(state.date.days(), DAYS_PER_TURN);
+        assert_eq!(state.last_report, Some(report));
+    }
+
+    #[test]
+    fn test_turns_are_deterministic() {
+        let mut a = GameState::new(9);
+        let mut b = GameState::new(9);
+        for _ in 0..20 {
+            assert_eq!(end_turn(&mut a), end_turn(&mut b));
+        }
+    }
+
+    #[test]
+    fn test_real_time_matches_turns() {
+        let mut turns = GameState::new(4);
+        let mut real_time = GameState::new(4);
+        let by_turn: Vec<TurnReport> = (0..3).map(|_| end_turn(&mut turns)).collect();
+        let mut by_day = advance_days(&mut real_time, DAYS_PER_TURN * 3 - 1);
+        assert_eq!(by_day.len(), 2);
+        by_day.extend(advance_days(&mut real_time, 1));
+
+        assert_eq!(by_turn, by_day);
+        assert_eq!(turns.date, real_time.date);
+    }
+
+    #[test]
+    fn test_production_fills_stockpile() {
+        let mut state = GameState::new(3);
+        let before = state.player_empire().stockpile;
+        end_turn(&mut state);
+        let empire = state.player_empire();
+
+        assert_eq!(empire.stockpile, before + empire.ledger.net());
+        assert!(empire.ledger.income().get(Resource::Minerals) > 0.0);
+        assert!(empire.ledger.by_source().iter().any(|(s, _)| s == "Fleet upkeep"));
+        assert_eq!(empire.history.len(), 1);
+    }
+
+    #[test]
+    fn test_deficit_is_reported() {
+        let mut state = GameState::new(3);
+        state.empires[0].stockpile.set(Resource::Energy, 0.0);
+        for _ in 0..50 {
+            state.fleets.push(state.fleets[0].clone());
+        }
+        let report = end_turn(&mut state);
+
+        assert_eq!(state.player_empire().stockpile.get(Resource::Energy), 0.0);
+        assert!(report.entries.iter().any(|e| e.kind == EventKind::ResourceDeficit));
+    }
+
+    #[test]
+    fn test_scheduled_notice() {
+        let mut state = GameState::new(1);
+        let now = state.date;
+        state.schedule.after(now, 45, ScheduledEvent::Notice { text: "Reminder".to_owned() });
+
+        let first = end_turn(&mut state);
+        assert!(first.entries.iter().all(|e| e.kind != EventKind::Notice));
+        let second = end_turn(&mut state);
+        assert_eq!(second.entries.iter().filter(|e| e.kind == EventKind::Notice).count(), 1);
+    }
+
+    #[test]
+    fn test_move_fleet_order() {
+        let mut state = GameState::new(2);
+        let fleet = state.fleets[0].id;
+        let start = state.fleets[0].location;
+        let destination = state.galaxy.neighbours(start)[0];
+        state.issue_order(state.player, Order::MoveFleet { fleet, destination });
+        let eta = state.fleets[0].arrival(&state.galaxy).unwrap();
+        let turns = eta.div_ceil(DAYS_PER_TURN);
+        let reports: Vec<TurnReport> = (0..turns).map(|_| end_turn(&mut state)).collect();
+
+        assert_eq!(state.fleets[0].system(), Some(destination));
+        assert!(reports.last().unwrap().entries.iter().any(|e| e.phase == Phase::Movement));
+    }
+
+    #[test]
+    fn test_events_of_unknown_systems_not_reported() {
+        let mut state = GameState::new(3);
+        let id = state.galaxy.systems.iter()
+            .position(|s| s.modifiers().flare_chance > 0.0)
+            .expect("some system should have flares");
+        let player = state.player;
+        state.empires[player].knowledge = Knowledge::default();
+        let mut report = TurnReport::default();
+        events(&mut state, &mut report, 1000);
+        assert!(report.entries.iter().all(|e| e.phase != Phase::Events));
+
+        let system = state.galaxy.systems[id].clone();
+        state.empires[player].knowledge.chart(id, &system, 0);
+        events(&mut state, &mut report, 1000);
+        assert!(report.entries.iter().any(|e| e.kind == EventKind::SolarFlare));
+    }
+
+    #[test]
+    fn test_star_destroys_colony() {
+        let mut state = GameState::new(4);
+        let player = state.player;
+        let home = state.galaxy.colonies()
+            .find(|(_, b)| b.colony.as_ref().unwrap().owner == player)
+            .map(|(r, _)| r)
+            .unwrap();
+        let design = state.player_empire().designs[0].clone();
+        let old = Ship::new("Old", &design);
+        let body = state.galaxy.body_mut(home).unwrap();
+        body.colony.as_mut().unwrap().shipyard_queue.push(ShipConstruction::refit(old, &design));
+        // Close enough to the star to be engulfed once it swells
+        body.orbit_radius = Some(1.0);
+        let fleet = state.fleets.iter().position(|f| f.owner == player && f.orbit == home.body).unwrap();
+
+        // Bring the star to the end of its main sequence
+        let system = &mut state.galaxy.systems[home.system];
+        system.star_age += system.years_left().unwrap() * STELLAR_TIME_SCALE;
+        let mut report = TurnReport::default();
+        events(&mut state, &mut report, DAYS_PER_TURN);
+
+        assert!(state.galaxy.body(home).is_none());
+        assert!(report.entries.iter().any(|e| e.kind == EventKind::ColonyLost));
+        assert_eq!(state.fleets[fleet].orbit, 0);
+        assert!(state.fleets.iter().any(|f| f.ships.iter().any(|s| s.name == "Old")));
+    }
+}
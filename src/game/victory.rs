@@ -0,0 +1,401 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use strum::{Display, EnumString};
+use crate::game::colony::is_being_colonized;
+use crate::game::diplomacy::military;
+use crate::game::empire::EmpireId;
+use crate::game::state::GameState;
+use crate::game::tech::tech;
+use crate::game::territory::update_systems;
+use crate::game::turn::{EventKind, Phase, ScheduledEvent, TurnReport};
+
+/// Technology that wins a technology victory
+pub const ASCENDANCY: &str = "ascendancy";
+
+/// Points of the score for every unit of population
+const POPULATION_SCORE: f32 = 1.0;
+
+const COLONY_SCORE: f32 = 5.0;
+
+const SYSTEM_SCORE: f32 = 3.0;
+
+const TECHNOLOGY_SCORE: f32 = 10.0;
+
+/// Points of the score for every point of fleet strength
+const MILITARY_SCORE: f32 = 0.5;
+
+/// A way to win the game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum Victory {
+    /// Every other empire lost its last colony
+    Domination,
+    /// The empire has colonies in a share of the systems of the galaxy
+    Colonization,
+    /// The empire researched ascendancy, the last technology
+    Technology,
+    /// The empire has the best score once the turn limit is reached
+    Score,
+}
+
+impl Victory {
+    pub const ALL: [Victory; 4] = [Victory::Domination, Victory::Colonization, Victory::Technology, Victory::Score];
+}
+
+/// How a game can be won, chosen when it is set up
+#[derive(Debug, Clone, PartialEq)]
+pub struct VictoryConditions {
+    pub enabled: Vec<Victory>,
+    /// Percentage of the systems of the galaxy an empire has to colonise for a colonization victory
+    pub colonization: f32,
+    /// Turn after which the empire with the best score wins
+    pub turn_limit: u32,
+}
+
+impl Default for VictoryConditions {
+    fn default() -> Self {
+        Self {
+            enabled: Victory::ALL.to_vec(),
+            colonization: 50.0,
+            turn_limit: 300,
+        }
+    }
+}
+
+impl VictoryConditions {
+    pub fn allows(&self, victory: Victory) -> bool {
+        self.enabled.contains(&victory)
+    }
+}
+
+/// How a game ended
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Victory { empire: EmpireId, victory: Victory, turn: u32 },
+    /// The player lost its last colony
+    Defeat { turn: u32 },
+}
+
+impl Outcome {
+    pub fn winner(&self) -> Option<EmpireId> {
+        match self {
+            Outcome::Victory { empire, .. } => { Some(*empire) }
+            Outcome::Defeat { .. } => { None }
+        }
+    }
+
+    pub fn turn(&self) -> u32 {
+        match self {
+            Outcome::Victory { turn, .. } | Outcome::Defeat { turn } => { *turn }
+        }
+    }
+
+    pub fn describe(&self, state: &GameState) -> String {
+        match self {
+            Outcome::Victory { empire, victory, .. } => {
+                let name = &state.empires[*empire].name;
+                match victory {
+                    Victory::Domination => { format!("{name} is the last empire standing and rules the galaxy") }
+                    Victory::Colonization => { format!("{name} settled the galaxy, with colonies in {:.0}% of its systems", state.victory.colonization) }
+                    Victory::Technology => { format!("{name} researched ascendancy and mastered every secret of the universe") }
+                    Victory::Score => { format!("{name} has the best score after {} turns", state.victory.turn_limit) }
+                }
+            }
+            Outcome::Defeat { .. } => {
+                format!("{} lost its last colony", state.player_empire().name)
+            }
+        }
+    }
+}
+
+/// Where an empire stood at the end of a turn
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Statistics {
+    pub turn: u32,
+    pub population: u32,
+    pub colonies: usize,
+    pub systems: usize,
+    pub technologies: usize,
+    pub ships: usize,
+    /// Strength of all fleets
+    pub military: f32,
+    pub score: f32,
+}
+
+impl Statistics {
+    /// What every part of the empire adds to its score
+    pub fn score_breakdown(&self) -> Vec<(&'static str, f32)> {
+        vec![
+            ("Population", self.population as f32 * POPULATION_SCORE),
+            ("Colonies", self.colonies as f32 * COLONY_SCORE),
+            ("Systems", self.systems as f32 * SYSTEM_SCORE),
+            ("Technologies", self.technologies as f32 * TECHNOLOGY_SCORE),
+            ("Military", self.military * MILITARY_SCORE),
+        ]
+    }
+}
+
+/// Where the empire stands now
+pub fn statistics(state: &GameState, empire: EmpireId) -> Statistics {
+    let colonies: Vec<u32> = state.galaxy.colonies()
+        .filter_map(|(_, b)| b.colony.as_ref().filter(|c| c.owner == empire))
+        .map(|c| c.population)
+        .collect();
+    let owner = &state.empires[empire];
+    let mut res = Statistics {
+        turn: state.turn,
+        population: colonies.iter().sum(),
+        colonies: colonies.len(),
+        systems: owner.systems.len(),
+        technologies: owner.research.known.len(),
+        ships: state.fleets.iter().filter(|f| f.owner == empire).map(|f| f.ships.len()).sum(),
+        military: military(state, empire),
+        score: 0.0,
+    };
+    res.score = res.score_breakdown().iter().map(|(_, points)| points).sum();
+    res
+}
+
+/// Whether the empire has no colony left and none on the way
+pub fn is_eliminated(state: &GameState, empire: EmpireId) -> bool {
+    let founding = state.schedule.iter()
+        .any(|(_, event)| matches!(event, ScheduledEvent::FoundColony { empire: e, .. } if *e == empire));
+    !founding && !state.galaxy.colonies().any(|(_, b)| b.colony.as_ref().is_some_and(|c| c.owner == empire))
+}
+
+/// How far the empire is from every victory the game allows, in words
+pub fn progress(state: &GameState, empire: EmpireId) -> Vec<(Victory, String)> {
+    let conditions = &state.victory;
+    let owner = &state.empires[empire];
+    Victory::ALL.iter()
+        .filter(|v| conditions.allows(**v))
+        .map(|v| {
+            let text = match v {
+                Victory::Domination => {
+                    let rivals = state.empires.iter().filter(|e| e.id != empire && e.eliminated.is_none()).count();
+                    format!("{rivals} rival empire(s) remain")
+                }
+                Victory::Colonization => {
+                    let share = 100.0 * colonised_systems(state, empire) as f32 / state.galaxy.systems.len() as f32;
+                    format!("{share:.0}% of {:.0}% of the systems colonised", conditions.colonization)
+                }
+                Victory::Technology => {
+                    let research = &owner.research;
+                    let Some(def) = tech(ASCENDANCY) else { return (*v, "ascendancy is unknown".to_owned()) };
+                    let missing = def.prerequisites.iter().filter(|p| !research.knows(p)).count();
+                    if research.knows(ASCENDANCY) {
+                        "ascendancy researched".to_owned()
+                    } else if missing > 0 {
+                        format!("{missing} technologies to go before ascendancy")
+                    } else {
+                        let progress = if research.queue.first().is_some_and(|t| t == ASCENDANCY) { research.progress } else { 0.0 };
+                        format!("ascendancy researched to {progress:.0} of {:.0}", def.cost)
+                    }
+                }
+                Victory::Score => {
+                    let scores: Vec<f32> = state.empires.iter().map(|e| statistics(state, e.id).score).collect();
+                    let rank = 1 + scores.iter().filter(|s| **s > scores[empire]).count();
+                    format!("ranked {rank} of {} on turn {} of {}", scores.len(), state.turn, conditions.turn_limit)
+                }
+            };
+            (*v, text)
+        })
+        .collect()
+}
+
+/// Number of systems with a colony of the empire. Outposts claim systems without settling them,
+/// so they do not count
+fn colonised_systems(state: &GameState, empire: EmpireId) -> usize {
+    state.galaxy.systems.iter()
+        .filter(|s| s.bodies.iter().any(|b| b.colony.as_ref().is_some_and(|c| c.owner == empire)))
+        .count()
+}
+
+/// The way the game ends this turn, if it does
+fn outcome(state: &GameState) -> Option<Outcome> {
+    let turn = state.turn;
+    let conditions = &state.victory;
+    let player = state.player_empire();
+    if player.ai.is_none() && player.eliminated.is_some() {
+        return Some(Outcome::Defeat { turn });
+    }
+    let win = |empire: EmpireId, victory: Victory| Outcome::Victory { empire, victory, turn };
+    if conditions.allows(Victory::Technology) {
+        if let Some(empire) = state.empires.iter().find(|e| e.research.knows(ASCENDANCY)) {
+            return Some(win(empire.id, Victory::Technology));
+        }
+    }
+    if conditions.allows(Victory::Colonization) {
+        let needed = conditions.colonization / 100.0 * state.galaxy.systems.len() as f32;
+        let colonizer = state.empires.iter()
+            .find(|e| e.eliminated.is_none() && colonised_systems(state, e.id) as f32 >= needed);
+        if let Some(empire) = colonizer {
+            return Some(win(empire.id, Victory::Colonization));
+        }
+    }
+    let survivors: Vec<EmpireId> = state.empires.iter().filter(|e| e.eliminated.is_none()).map(|e| e.id).collect();
+    if conditions.allows(Victory::Domination) && state.empires.len() > 1 && survivors.len() == 1 {
+        return Some(win(survivors[0], Victory::Domination));
+    }
+    if conditions.allows(Victory::Score) && turn >= conditions.turn_limit {
+        // The first of the empires with the best score
+        let best = state.empires.iter()
+            .map(|e| (e.id, e.statistics.last().map_or(0.0, |s| s.score)))
+            .fold(None, |best: Option<(EmpireId, f32)>, (id, score)| match best {
+                Some((_, top)) if top >= score => { best }
+                _ => { Some((id, score)) }
+            });
+        return best.map(|(empire, _)| win(empire, Victory::Score));
+    }
+    None
+}
+
+/// Records where every empire stands, notes the empires that lost their last colony and checks
+/// whether the game is won or lost
+pub fn standings(state: &mut GameState, report: &mut TurnReport) {
+    let turn = state.turn;
+    update_systems(state);
+    for empire in 0..state.empires.len() {
+        let statistics = statistics(state, empire);
+        state.empires[empire].statistics.push(statistics);
+        if state.empires[empire].eliminated.is_none() && is_eliminated(state, empire) {
+            state.empires[empire].eliminated = Some(turn);
+            let player = state.player_empire();
+            if empire == player.id || player.contacts.contains(&empire) {
+                report.add(Phase::Standings, EventKind::EmpireEliminated, format!(
+                    "{} lost its last colony and is no more",
+                    state.empires[empire].name
+                ));
+            }
+        }
+    }
+    if state.outcome.is_none() {
+        if let Some(outcome) = outcome(state) {
+            report.add(Phase::Standings, EventKind::GameOver, outcome.describe(state));
+            state.outcome = Some(outcome);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::clock::TimeMode;
+    use crate::game::state::Setup;
+    use crate::game::territory::Outpost;
+    use crate::game::turn::end_turn;
+    use super::*;
+
+    fn game(victory: VictoryConditions) -> GameState {
+        GameState::with_setup(1, TimeMode::Turns, &Setup { opponents: 1, victory, ..Setup::default() })
+    }
+
+    fn abandon_colonies(state: &mut GameState, empire: EmpireId) {
+        for system in state.galaxy.systems.iter_mut() {
+            for body in system.bodies.iter_mut() {
+                if body.colony.as_ref().is_some_and(|c| c.owner == empire) {
+                    body.colony = None;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_statistics() {
+        let mut state = game(VictoryConditions::default());
+        let statistics = statistics(&state, 0);
+        assert_eq!((statistics.colonies, statistics.systems, statistics.technologies), (1, 1, 0));
+        assert!(statistics.ships > 0 && statistics.military > 0.0);
+        let total: f32 = statistics.score_breakdown().iter().map(|(_, points)| points).sum();
+        assert_eq!(statistics.score, total);
+
+        end_turn(&mut state);
+        end_turn(&mut state);
+        assert_eq!(state.empires[1].statistics.len(), 2);
+        assert_eq!(state.empires[1].statistics[1].turn, 2);
+        assert!(state.outcome.is_none());
+    }
+
+    #[test]
+    fn test_defeat() {
+        let mut state = game(VictoryConditions::default());
+        abandon_colonies(&mut state, 0);
+        let report = end_turn(&mut state);
+        assert_eq!(state.outcome, Some(Outcome::Defeat { turn: 1 }));
+        assert_eq!(state.empires[0].eliminated, Some(1));
+        assert!(report.entries.iter().any(|e| e.kind == EventKind::GameOver));
+    }
+
+    #[test]
+    fn test_domination() {
+        let mut state = game(VictoryConditions::default());
+        abandon_colonies(&mut state, 1);
+        end_turn(&mut state);
+        assert_eq!(state.outcome, Some(Outcome::Victory { empire: 0, victory: Victory::Domination, turn: 1 }));
+        assert!(state.empires[1].eliminated.is_some());
+
+        // Domination can be ruled out
+        let mut state = game(VictoryConditions { enabled: vec![Victory::Technology], ..VictoryConditions::default() });
+        abandon_colonies(&mut state, 1);
+        end_turn(&mut state);
+        assert!(state.outcome.is_none());
+    }
+
+    #[test]
+    fn test_lost_systems() {
+        // Systems whose colonies died out no longer count towards a colonization victory
+        let mut state = game(VictoryConditions { colonization: 50.0, ..VictoryConditions::default() });
+        abandon_colonies(&mut state, 1);
+        state.empires[1].systems = (0..state.galaxy.systems.len()).collect();
+        end_turn(&mut state);
+        assert!(state.empires[1].systems.is_empty());
+        assert_eq!(state.empires[1].statistics[0].systems, 0);
+        assert_eq!(state.outcome, Some(Outcome::Victory { empire: 0, victory: Victory::Domination, turn: 1 }));
+    }
+
+    #[test]
+    fn test_outposts_do_not_colonise() {
+        let mut state = game(VictoryConditions { colonization: 50.0, ..VictoryConditions::default() });
+        let built = state.date;
+        for system in state.galaxy.systems.iter_mut().filter(|s| s.owner().is_none()) {
+            system.outpost = Some(Outpost { owner: 0, built });
+        }
+        end_turn(&mut state);
+        assert!(state.empires[0].systems.len() > state.galaxy.systems.len() / 2);
+        assert!(state.outcome.is_none());
+    }
+
+    #[test]
+    fn test_victories() {
+        let mut state = game(VictoryConditions { colonization: 1.0, ..VictoryConditions::default() });
+        end_turn(&mut state);
+        assert_eq!(state.outcome.and_then(|o| o.winner()), Some(0));
+
+        let mut state = game(VictoryConditions::default());
+        state.empires[1].research.known.push(ASCENDANCY.to_owned());
+        end_turn(&mut state);
+        assert_eq!(state.outcome, Some(Outcome::Victory { empire: 1, victory: Victory::Technology, turn: 1 }));
+
+        let mut state = game(VictoryConditions { turn_limit: 3, ..VictoryConditions::default() });
+        end_turn(&mut state);
+        end_turn(&mut state);
+        assert!(state.outcome.is_none());
+        end_turn(&mut state);
+        let best = if state.empires[0].statistics[2].score >= state.empires[1].statistics[2].score { 0 } else { 1 };
+        assert_eq!(state.outcome, Some(Outcome::Victory { empire: best, victory: Victory::Score, turn: 3 }));
+        assert_eq!(progress(&state, 0).len(), Victory::ALL.len());
+    }
+}
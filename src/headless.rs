@@ -0,0 +1,87 @@
+// Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
+// Copyright (C) 2024 Egor Kosachev
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use color_eyre::eyre::Result;
+
+use crate::game::{
+  ai::{personality, Ai, PERSONALITIES},
+  clock::TimeMode,
+  state::{GameState, Setup},
+  turn::end_turn,
+};
+
+/// Turns between the standings printed during a headless game
+const STANDINGS_INTERVAL: u32 = 10;
+
+/// A line on every empire: what it owns, how many people and ships it has and what it researched
+pub fn standings(state: &GameState) -> Vec<String> {
+  state
+    .empires
+    .iter()
+    .map(|empire| {
+      let colonies: Vec<u32> = state
+        .galaxy
+        .colonies()
+        .filter_map(|(_, b)| b.colony.as_ref().filter(|c| c.owner == empire.id))
+        .map(|c| c.population)
+        .collect();
+      let ships: usize = state.fleets.iter().filter(|f| f.owner == empire.id).map(|f| f.ships.len()).sum();
+      let personality = empire.ai.as_ref().map_or("Player", |ai| ai.personality.name.as_str());
+      let score = empire.statistics.last().map_or(0.0, |s| s.score);
+      format!(
+        "{:<22} {:<14} systems {:>2}  colonies {:>2}  population {:>3}  ships {:>3}  technologies {:>2}  score {:>4.0}",
+        empire.name,
+        personality,
+        empire.systems.len(),
+        colonies.len(),
+        colonies.iter().sum::<u32>(),
+        ships,
+        empire.research.known.len(),
+        score,
+      )
+    })
+    .collect()
+}
+
+/// Plays a game with the computer in charge of every empire, printing the standings and the
+/// dealings of the empires as it goes, until the turns run out or the game is won.
+/// The same seed and setup always play out the same way
+pub fn run(seed: u64, setup: &Setup, turns: u32) -> Result<()> {
+  let mut state = GameState::with_setup(seed, TimeMode::Turns, setup);
+  let player = state.player;
+  let personality = setup.personality.as_deref().and_then(personality).unwrap_or(&PERSONALITIES[0]);
+  state.empires[player].ai = Some(Ai::new(personality, setup.difficulty));
+
+  println!("Seed {seed}, {} empires, {} difficulty", state.empires.len(), setup.difficulty);
+  for _ in 0..turns {
+    let known = state.diplomacy.history.len();
+    end_turn(&mut state);
+    for event in &state.diplomacy.history[known..] {
+      println!("Turn {}: {}", event.turn, event.text);
+    }
+    if state.turn.is_multiple_of(STANDINGS_INTERVAL) || state.turn == turns || state.outcome.is_some() {
+      println!("Turn {} ({})", state.turn, state.date);
+      for line in standings(&state) {
+        println!("  {line}");
+      }
+    }
+    if let Some(outcome) = state.outcome {
+      println!("Game over: {}", outcome.describe(&state));
+      break;
+    }
+  }
+  Ok(())
+}
@@ -1,4 +1,3 @@
-#![feature(exclusive_range_pattern)]
 // Ad Infinitum - a 4x strategy set in a procedurally generated galaxy
 // Copyright (C) 2024 Egor Kosachev
 //
@@ -24,6 +23,7 @@ pub mod app;
 pub mod cli;
 pub mod components;
 pub mod config;
+pub mod headless;
 pub mod mode;
 pub mod tui;
 pub mod utils;
@@ -44,7 +44,11 @@ async fn tokio_main() -> Result<()> {
   initialize_panic_handler()?;
 
   let args = Cli::parse();
-  let mut app = App::new(args.tick_rate, args.frame_rate)?;
+  let seed = args.seed.unwrap_or_else(rand::random);
+  if args.headless {
+    return headless::run(seed, &args.setup(), args.turns);
+  }
+  let mut app = App::new(args.tick_rate, args.frame_rate, seed, args.real_time, &args.setup())?;
   app.run().await?;
 
   Ok(())
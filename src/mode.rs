@@ -18,6 +18,23 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Mode {
+  /// The system view
   #[default]
   Home,
+  /// Overview of the player's empire
+  Empire,
+  /// Map of the whole galaxy
+  Galaxy,
+  /// Technology tree and research queue
+  Research,
+  /// Ship designer
+  Designer,
+  /// Relations with the other empires
+  Diplomacy,
+  /// How the empires developed and how close the player is to victory
+  Statistics,
+  /// How the game ended
+  Summary,
+  /// Everything that happened last turn
+  Report,
 }